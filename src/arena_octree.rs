@@ -0,0 +1,454 @@
+use crate::{
+    naive_octree::{ NaiveOctree, clip_tool_aabbs, tool_needs_subdivision },
+    tool::{ Tool, ToolFunc, Action, AABB, IntersectType::* },
+    marching_cubes::march_cube,
+    utils, UnindexedMesh, CUBE_CORNERS,
+};
+use glam::{ Vec3, UVec3 };
+use std::{ borrow::Borrow, ops::Range };
+
+/// The first child of a node without children. The root is nobody's
+/// child, so its ID is free to mean this.
+const LEAF: u32 = 0;
+
+/// The brick of a node without one.
+const NO_BRICK: u32 = u32::MAX;
+
+/// A Terrain with the same cells as a [NaiveOctree], stored as flat
+/// arrays indexed by node ID instead of a tree of boxed cells.
+///
+/// The topology and the corner values are kept in separate arrays, and
+/// the 8 children of a node always have consecutive IDs. Tools are applied
+/// one depth at a time, so the tool is evaluated and the [Action] blended
+/// in loops over contiguous corner values rather than while walking the
+/// tree. Only the terrain is stored, not fluid or materials.
+///
+/// With [`with_bricks`](Self::with_bricks), leaves below a depth store a
+/// dense grid of values instead of being subdivided further.
+#[derive(Debug, Clone)]
+pub struct ArenaOctree {
+    pub scale: f32,
+    pub origin: Vec3,
+    /// The ID of each node's first child, or [LEAF].
+    children: Vec<u32>,
+    /// The corner values of each node, in the order of [CUBE_CORNERS](crate::CUBE_CORNERS).
+    values: Vec<[f32; 8]>,
+    /// The first IDs of collapsed blocks of children, to be reused.
+    free: Vec<u32>,
+    /// The depth at which leaves get a brick instead of children.
+    brick_depth: Option<u8>,
+    /// The number of cells along each axis of a brick.
+    brick_resolution: u32,
+    /// The brick of each node, or [NO_BRICK].
+    bricks: Vec<u32>,
+    /// The point values of every brick, one brick after the other.
+    brick_values: Vec<f32>,
+    /// Bricks that were dropped, to be reused.
+    free_bricks: Vec<u32>,
+}
+
+impl ArenaOctree {
+    pub fn new(scale: f32) -> Self {
+        Self {
+            scale,
+            origin: Vec3::ZERO,
+            children: vec![LEAF],
+            values: vec![[-1.0; 8]],
+            free: Vec::new(),
+            brick_depth: None,
+            brick_resolution: 8,
+            bricks: vec![NO_BRICK],
+            brick_values: Vec::new(),
+            free_bricks: Vec::new(),
+        }
+    }
+
+    /// Makes leaves at `depth` that need more detail store a brick of
+    /// `resolution` cells along each axis, such as 8 or 16, instead of
+    /// being subdivided further. This should be set before any edits.
+    ///
+    /// A brick replaces the many tiny nodes near the surface with one
+    /// contiguous block of values, which is quicker to mesh. Its values are
+    /// laid out like a [DenseGrid](crate::DenseGrid), so it can be uploaded
+    /// to the GPU as it is. A brick is dropped once no surface is left in it.
+    pub fn with_bricks(mut self, depth: u8, resolution: u32) -> Self {
+        self.brick_depth = Some(depth);
+        self.brick_resolution = resolution.max(1);
+        self
+    }
+
+    /// The AABB covered by the Terrain.
+    pub fn aabb(&self) -> AABB {
+        AABB { start: self.origin, size: Vec3::splat(self.scale) }
+    }
+
+    /// The number of nodes in the tree, including the root.
+    pub fn cell_count(&self) -> usize {
+        self.children.len() - self.free.len() * 8
+    }
+
+    /// The corner values of every node, indexed by node ID. The root is
+    /// node 0. IDs freed by collapsing keep their old values until reused.
+    pub fn values(&self) -> &[[f32; 8]] {
+        &self.values
+    }
+
+    /// Returns the ID of the first of `node`'s 8 children, or [None] if
+    /// it has none.
+    pub fn children(&self, node: u32) -> Option<u32> {
+        Some(self.children[node as usize]).filter(|&first| first != LEAF)
+    }
+
+    /// Returns true if `node` has neither children nor a brick.
+    fn is_leaf(&self, node: u32) -> bool {
+        self.children[node as usize] == LEAF && self.bricks[node as usize] == NO_BRICK
+    }
+
+    /// The number of bricks in the tree.
+    pub fn brick_count(&self) -> usize {
+        self.brick_values.len() / self.brick_len() - self.free_bricks.len()
+    }
+
+    /// The point values of `node`'s brick, ordered by X, then Y, then Z,
+    /// or [None] if it has no brick. There are one more points along each
+    /// axis than the brick has cells.
+    pub fn brick(&self, node: u32) -> Option<&[f32]> {
+        let brick = self.bricks[node as usize];
+        (brick != NO_BRICK).then(|| &self.brick_values[self.brick_range(brick)])
+    }
+
+    /// The number of values in a brick.
+    fn brick_len(&self) -> usize {
+        (self.brick_resolution + 1).pow(3) as usize
+    }
+
+    fn brick_range(&self, brick: u32) -> Range<usize> {
+        brick as usize * self.brick_len()..(brick as usize + 1) * self.brick_len()
+    }
+
+    /// Gives `node` a brick interpolated from the corner values `values`.
+    fn alloc_brick(&mut self, node: u32, values: &[f32; 8]) {
+        let (resolution, dims) = (self.brick_resolution, self.brick_resolution + 1);
+        let points = (0..dims.pow(3)).map(|index| utils::trilinear(values, brick_point(index, dims).as_vec3() / resolution as f32));
+        let brick = if let Some(brick) = self.free_bricks.pop() {
+            let range = self.brick_range(brick);
+            self.brick_values[range].iter_mut().zip(points).for_each(|(value, point)| *value = point);
+            brick
+        }
+        else {
+            let brick = (self.brick_values.len() / self.brick_len()) as u32;
+            self.brick_values.extend(points);
+            brick
+        };
+        self.bricks[node as usize] = brick;
+    }
+
+    fn free_brick(&mut self, node: u32) {
+        let brick = std::mem::replace(&mut self.bricks[node as usize], NO_BRICK);
+        if brick != NO_BRICK {
+            self.free_bricks.push(brick);
+        }
+    }
+
+    /// Applies the [Tool] to every point of `node`'s brick, and returns
+    /// the values at its corners.
+    fn apply_brick<F: ToolFunc>(&mut self, node: u32, tool: &Tool<F>, action: Action, cell_aabb: AABB) -> [f32; 8] {
+        let (resolution, dims) = (self.brick_resolution, self.brick_resolution + 1);
+        let spacing = cell_aabb.size / resolution as f32;
+        let positions: Vec<Vec3> = (0..dims.pow(3)).map(|index| cell_aabb.start + brick_point(index, dims).as_vec3() * spacing).collect();
+        let mut toolvals = Vec::with_capacity(positions.len());
+        let mut chunks = positions.chunks_exact(8);
+        for chunk in &mut chunks {
+            toolvals.extend(tool.value8(chunk.try_into().unwrap()));
+        }
+        toolvals.extend(chunks.remainder().iter().map(|&pos| tool.value(pos)));
+
+        let weight = tool.weight();
+        let range = self.brick_range(self.bricks[node as usize]);
+        let values = &mut self.brick_values[range];
+        for (value, toolval) in values.iter_mut().zip(toolvals) {
+            action.apply_weighted(value, toolval, weight);
+        }
+        CUBE_CORNERS.map(|corner| values[brick_index(corner.as_uvec3() * resolution, dims)])
+    }
+
+    /// Stores 8 new leaves with consecutive IDs and returns the first.
+    fn alloc(&mut self, values: [[f32; 8]; 8]) -> u32 {
+        if let Some(first) = self.free.pop() {
+            let block = first as usize..first as usize + 8;
+            self.children[block.clone()].fill(LEAF);
+            self.bricks[block.clone()].fill(NO_BRICK);
+            self.values[block].copy_from_slice(&values);
+            first
+        }
+        else {
+            let first = self.children.len() as u32;
+            self.children.extend([LEAF; 8]);
+            self.bricks.extend([NO_BRICK; 8]);
+            self.values.extend(values);
+            first
+        }
+    }
+
+    /// Turns `node` into a leaf, freeing every node and brick below it.
+    fn collapse(&mut self, node: u32) {
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            self.free_brick(node);
+            let first = std::mem::replace(&mut self.children[node as usize], LEAF);
+            if first != LEAF {
+                self.free.push(first);
+                stack.extend(first..first + 8);
+            }
+        }
+    }
+
+    /// Applies the [Tool] to the Terrain with the given [Action].
+    /// Will subdivide the Terrain if needed up to `max_depth`, the same
+    /// as [`NaiveOctree::apply_tool`].
+    pub fn apply_tool<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action, max_depth: u8) {
+        let max_depth = max_depth.min(NaiveOctree::MAX_DEPTH);
+        let tool = tool.borrow();
+        let Some((tool_aabb, aoe_aabb)) = clip_tool_aabbs(self.aabb(), tool, action) else {
+            return;
+        };
+        let weight = tool.weight();
+        let reaches = |corners: &[Vec3; 8]| !matches!(aoe_aabb.intersect(AABB { start: corners[0], size: corners[7] - corners[0] }), DoesNotIntersect);
+
+        // The nodes of the current depth the tool reaches, and their corners
+        let root_corners = self.aabb().calculate_corners();
+        let (mut nodes, mut corners) = if reaches(&root_corners) { (vec![0], vec![root_corners]) } else { (Vec::new(), Vec::new()) };
+        // Every visited node by depth, to collapse them from the bottom up
+        let mut levels: Vec<Vec<u32>> = Vec::new();
+        let mut toolvals = Vec::new();
+        let mut newvals = Vec::new();
+        let mut depth = 0;
+        while !nodes.is_empty() {
+            toolvals.clear();
+            toolvals.extend(corners.iter().map(|corners| tool.value8(*corners)));
+            newvals.clear();
+            newvals.extend(nodes.iter().map(|&node| self.values[node as usize]));
+            for (values, toolvals) in newvals.iter_mut().zip(&toolvals) {
+                for (value, &toolval) in values.iter_mut().zip(toolvals) {
+                    action.apply_weighted(value, toolval, weight);
+                }
+            }
+
+            let mut next_nodes = Vec::new();
+            let mut next_corners = Vec::new();
+            for (index, &node) in nodes.iter().enumerate() {
+                // A convex tool covering every corner covers the whole node
+                if tool.is_convex() && weight >= 1.0 && toolvals[index].iter().all(|&val| val > 0.0) {
+                    self.collapse(node);
+                    self.values[node as usize] = newvals[index];
+                    continue;
+                }
+
+                let cell_aabb = AABB { start: corners[index][0], size: corners[index][7] - corners[index][0] };
+                if self.is_leaf(node) && depth < max_depth &&
+                    tool_needs_subdivision(tool, tool_aabb, aoe_aabb, action, cell_aabb, &newvals[index])
+                {
+                    // The children interpolate the values from before the edit
+                    let values = self.values[node as usize];
+                    if self.brick_depth.is_some_and(|brick_depth| depth >= brick_depth) {
+                        self.alloc_brick(node, &values);
+                    }
+                    else {
+                        let first = self.alloc(utils::subdivide_cell(&values));
+                        self.children[node as usize] = first;
+                    }
+                }
+
+                if self.bricks[node as usize] != NO_BRICK {
+                    self.values[node as usize] = self.apply_brick(node, tool, action, cell_aabb);
+                    if !self.brick(node).is_some_and(brick_has_surface) {
+                        self.free_brick(node);
+                    }
+                    continue;
+                }
+                self.values[node as usize] = newvals[index];
+
+                if let Some(first) = self.children(node) {
+                    for (child, corners) in (first..).zip(utils::subdivide_cell_positions(&corners[index])) {
+                        if reaches(&corners) {
+                            next_nodes.push(child);
+                            next_corners.push(corners);
+                        }
+                    }
+                }
+            }
+
+            levels.push(std::mem::replace(&mut nodes, next_nodes));
+            corners = next_corners;
+            depth += 1;
+        }
+
+        // Children that are all plain leaves without a surface are merged
+        for level in levels.iter().rev() {
+            for &node in level {
+                let Some(first) = self.children(node) else {
+                    continue;
+                };
+                let block = first as usize..first as usize + 8;
+                if (first..first + 8).all(|child| self.is_leaf(child)) &&
+                    !self.values[block].iter().any(crate::intersects_surface)
+                {
+                    self.children[node as usize] = LEAF;
+                    self.free.push(first);
+                }
+            }
+        }
+    }
+
+    /// Returns the leaf containing `pos` and its AABB.
+    fn leaf_containing(&self, pos: Vec3) -> (u32, AABB) {
+        let mut node = 0;
+        let mut aabb = self.aabb();
+        while let Some(first) = self.children(node) {
+            let center = aabb.center();
+            let index = (pos.x >= center.x) as u8 | ((pos.y >= center.y) as u8) << 1 | ((pos.z >= center.z) as u8) << 2;
+            node = first + index as u32;
+            aabb = aabb.octree_child(index);
+        }
+        (node, aabb)
+    }
+
+    /// Returns the value of the terrain at `pos`. Positions outside of
+    /// the terrain are empty.
+    pub fn sample(&self, pos: Vec3) -> f32 {
+        if !self.aabb().contains(pos) {
+            return -1.0;
+        }
+        let (node, aabb) = self.leaf_containing(pos);
+        let t = ((pos - aabb.start) / aabb.size).clamp(Vec3::ZERO, Vec3::ONE);
+        let Some(brick) = self.brick(node) else {
+            return utils::trilinear(&self.values[node as usize], t);
+        };
+        let local = t * self.brick_resolution as f32;
+        let cell = local.floor().as_uvec3().min(UVec3::splat(self.brick_resolution - 1));
+        utils::trilinear(&brick_cell_values(brick, cell, self.brick_resolution + 1), (local - cell.as_vec3()).clamp(Vec3::ZERO, Vec3::ONE))
+    }
+
+    /// Uses Marching Cubes to generate an [UnindexedMesh]. Without bricks,
+    /// the triangles come out in the same order as [`NaiveOctree::generate_mesh`].
+    /// Bricks are only meshed at full detail if they are above `max_depth`.
+    pub fn generate_mesh(&self, max_depth: u8) -> UnindexedMesh {
+        let mut faces = Vec::new();
+        let mut stack = vec![(0, self.aabb(), 0)];
+        while let Some((node, aabb, depth)) = stack.pop() {
+            match self.children(node) {
+                Some(first) if depth < max_depth => {
+                    stack.extend((first..first + 8).zip(aabb.octree_subdivide()).map(|(child, aabb)| (child, aabb, depth + 1)).rev());
+                },
+                _ => match self.brick(node).filter(|_| depth < max_depth) {
+                    Some(brick) => self.march_brick(brick, aabb, &mut faces),
+                    None => faces.extend(march_cube(&aabb.calculate_corners(), &self.values[node as usize])),
+                },
+            }
+        }
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+
+    /// Marches every cell of a brick covering `aabb`.
+    fn march_brick(&self, brick: &[f32], aabb: AABB, faces: &mut Vec<[Vec3; 3]>) {
+        let (resolution, dims) = (self.brick_resolution, self.brick_resolution + 1);
+        let spacing = aabb.size / resolution as f32;
+        for index in 0..resolution.pow(3) {
+            let cell = brick_point(index, resolution);
+            let corners = CUBE_CORNERS.map(|corner| aabb.start + (cell + corner.as_uvec3()).as_vec3() * spacing);
+            faces.extend(march_cube(&corners, &brick_cell_values(brick, cell, dims)));
+        }
+    }
+}
+
+/// The point at `index` of a box with `dims` points along each axis,
+/// ordered by X, then Y, then Z.
+fn brick_point(index: u32, dims: u32) -> UVec3 {
+    UVec3::new(index % dims, index / dims % dims, index / (dims * dims))
+}
+
+/// The index of `point` in a brick with `dims` points along each axis.
+fn brick_index(point: UVec3, dims: u32) -> usize {
+    (point.x + point.y * dims + point.z * dims * dims) as usize
+}
+
+/// The values at the corners of the brick cell starting at `cell`, in the
+/// order of [CUBE_CORNERS].
+fn brick_cell_values(brick: &[f32], cell: UVec3, dims: u32) -> [f32; 8] {
+    CUBE_CORNERS.map(|corner| brick[brick_index(cell + corner.as_uvec3(), dims)])
+}
+
+/// Returns true if some points of a brick are inside and some are outside.
+fn brick_has_surface(brick: &[f32]) -> bool {
+    brick.iter().any(|&value| value > 0.0) && brick.iter().any(|&value| value <= 0.0)
+}
+
+#[test]
+fn arena_octree_test() {
+    use crate::tool::{ Sphere, Cuboid, Weighted };
+    use glam::{ Vec3A, Quat };
+
+    let mut arena = ArenaOctree::new(16.0);
+    let mut octree = NaiveOctree::new(16.0);
+    let edits = [
+        (Tool::new(Weighted::new(Sphere, 1.0)).scaled(Vec3::splat(5.0)).translated(Vec3A::new(8.0, 7.5, 8.5)), Action::Place),
+        (Tool::new(Weighted::new(Sphere, 1.0)).scaled(Vec3::splat(3.0)).translated(Vec3A::new(11.0, 9.0, 8.0)), Action::Remove),
+        (Tool::new(Weighted::new(Sphere, 0.5)).scaled(Vec3::splat(2.0)).translated(Vec3A::new(4.0, 4.0, 12.0)), Action::Place),
+    ];
+    for (tool, action) in edits {
+        arena.apply_tool(tool, action, 5);
+        octree.apply_tool(tool, action, 5);
+    }
+    let cuboid = Tool::new(Cuboid).scaled(Vec3::new(3.0, 2.0, 4.0)).rotated(Quat::from_rotation_z(0.5)).translated(Vec3A::new(6.0, 12.0, 6.0));
+    arena.apply_tool(cuboid, Action::Place, 6);
+    octree.apply_tool(cuboid, Action::Place, 6);
+
+    // The same edits make the same cells
+    assert_eq!(arena.cell_count(), octree.cell_count());
+    assert_eq!(arena.generate_mesh(6).faces, octree.generate_mesh(6).faces);
+    assert_eq!(arena.generate_mesh(3).faces, octree.generate_mesh(3).faces);
+    for pos in [Vec3::new(8.3, 7.5, 13.2), Vec3::new(11.0, 9.0, 8.0), Vec3::new(6.5, 12.1, 5.0)] {
+        assert_eq!(arena.sample(pos), octree.sample(pos), "{pos}");
+    }
+
+    // Clearing the terrain frees every node, and they are reused
+    let nodes = arena.values().len();
+    let clear = Tool::new(Cuboid).scaled(Vec3::splat(20.0)).translated(Vec3A::splat(8.0));
+    arena.apply_tool(clear, Action::Remove, 6);
+    assert_eq!(arena.cell_count(), 1);
+    for (tool, action) in edits {
+        arena.apply_tool(tool, action, 5);
+    }
+    assert_eq!(arena.values().len(), nodes);
+}
+
+#[test]
+fn arena_brick_test() {
+    use crate::tool::{ Sphere, Cuboid };
+    use glam::Vec3A;
+
+    // Bricks of 8 cells at depth 2 are as detailed as cells at depth 5
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::new(8.0, 7.5, 8.5));
+    let mut arena = ArenaOctree::new(16.0).with_bricks(2, 8);
+    arena.apply_tool(tool, Action::Place, 5);
+    let mut octree = NaiveOctree::new(16.0);
+    octree.apply_tool(tool, Action::Place, 5);
+
+    assert!(arena.brick_count() > 0);
+    assert!(arena.cell_count() < octree.cell_count() / 10);
+    assert_eq!(arena.generate_mesh(5).faces.len(), octree.generate_mesh(5).faces.len());
+    for pos in [Vec3::new(8.3, 7.5, 13.2), Vec3::new(3.1, 7.7, 8.4), Vec3::new(8.0, 2.6, 8.5)] {
+        assert!((arena.sample(pos) - octree.sample(pos)).abs() < 1e-5, "{pos}");
+    }
+    let node = (0..arena.values().len() as u32).find(|&node| arena.brick(node).is_some()).unwrap();
+    assert_eq!(arena.brick(node).unwrap().len(), 9 * 9 * 9);
+
+    // Bricks left without a surface are dropped
+    let clear = Tool::new(Cuboid).scaled(Vec3::splat(20.0)).translated(Vec3A::splat(8.0));
+    arena.apply_tool(clear, Action::Remove, 5);
+    assert_eq!(arena.brick_count(), 0);
+    assert_eq!(arena.cell_count(), 1);
+}
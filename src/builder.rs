@@ -0,0 +1,64 @@
+use crate::{ naive_octree::NaiveOctree, tool::AABB };
+
+/// Configures and creates a Terrain, gathering the settings that would
+/// otherwise be set on the Terrain after construction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TerrainBuilder {
+    bounds: AABB,
+    default_depth: u8,
+}
+
+impl Default for TerrainBuilder {
+    fn default() -> Self {
+        Self {
+            bounds: AABB::ONE_CUBIC_METER,
+            default_depth: 8,
+        }
+    }
+}
+
+impl TerrainBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The space covered by the Terrain. Octrees are cubes, so the
+    /// Terrain starts at `bounds.start` and covers the longest side of
+    /// `bounds` on every axis.
+    pub fn bounds(mut self, bounds: AABB) -> Self {
+        self.bounds = bounds;
+        self
+    }
+
+    /// The maximum depth used when applying tools without an explicit
+    /// depth.
+    pub fn default_depth(mut self, depth: u8) -> Self {
+        self.default_depth = depth;
+        self
+    }
+
+    pub fn build(&self) -> NaiveOctree {
+        let mut terrain = NaiveOctree::new(self.bounds.size.max_element());
+        terrain.origin = self.bounds.start;
+        terrain.default_depth = self.default_depth;
+        terrain
+    }
+}
+
+#[test]
+fn builder_test() {
+    use crate::tool::{ Tool, Sphere, Action };
+    use glam::{ Vec3, Vec3A };
+
+    let mut terrain = TerrainBuilder::new()
+        .bounds(AABB { start: Vec3::splat(-10.0), size: Vec3::new(20.0, 10.0, 20.0) })
+        .default_depth(5)
+        .build();
+    assert_eq!(terrain.aabb(), AABB { start: Vec3::splat(-10.0), size: Vec3::splat(20.0) });
+
+    terrain.apply(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::splat(-5.0)), Action::Place);
+    assert_eq!(terrain.depth(), 5);
+    assert!(terrain.sample(Vec3::splat(-5.0)) > 0.0);
+    assert!(terrain.generate_mesh(255).faces.iter().flatten().all(|vert| vert.distance(Vec3::splat(-5.0)) < 3.1));
+}
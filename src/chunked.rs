@@ -0,0 +1,238 @@
+use glam::{ Vec3, IVec3 };
+use ahash::AHashMap;
+use std::borrow::Borrow;
+use crate::{
+    naive_octree::NaiveOctree,
+    tool::{ Tool, ToolFunc, Action, AABB },
+    utils::{ span, span_record },
+    UnindexedMesh,
+};
+
+/// The integer coordinates of a chunk in world space. Chunk `(0, 0, 0)`
+/// spans from the origin to `chunk_size` on every axis, and negative
+/// coordinates extend in the other direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkKey(pub i32, pub i32, pub i32);
+
+impl ChunkKey {
+    /// The minimum corner of the chunk.
+    pub fn origin(self, chunk_size: f32) -> Vec3 {
+        chunk_to_world(self, chunk_size)
+    }
+
+    /// The space covered by the chunk.
+    pub fn aabb(self, chunk_size: f32) -> AABB {
+        AABB { start: self.origin(chunk_size), size: Vec3::splat(chunk_size) }
+    }
+}
+
+impl From<IVec3> for ChunkKey {
+    fn from(value: IVec3) -> Self {
+        ChunkKey(value.x, value.y, value.z)
+    }
+}
+
+impl From<ChunkKey> for IVec3 {
+    fn from(value: ChunkKey) -> Self {
+        IVec3::new(value.0, value.1, value.2)
+    }
+}
+
+/// Returns the key of the chunk containing `pos`.
+pub fn world_to_chunk(pos: Vec3, chunk_size: f32) -> ChunkKey {
+    (pos / chunk_size).floor().as_ivec3().into()
+}
+
+/// Returns the minimum corner of the chunk at `key`.
+pub fn chunk_to_world(key: ChunkKey, chunk_size: f32) -> Vec3 {
+    IVec3::from(key).as_vec3() * chunk_size
+}
+
+/// A function called with each chunk changed by an edit.
+pub type DirtyCallback = Box<dyn FnMut(ChunkKey, &NaiveOctree) + Send>;
+
+/// An unbounded Terrain made of equally sized [NaiveOctree] chunks,
+/// which are created as tools are placed into them.
+pub struct ChunkedTerrain {
+    chunk_size: f32,
+    /// The maximum depth used by [`apply`](Self::apply).
+    pub default_depth: u8,
+    chunks: AHashMap<ChunkKey, NaiveOctree>,
+    on_dirty: Option<DirtyCallback>,
+}
+
+impl std::fmt::Debug for ChunkedTerrain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChunkedTerrain")
+            .field("chunk_size", &self.chunk_size)
+            .field("default_depth", &self.default_depth)
+            .field("chunks", &self.chunks)
+            .field("on_dirty", &self.on_dirty.is_some())
+            .finish()
+    }
+}
+
+impl ChunkedTerrain {
+    pub fn new(chunk_size: f32) -> Self {
+        Self {
+            chunk_size,
+            default_depth: 8,
+            chunks: Default::default(),
+            on_dirty: None,
+        }
+    }
+
+    /// Sets a function to be called with each chunk changed by an edit,
+    /// such as to remesh it. It is only called once the edit has been
+    /// applied to every chunk it affects, so a callback never sees a
+    /// half-applied edit.
+    pub fn set_dirty_callback(&mut self, callback: impl FnMut(ChunkKey, &NaiveOctree) + Send + 'static) {
+        self.on_dirty = Some(Box::new(callback));
+    }
+
+    /// Removes the function set by [`set_dirty_callback`](Self::set_dirty_callback).
+    pub fn clear_dirty_callback(&mut self) {
+        self.on_dirty = None;
+    }
+
+    pub fn chunk_size(&self) -> f32 {
+        self.chunk_size
+    }
+
+    pub fn chunk(&self, key: ChunkKey) -> Option<&NaiveOctree> {
+        self.chunks.get(&key)
+    }
+
+    pub fn chunk_mut(&mut self, key: ChunkKey) -> Option<&mut NaiveOctree> {
+        self.chunks.get_mut(&key)
+    }
+
+    /// Returns the chunk at `key`, creating an empty one if needed.
+    pub fn chunk_or_create(&mut self, key: ChunkKey) -> &mut NaiveOctree {
+        let chunk_size = self.chunk_size;
+        let default_depth = self.default_depth;
+        self.chunks.entry(key).or_insert_with(|| {
+            let mut chunk = NaiveOctree::new(chunk_size);
+            chunk.origin = key.origin(chunk_size);
+            chunk.default_depth = default_depth;
+            chunk
+        })
+    }
+
+    /// Iterates over every chunk that has been created, in no particular
+    /// order. Sort the keys when the result must be deterministic.
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkKey, &NaiveOctree)> {
+        self.chunks.iter().map(|(&key, chunk)| (key, chunk))
+    }
+
+    /// Returns the keys of every chunk overlapping `aabb`.
+    pub fn chunks_overlapping(&self, aabb: AABB) -> impl Iterator<Item = ChunkKey> {
+        let low = IVec3::from(world_to_chunk(aabb.start, self.chunk_size));
+        let high = IVec3::from(world_to_chunk(aabb.end(), self.chunk_size));
+        (low.z..=high.z).flat_map(move |z| (low.y..=high.y).flat_map(move |y| (low.x..=high.x).map(move |x| ChunkKey(x, y, z))))
+    }
+
+    /// Applies the [Tool] to every chunk it affects with the given
+    /// [Action], up to `max_depth`, and returns the sorted keys of those
+    /// chunks.
+    /// 
+    /// Placing creates any missing chunks the tool reaches. Removing only
+    /// affects chunks that already exist. The dirty callback is called
+    /// for each returned chunk after all of them have been edited.
+    pub fn apply_tool<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action, max_depth: u8) -> Vec<ChunkKey> {
+        span!("chunked_apply_tool", ?action, max_depth, chunks = tracing::field::Empty);
+        let tool = tool.borrow();
+        let mut keys: Vec<ChunkKey> = self.chunks_overlapping(tool.aoe_aabb())
+            .filter(|key| self.chunks.contains_key(key))
+            .collect();
+        if matches!(action, Action::Place) {
+            let new_keys: Vec<ChunkKey> = self.chunks_overlapping(tool.tool_aabb())
+                .filter(|key| !self.chunks.contains_key(key))
+                .collect();
+            keys.extend(new_keys);
+        }
+
+        keys.sort_unstable();
+        span_record!("chunks", keys.len());
+
+        for &key in keys.iter() {
+            self.chunk_or_create(key).apply_tool(tool, action, max_depth);
+        }
+
+        if let Some(on_dirty) = self.on_dirty.as_mut() {
+            for key in keys.iter() {
+                on_dirty(*key, &self.chunks[key]);
+            }
+        }
+        keys
+    }
+
+    /// Same as [`apply_tool`](Self::apply_tool), using `default_depth`.
+    pub fn apply<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action) -> Vec<ChunkKey> {
+        self.apply_tool(tool, action, self.default_depth)
+    }
+
+    /// Returns the value of the terrain at `pos`. Positions in chunks that
+    /// haven't been created are empty.
+    pub fn sample(&self, pos: Vec3) -> f32 {
+        self.chunk(world_to_chunk(pos, self.chunk_size)).map_or(-1.0, |chunk| chunk.sample(pos))
+    }
+
+    /// Uses Marching Cubes to generate an [UnindexedMesh] of the chunk at
+    /// `key`, in world space.
+    pub fn generate_mesh(&self, key: ChunkKey, max_depth: u8) -> Option<UnindexedMesh> {
+        self.chunk(key).map(|chunk| chunk.generate_mesh(max_depth))
+    }
+}
+
+#[test]
+fn chunk_key_test() {
+    assert_eq!(world_to_chunk(Vec3::new(0.5, -0.5, 25.0), 10.0), ChunkKey(0, -1, 2));
+    assert_eq!(chunk_to_world(ChunkKey(0, -1, 2), 10.0), Vec3::new(0.0, -10.0, 20.0));
+    assert_eq!(world_to_chunk(chunk_to_world(ChunkKey(-3, 4, -5), 8.0), 8.0), ChunkKey(-3, 4, -5));
+}
+
+#[test]
+fn chunked_terrain_test() {
+    use crate::tool::Sphere;
+    use glam::Vec3A;
+
+    let mut terrain = ChunkedTerrain::new(10.0);
+    let keys = terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)), Action::Place, 5);
+    // A sphere at the origin touches the 8 chunks around it
+    assert_eq!(keys.len(), 8);
+    assert!(keys.contains(&ChunkKey(-1, -1, -1)));
+    assert!(terrain.sample(Vec3::splat(-1.0)) > 0.0);
+    assert!(terrain.sample(Vec3::splat(1.0)) > 0.0);
+    assert_eq!(terrain.sample(Vec3::splat(-15.0)), -1.0);
+
+    let mesh = terrain.generate_mesh(ChunkKey(-1, -1, -1), 255).unwrap();
+    assert!(mesh.faces.iter().flatten().all(|vert| vert.cmple(Vec3::ZERO).all()));
+
+    // Removing far away doesn't create chunks
+    let keys = terrain.apply_tool(Tool::new(Sphere).translated(Vec3A::splat(50.0)), Action::Remove, 5);
+    assert!(keys.is_empty());
+    assert_eq!(terrain.chunks().count(), 8);
+}
+
+#[test]
+fn dirty_callback_test() {
+    use crate::tool::Sphere;
+    use std::sync::{ Arc, Mutex };
+
+    let mut terrain = ChunkedTerrain::new(10.0);
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let callback_seen = seen.clone();
+    terrain.set_dirty_callback(move |key, chunk| {
+        // Each chunk has its part of the sphere near the origin
+        let aabb = chunk.aabb();
+        let near_origin = aabb.closest_point(Vec3::ZERO).lerp(aabb.center(), 0.1);
+        callback_seen.lock().unwrap().push((key, chunk.sample(near_origin) > 0.0));
+    });
+
+    let keys = terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)), Action::Place, 4);
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.iter().map(|(key, _)| *key).collect::<Vec<_>>(), keys);
+    assert!(seen.iter().all(|(_, edited)| *edited));
+}
@@ -0,0 +1,178 @@
+use glam::{ Vec3, UVec3 };
+use std::borrow::Borrow;
+use crate::{
+    tool::{ Tool, ToolFunc, Action, AABB },
+    marching_cubes::march_cube,
+    utils,
+    UnindexedMesh, CUBE_CORNERS,
+};
+
+/// A Terrain that stores a value at every point of a regular grid.
+/// 
+/// This uses far more memory than a [NaiveOctree](crate::naive_octree::NaiveOctree)
+/// for large volumes, but every cell is at full detail, which suits small
+/// objects. It also makes a simple reference for testing the octrees.
+#[derive(Debug, Clone)]
+pub struct DenseGrid {
+    dims: UVec3,
+    /// The position of the first grid point.
+    pub origin: Vec3,
+    /// The distance between neighbouring grid points.
+    pub spacing: f32,
+    values: Vec<f32>,
+}
+
+impl DenseGrid {
+    /// Creates an empty grid with `dims` points along each axis.
+    pub fn new(dims: UVec3, origin: Vec3, spacing: f32) -> Self {
+        Self {
+            dims,
+            origin,
+            spacing,
+            values: vec![-1.0; (dims.x * dims.y * dims.z) as usize],
+        }
+    }
+
+    /// Creates a grid from `values`, ordered by X, then Y, then Z.
+    pub fn from_values(dims: UVec3, origin: Vec3, spacing: f32, values: Vec<f32>) -> crate::Result<Self> {
+        if values.len() != (dims.x * dims.y * dims.z) as usize {
+            return Err(crate::Error::InvalidDimensions { dims: dims.to_array().map(|dim| dim as usize).to_vec(), len: values.len() });
+        }
+        Ok(Self { dims, origin, spacing, values })
+    }
+
+    /// The number of points along each axis.
+    pub fn dims(&self) -> UVec3 {
+        self.dims
+    }
+
+    /// The values of the grid, ordered by X, then Y, then Z.
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn values_mut(&mut self) -> &mut [f32] {
+        &mut self.values
+    }
+
+    fn index(&self, point: UVec3) -> usize {
+        (point.x + point.y * self.dims.x + point.z * self.dims.x * self.dims.y) as usize
+    }
+
+    pub fn get(&self, point: UVec3) -> f32 {
+        self.values[self.index(point)]
+    }
+
+    pub fn set(&mut self, point: UVec3, value: f32) {
+        let index = self.index(point);
+        self.values[index] = value;
+    }
+
+    /// The number of cells along each axis.
+    fn cells(&self) -> UVec3 {
+        self.dims.max(UVec3::ONE) - UVec3::ONE
+    }
+
+    /// The position of a grid point.
+    pub fn position(&self, point: UVec3) -> Vec3 {
+        self.origin + point.as_vec3() * self.spacing
+    }
+
+    /// The AABB covered by the grid.
+    pub fn aabb(&self) -> AABB {
+        AABB { start: self.origin, size: self.cells().as_vec3() * self.spacing }
+    }
+
+    /// Applies the [Tool] to every grid point inside of its area of effect
+    /// with the given [Action].
+    pub fn apply_tool<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action) {
+        let tool = tool.borrow();
+        let Some((low, high)) = self.points_in(tool.aoe_aabb()) else {
+            return;
+        };
+        let weight = tool.weight();
+        for z in low.z..=high.z {
+            for y in low.y..=high.y {
+                for x in low.x..=high.x {
+                    let point = UVec3::new(x, y, z);
+                    let value = tool.value(self.position(point));
+                    let index = self.index(point);
+                    action.apply_weighted(&mut self.values[index], value, weight);
+                }
+            }
+        }
+    }
+
+    /// Returns the first and last grid point of the box of points covering
+    /// `aabb`, or [None] if it is outside of the grid.
+    pub(crate) fn points_in(&self, aabb: AABB) -> Option<(UVec3, UVec3)> {
+        let aabb = self.aabb().get_intersect_aabb(aabb)?;
+        let low = ((aabb.start - self.origin) / self.spacing).floor().as_uvec3();
+        let high = ((aabb.end() - self.origin) / self.spacing).ceil().as_uvec3().min(self.cells());
+        Some((low, high))
+    }
+
+    /// Returns the value at `pos` by interpolating the nearest grid points.
+    /// Positions outside of the grid are empty.
+    pub fn sample(&self, pos: Vec3) -> f32 {
+        if !self.aabb().contains(pos) || self.dims.cmplt(UVec3::splat(2)).any() {
+            return -1.0;
+        }
+        let local = (pos - self.origin) / self.spacing;
+        let cell = local.floor().as_uvec3().min(self.dims - 2);
+        utils::trilinear(&self.cell_values(cell), (local - cell.as_vec3()).clamp(Vec3::ZERO, Vec3::ONE))
+    }
+
+    /// The values at the corners of the cell starting at `cell`, in the
+    /// order of [CUBE_CORNERS].
+    fn cell_values(&self, cell: UVec3) -> [f32; 8] {
+        CUBE_CORNERS.map(|corner| self.get(cell + corner.as_uvec3()))
+    }
+
+    /// Uses Marching Cubes to generate an [UnindexedMesh].
+    pub fn generate_mesh(&self) -> UnindexedMesh {
+        let mut faces = Vec::new();
+        let cells = self.cells();
+        for z in 0..cells.z {
+            for y in 0..cells.y {
+                for x in 0..cells.x {
+                    let cell = UVec3::new(x, y, z);
+                    let corners = CUBE_CORNERS.map(|corner| self.position(cell + corner.as_uvec3()));
+                    faces.extend(march_cube(&corners, &self.cell_values(cell)));
+                }
+            }
+        }
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+}
+
+#[test]
+fn dense_grid_test() {
+    use crate::{ naive_octree::NaiveOctree, tool::Sphere };
+    use glam::Vec3A;
+
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::new(8.0, 7.5, 8.5));
+    let mut grid = DenseGrid::new(UVec3::splat(17), Vec3::ZERO, 1.0);
+    grid.apply_tool(tool, Action::Place);
+    let mut octree = NaiveOctree::new(16.0);
+    octree.apply_tool(tool, Action::Place, 4);
+
+    // The octree agrees with the grid on which points are inside
+    for z in 0..17 {
+        for y in 0..17 {
+            for x in 0..17 {
+                let point = UVec3::new(x, y, z);
+                let pos = grid.position(point);
+                assert_eq!(grid.get(point) > 0.0, octree.sample(pos) > 0.0, "{pos}");
+            }
+        }
+    }
+    assert_eq!(grid.generate_mesh().faces.len(), octree.generate_mesh(4).faces.len());
+    // Near the surface, the octree is at full detail too
+    let pos = Vec3::new(8.3, 7.5, 13.2);
+    assert!((grid.sample(pos) - octree.sample(pos)).abs() < 1e-5);
+    assert!(DenseGrid::from_values(UVec3::splat(2), Vec3::ZERO, 1.0, vec![0.0; 7]).is_err());
+}
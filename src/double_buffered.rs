@@ -0,0 +1,77 @@
+/// Keeps a read copy of a Terrain for meshing and queries, and a write
+/// copy for edits.
+/// 
+/// Edits made through [`write`](Self::write) aren't seen by [`read`](Self::read)
+/// until [`swap`](Self::swap) is called, so a renderer can keep using the
+/// read copy while the next edits are being made.
+/// 
+/// Swapping clones the write copy. Cloning a
+/// [NaiveOctree](crate::naive_octree::NaiveOctree) shares its cells, so
+/// the swap itself is O(1), and the next edits only copy the cells on
+/// the path to them. Other Terrains are copied in full.
+#[derive(Debug, Clone, Default)]
+pub struct DoubleBuffered<T: Clone> {
+    read: T,
+    write: T,
+    dirty: bool,
+}
+
+impl<T: Clone> DoubleBuffered<T> {
+    pub fn new(terrain: T) -> Self {
+        Self {
+            read: terrain.clone(),
+            write: terrain,
+            dirty: false,
+        }
+    }
+
+    /// The copy used for meshing and queries.
+    pub fn read(&self) -> &T {
+        &self.read
+    }
+
+    /// The copy used for edits.
+    pub fn write(&mut self) -> &mut T {
+        self.dirty = true;
+        &mut self.write
+    }
+
+    /// Returns true if the write copy may have changed since the last
+    /// [`swap`](Self::swap).
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Makes the read copy a clone of the write copy. Returns false
+    /// without cloning anything if there were no edits since the last swap.
+    pub fn swap(&mut self) -> bool {
+        if !self.dirty {
+            return false;
+        }
+        self.read.clone_from(&self.write);
+        self.dirty = false;
+        true
+    }
+
+    /// Returns the write copy.
+    pub fn into_inner(self) -> T {
+        self.write
+    }
+}
+
+#[test]
+fn double_buffered_test() {
+    use crate::{ naive_octree::NaiveOctree, tool::{ Tool, Sphere, Action } };
+    use glam::{ Vec3, Vec3A };
+
+    let mut terrain = DoubleBuffered::new(NaiveOctree::new(16.0));
+    assert!(!terrain.swap());
+
+    terrain.write().apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+    assert!(terrain.read().sample(Vec3::splat(8.0)) < 0.0);
+    assert!(terrain.is_dirty());
+
+    assert!(terrain.swap());
+    assert!(terrain.read().sample(Vec3::splat(8.0)) > 0.0);
+    assert!(!terrain.is_dirty());
+}
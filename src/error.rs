@@ -0,0 +1,80 @@
+use std::fmt;
+
+/// Errors that can be caused by user input or the environment.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing a file failed.
+    Io(std::io::Error),
+    /// An image couldn't be decoded.
+    #[cfg(feature = "image")]
+    Image(image::ImageError),
+    /// A glTF file couldn't be imported.
+    #[cfg(feature = "gltf")]
+    Gltf(gltf::Error),
+    /// No GPU was found, or it failed to run a command.
+    #[cfg(feature = "gpu")]
+    Gpu(String),
+    /// A grid of values didn't match its dimensions, or a dimension was 0.
+    InvalidDimensions {
+        dims: Vec<usize>,
+        len: usize,
+    },
+    /// A mesh vertex index didn't fit in a `u32`.
+    IndexOverflow(usize),
+}
+
+/// A Result using the crate's [Error] type.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "IO error: {err}"),
+            #[cfg(feature = "image")]
+            Error::Image(err) => write!(f, "Image error: {err}"),
+            #[cfg(feature = "gltf")]
+            Error::Gltf(err) => write!(f, "glTF error: {err}"),
+            #[cfg(feature = "gpu")]
+            Error::Gpu(message) => write!(f, "GPU error: {message}"),
+            Error::InvalidDimensions { dims, len } => write!(f, "{len} values don't fit dimensions {dims:?}"),
+            Error::IndexOverflow(index) => write!(f, "Vertex index {index} doesn't fit in a u32"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            #[cfg(feature = "image")]
+            Error::Image(err) => Some(err),
+            #[cfg(feature = "gltf")]
+            Error::Gltf(err) => Some(err),
+            #[cfg(feature = "gpu")]
+            Error::Gpu(_) => None,
+            Error::InvalidDimensions { .. } => None,
+            Error::IndexOverflow(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<image::ImageError> for Error {
+    fn from(err: image::ImageError) -> Self {
+        Error::Image(err)
+    }
+}
+
+#[cfg(feature = "gltf")]
+impl From<gltf::Error> for Error {
+    fn from(err: gltf::Error) -> Self {
+        Error::Gltf(err)
+    }
+}
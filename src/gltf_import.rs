@@ -0,0 +1,109 @@
+use glam::{ Vec3, Mat4, Mat3 };
+use std::path::Path;
+
+use crate::{ IndexedMesh, Normals };
+
+impl IndexedMesh {
+    /// Loads a glTF file and merges every triangle primitive in its default
+    /// scene into a single mesh, with node transforms applied.
+    /// 
+    /// If every primitive has normals, the mesh will have
+    /// [vertex normals](Normals::Vertex).
+    pub fn from_gltf_path(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let (document, buffers, _) = gltf::import(path)?;
+        Ok(Self::from_gltf(&document, &buffers))
+    }
+
+    /// Parses a glTF or GLB file from memory and merges every triangle
+    /// primitive in its default scene into a single mesh.
+    /// 
+    /// See also: [`from_gltf_path`](Self::from_gltf_path)
+    pub fn from_gltf_slice(bytes: &[u8]) -> crate::Result<Self> {
+        let (document, buffers, _) = gltf::import_slice(bytes)?;
+        Ok(Self::from_gltf(&document, &buffers))
+    }
+
+    /// Merges every triangle primitive in the default scene of an already
+    /// loaded glTF document into a single mesh.
+    pub fn from_gltf(document: &gltf::Document, buffers: &[gltf::buffer::Data]) -> Self {
+        let mut mesh = IndexedMesh {
+            verts: Vec::new(),
+            faces: Vec::new(),
+            normals: None,
+        };
+        let mut normals = Some(Vec::new());
+
+        let scene = document.default_scene().or_else(|| document.scenes().next());
+        if let Some(scene) = scene {
+            scene.nodes().for_each(|node| {
+                add_gltf_node(&node, Mat4::IDENTITY, buffers, &mut mesh, &mut normals);
+            });
+        }
+
+        mesh.normals = normals.filter(|normals| !normals.is_empty()).map(Normals::Vertex);
+        mesh
+    }
+}
+
+fn add_gltf_node(
+    node: &gltf::Node,
+    parent_transform: Mat4,
+    buffers: &[gltf::buffer::Data],
+    mesh: &mut IndexedMesh,
+    normals: &mut Option<Vec<Vec3>>
+) {
+    let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+    let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+
+    if let Some(gltf_mesh) = node.mesh() {
+        gltf_mesh.primitives()
+            .filter(|primitive| primitive.mode() == gltf::mesh::Mode::Triangles)
+            .for_each(|primitive| {
+                let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+                let positions = match reader.read_positions() {
+                    Some(positions) => positions,
+                    None => return,
+                };
+
+                let base = mesh.verts.len();
+                mesh.verts.extend(positions.map(|pos| transform.transform_point3(Vec3::from(pos))));
+                let count = mesh.verts.len() - base;
+
+                match reader.read_indices() {
+                    Some(indices) => {
+                        let indices: Vec<usize> = indices.into_u32().map(|index| base + index as usize).collect();
+                        mesh.faces.extend(indices.chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]));
+                    },
+                    None => {
+                        mesh.faces.extend((base..base + count).step_by(3)
+                            .filter(|&i| i + 2 < base + count)
+                            .map(|i| [i, i+1, i+2]));
+                    },
+                }
+
+                match (reader.read_normals(), normals.as_mut()) {
+                    (Some(prim_normals), Some(normals)) => {
+                        normals.extend(prim_normals.map(|normal| {
+                            (normal_matrix * Vec3::from(normal)).normalize_or_zero()
+                        }));
+                    },
+                    _ => *normals = None,
+                }
+            });
+    }
+
+    node.children().for_each(|child| {
+        add_gltf_node(&child, transform, buffers, mesh, normals);
+    });
+}
+
+#[test]
+fn gltf_import_test() {
+    // A single triangle, translated 5 units along Z by its node
+    const GLTF: &str = r#"{"asset":{"version":"2.0"},"scene":0,"scenes":[{"nodes":[0]}],"nodes":[{"mesh":0,"translation":[0,0,5]}],"meshes":[{"primitives":[{"attributes":{"POSITION":0},"indices":1}]}],"buffers":[{"byteLength":44,"uri":"data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAAAAABAAIAAAA="}],"bufferViews":[{"buffer":0,"byteOffset":0,"byteLength":36},{"buffer":0,"byteOffset":36,"byteLength":6}],"accessors":[{"bufferView":0,"componentType":5126,"count":3,"type":"VEC3","min":[0,0,0],"max":[1,1,0]},{"bufferView":1,"componentType":5123,"count":3,"type":"SCALAR"}]}"#;
+
+    let mesh = IndexedMesh::from_gltf_slice(GLTF.as_bytes()).unwrap();
+    assert_eq!(mesh.verts, vec![Vec3::new(0.0,0.0,5.0), Vec3::new(1.0,0.0,5.0), Vec3::new(0.0,1.0,5.0)]);
+    assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+    assert!(mesh.normals.is_none());
+}
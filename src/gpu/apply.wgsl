@@ -0,0 +1,58 @@
+// Applies a tool to a box of DenseGrid values. Matches DenseGrid::apply_tool.
+
+struct Params {
+    // The inverse of the tool's transform
+    inverse: mat4x4<f32>,
+    // The position of the first grid point, and the spacing in w
+    origin: vec4<f32>,
+    // The first grid point of the box, and the shape in w
+    low: vec4<u32>,
+    // The number of points in the box, and the action in w
+    dims: vec4<u32>,
+    // The weight of the tool in x
+    weight: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> values: array<f32>;
+
+fn sphere(pos: vec3<f32>) -> f32 {
+    return clamp(1.0 - length(pos), -1.0, 1.0);
+}
+
+fn cuboid(pos: vec3<f32>) -> f32 {
+    let d = abs(pos) - vec3<f32>(1.0);
+    let distance = length(max(d, vec3<f32>(0.0))) + min(max(d.x, max(d.y, d.z)), 0.0);
+    return clamp(-distance, -1.0, 1.0);
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dims = params.dims.xyz;
+    if any(id >= dims) {
+        return;
+    }
+
+    let point = params.low.xyz + id;
+    let pos = params.origin.xyz + vec3<f32>(point) * params.origin.w;
+    let local = (params.inverse * vec4<f32>(pos, 1.0)).xyz;
+    var value: f32;
+    switch params.low.w {
+        case 0u: { value = sphere(local); }
+        default: { value = cuboid(local); }
+    }
+
+    let index = id.x + id.y * dims.x + id.z * dims.x * dims.y;
+    let old = values[index];
+    var result: f32;
+    if params.dims.w == 0u {
+        result = max(old, value);
+    } else {
+        result = min(old, -value);
+    }
+    let weight = params.weight.x;
+    if weight < 1.0 {
+        result = old + (result - old) * max(weight, 0.0);
+    }
+    values[index] = result;
+}
@@ -0,0 +1,171 @@
+// Marching Cubes over the cells of a DenseGrid. Matches DenseGrid::generate_mesh,
+// except that the triangles come out in no particular order and share vertices.
+//
+// Every grid point owns the edges going towards +X, +Y and +Z from it, and
+// makes one vertex for each of them that crosses the surface. The cells then
+// look their vertices up by edge to build the index buffer.
+//
+// Each `count_*` entry point adds up how many items its `emit_*` counterpart
+// will write, so a buffer of the right size can be made first.
+
+struct Params {
+    // The position of the first grid point, and the spacing in w
+    origin: vec4<f32>,
+    // The number of points along each axis
+    dims: vec4<u32>,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> values: array<f32>;
+// TRI_TABLE, 16 edges per row padded with -1
+@group(0) @binding(2) var<storage, read> tri_table: array<i32>;
+@group(0) @binding(3) var<storage, read_write> counter: atomic<u32>;
+@group(0) @binding(4) var<storage, read_write> vertices: array<vec4<f32>>;
+// The vertex of each crossing edge, 3 per grid point
+@group(0) @binding(5) var<storage, read_write> edge_vertices: array<u32>;
+@group(0) @binding(6) var<storage, read_write> indices: array<u32>;
+
+// CUBE_CORNERS
+const CORNERS = array<vec3<u32>, 8>(
+    vec3<u32>(0u, 0u, 0u), vec3<u32>(1u, 0u, 0u), vec3<u32>(0u, 1u, 0u), vec3<u32>(1u, 1u, 0u),
+    vec3<u32>(0u, 0u, 1u), vec3<u32>(1u, 0u, 1u), vec3<u32>(0u, 1u, 1u), vec3<u32>(1u, 1u, 1u),
+);
+
+// EDGE_CORNERS
+const EDGES = array<vec2<u32>, 12>(
+    vec2<u32>(0u, 1u), vec2<u32>(0u, 4u), vec2<u32>(4u, 5u), vec2<u32>(5u, 1u),
+    vec2<u32>(2u, 3u), vec2<u32>(2u, 6u), vec2<u32>(6u, 7u), vec2<u32>(7u, 3u),
+    vec2<u32>(0u, 2u), vec2<u32>(4u, 6u), vec2<u32>(5u, 7u), vec2<u32>(1u, 3u),
+);
+
+const AXES = array<vec3<u32>, 3>(
+    vec3<u32>(1u, 0u, 0u), vec3<u32>(0u, 1u, 0u), vec3<u32>(0u, 0u, 1u),
+);
+
+fn point_index(point: vec3<u32>) -> u32 {
+    let dims = params.dims.xyz;
+    return point.x + point.y * dims.x + point.z * dims.x * dims.y;
+}
+
+fn value_at(point: vec3<u32>) -> f32 {
+    return values[point_index(point)];
+}
+
+fn position(point: vec3<u32>) -> vec3<f32> {
+    return params.origin.xyz + vec3<f32>(point) * params.origin.w;
+}
+
+// Same as marching_cubes::vert_interp
+fn vert_interp(pos1: vec3<f32>, val1: f32, pos2: vec3<f32>, val2: f32) -> vec3<f32> {
+    if abs(val1) < 0.00001 { return pos1; }
+    if abs(val2) < 0.00001 { return pos2; }
+    if abs(val1 - val2) < 0.00001 { return pos1; }
+    let t = clamp(-val1 / (val2 - val1), 0.0, 1.0);
+    return mix(pos1, pos2, t);
+}
+
+fn is_point(point: vec3<u32>) -> bool {
+    return all(point < params.dims.xyz);
+}
+
+fn is_cell(cell: vec3<u32>) -> bool {
+    return all(cell + 1u < params.dims.xyz);
+}
+
+// True if the edge from `point` along `axis` is inside the grid and crosses
+// the surface, using the same test as cube_index.
+fn crosses(point: vec3<u32>, axis: u32) -> bool {
+    var axes = AXES;
+    let other = point + axes[axis];
+    if !is_point(other) {
+        return false;
+    }
+    return (value_at(point) > 0.0) != (value_at(other) > 0.0);
+}
+
+fn cube_index(cell: vec3<u32>) -> u32 {
+    var corners = CORNERS;
+    var index = 0u;
+    for (var corner = 0u; corner < 8u; corner++) {
+        if value_at(cell + corners[corner]) > 0.0 {
+            index |= 1u << corner;
+        }
+    }
+    return index;
+}
+
+fn index_count(row: u32) -> u32 {
+    var count = 0u;
+    while count < 16u && tri_table[row * 16u + count] >= 0 {
+        count++;
+    }
+    return count;
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn count_vertices(@builtin(global_invocation_id) point: vec3<u32>) {
+    if !is_point(point) {
+        return;
+    }
+    var count = 0u;
+    for (var axis = 0u; axis < 3u; axis++) {
+        if crosses(point, axis) {
+            count++;
+        }
+    }
+    if count > 0u {
+        atomicAdd(&counter, count);
+    }
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn emit_vertices(@builtin(global_invocation_id) point: vec3<u32>) {
+    if !is_point(point) {
+        return;
+    }
+    var axes = AXES;
+    for (var axis = 0u; axis < 3u; axis++) {
+        if crosses(point, axis) {
+            let other = point + axes[axis];
+            let vertex = atomicAdd(&counter, 1u);
+            let pos = vert_interp(position(point), value_at(point), position(other), value_at(other));
+            vertices[vertex] = vec4<f32>(pos, 1.0);
+            edge_vertices[point_index(point) * 3u + axis] = vertex;
+        }
+    }
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn count_indices(@builtin(global_invocation_id) cell: vec3<u32>) {
+    if !is_cell(cell) {
+        return;
+    }
+    let count = index_count(cube_index(cell));
+    if count > 0u {
+        atomicAdd(&counter, count);
+    }
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn emit_indices(@builtin(global_invocation_id) cell: vec3<u32>) {
+    if !is_cell(cell) {
+        return;
+    }
+    let row = cube_index(cell);
+    let count = index_count(row);
+    if count == 0u {
+        return;
+    }
+
+    var corners = CORNERS;
+    var edges = EDGES;
+    let start = atomicAdd(&counter, count);
+    for (var index = 0u; index < count; index++) {
+        let edge = edges[tri_table[row * 16u + index]];
+        let point1 = cell + corners[edge.x];
+        let point2 = cell + corners[edge.y];
+        let diff = max(point1, point2) - min(point1, point2);
+        let axis = select(select(2u, 1u, diff.y != 0u), 0u, diff.x != 0u);
+        indices[start + index] = edge_vertices[point_index(min(point1, point2)) * 3u + axis];
+    }
+}
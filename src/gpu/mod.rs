@@ -0,0 +1,441 @@
+//! Applying tools to a [DenseGrid] and meshing it with wgpu compute shaders.
+//!
+//! Large brushes at fine spacings touch millions of grid points, which
+//! the GPU can evaluate all at once. Only the box of points inside of the
+//! tool's area of effect is uploaded and read back for each edit.
+//!
+//! Every cell of the grid is marched in parallel when meshing, and the
+//! vertices can be left on the GPU for rendering.
+//!
+//! Only [DenseGrid] is supported. The octree's leaves are different sizes,
+//! so a [NaiveOctree](crate::naive_octree::NaiveOctree) region has to be
+//! sampled into a grid with
+//! [`to_dense`](crate::naive_octree::NaiveOctree::to_dense) first, and
+//! edits made on the GPU written back with
+//! [`from_dense`](crate::naive_octree::NaiveOctree::from_dense).
+
+use glam::{ Mat4, UVec3, Vec3 };
+use std::borrow::Borrow;
+use wgpu::util::DeviceExt;
+use crate::{
+    tool::{ Tool, ToolFunc, Action, Sphere, Cuboid, Weighted },
+    marching_cubes::TRI_TABLE,
+    DenseGrid, IndexedMesh, Error,
+};
+
+/// The shapes the compute shader can evaluate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuShape {
+    Sphere,
+    Cuboid,
+}
+
+/// A [ToolFunc] that the compute shader can evaluate.
+pub trait GpuToolFunc: ToolFunc {
+    fn gpu_shape(&self) -> GpuShape;
+}
+
+impl GpuToolFunc for Sphere {
+    fn gpu_shape(&self) -> GpuShape {
+        GpuShape::Sphere
+    }
+}
+
+impl GpuToolFunc for Cuboid {
+    fn gpu_shape(&self) -> GpuShape {
+        GpuShape::Cuboid
+    }
+}
+
+/// The weight is applied by the shader from [`ToolFunc::weight`].
+impl<F: GpuToolFunc> GpuToolFunc for Weighted<F> {
+    fn gpu_shape(&self) -> GpuShape {
+        self.func.gpu_shape()
+    }
+}
+
+/// The uniforms of `apply.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ApplyParams {
+    inverse: [[f32; 4]; 4],
+    origin: [f32; 4],
+    low: [u32; 4],
+    dims: [u32; 4],
+    weight: [f32; 4],
+}
+
+/// The uniforms of `marching_cubes.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MeshParams {
+    origin: [f32; 4],
+    dims: [u32; 4],
+}
+
+/// Triangles generated by [`GpuContext::generate_mesh_buffers`], still on
+/// the GPU.
+#[derive(Debug)]
+pub struct GpuMesh {
+    /// `vertex_count` positions, each stored as 4 f32s with a w of 1.0. It
+    /// can be bound as a vertex buffer with
+    /// [`wgpu::VertexFormat::Float32x4`].
+    pub vertices: wgpu::Buffer,
+    pub vertex_count: u32,
+    /// A triangle list of `index_count` indices into `vertices`, in the
+    /// [`wgpu::IndexFormat::Uint32`] format.
+    pub indices: wgpu::Buffer,
+    pub index_count: u32,
+}
+
+/// A wgpu device with the compute pipelines used by this module.
+#[derive(Debug)]
+pub struct GpuContext {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    apply_pipeline: wgpu::ComputePipeline,
+    count_vertices_pipeline: wgpu::ComputePipeline,
+    emit_vertices_pipeline: wgpu::ComputePipeline,
+    count_indices_pipeline: wgpu::ComputePipeline,
+    emit_indices_pipeline: wgpu::ComputePipeline,
+    tri_table: wgpu::Buffer,
+}
+
+impl GpuContext {
+    /// Creates a device on the default adapter. Returns [`Error::Gpu`] if
+    /// there isn't one.
+    pub fn new() -> crate::Result<Self> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+            .ok_or_else(|| Error::Gpu("no adapter found".to_string()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("pie-crust"),
+            required_features: wgpu::Features::empty(),
+            required_limits: adapter.limits(),
+            memory_hints: wgpu::MemoryHints::default(),
+        }, None)).map_err(|err| Error::Gpu(err.to_string()))?;
+        Ok(Self::from_device(device, queue))
+    }
+
+    /// Uses a device created by the application, so the buffers can be
+    /// shared with its renderer.
+    pub fn from_device(device: wgpu::Device, queue: wgpu::Queue) -> Self {
+        let apply_pipeline = compute_pipeline(&device, "apply", include_str!("apply.wgsl"), "main");
+        let marching_cubes = include_str!("marching_cubes.wgsl");
+        let count_vertices_pipeline = compute_pipeline(&device, "marching cubes count vertices", marching_cubes, "count_vertices");
+        let emit_vertices_pipeline = compute_pipeline(&device, "marching cubes emit vertices", marching_cubes, "emit_vertices");
+        let count_indices_pipeline = compute_pipeline(&device, "marching cubes count indices", marching_cubes, "count_indices");
+        let emit_indices_pipeline = compute_pipeline(&device, "marching cubes emit indices", marching_cubes, "emit_indices");
+        let tri_table: Vec<i32> = TRI_TABLE.iter()
+            .flat_map(|row| (0..16).map(|index| row.get(index).map_or(-1, |&edge| edge as i32)))
+            .collect();
+        let tri_table = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("tri table"),
+            contents: bytemuck::cast_slice(&tri_table),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        Self {
+            device,
+            queue,
+            apply_pipeline,
+            count_vertices_pipeline,
+            emit_vertices_pipeline,
+            count_indices_pipeline,
+            emit_indices_pipeline,
+            tri_table,
+        }
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Same as [`DenseGrid::apply_tool`], but the tool is evaluated on the
+    /// GPU. The values can differ from the CPU in the last few bits.
+    /// 
+    /// This only takes a [DenseGrid]. See the [module docs](self) for
+    /// editing an octree.
+    pub fn apply_tool<T: Borrow<Tool<F>>, F: GpuToolFunc>(&self, grid: &mut DenseGrid, tool: T, action: Action) -> crate::Result<()> {
+        let tool = tool.borrow();
+        let Some((low, high)) = grid.points_in(tool.aoe_aabb()) else {
+            return Ok(());
+        };
+        let dims = high - low + 1;
+
+        let mut values = Vec::with_capacity((dims.x * dims.y * dims.z) as usize);
+        for z in low.z..=high.z {
+            for y in low.y..=high.y {
+                for x in low.x..=high.x {
+                    values.push(grid.get(UVec3::new(x, y, z)));
+                }
+            }
+        }
+
+        let params = ApplyParams {
+            inverse: Mat4::from(tool.transform().inverse()).to_cols_array_2d(),
+            origin: grid.origin.extend(grid.spacing).to_array(),
+            low: low.extend(tool.func().gpu_shape() as u32).to_array(),
+            dims: dims.extend(matches!(action, Action::Remove) as u32).to_array(),
+            weight: [tool.weight(), 0.0, 0.0, 0.0],
+        };
+        let params = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("apply params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let storage = self.storage_buffer("apply values", bytemuck::cast_slice(&values), wgpu::BufferUsages::COPY_SRC)?;
+        self.dispatch(&self.apply_pipeline, &[
+            wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() },
+            wgpu::BindGroupEntry { binding: 1, resource: storage.as_entire_binding() },
+        ], (dims + 3) / 4);
+
+        let values = self.read_buffer(&storage)?;
+        let mut values = bytemuck::cast_slice::<u8, f32>(&values).iter();
+        for z in low.z..=high.z {
+            for y in low.y..=high.y {
+                for x in low.x..=high.x {
+                    grid.set(UVec3::new(x, y, z), *values.next().unwrap());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Uses Marching Cubes to generate the triangles of `grid`, leaving
+    /// them on the GPU. Like [`apply_tool`](Self::apply_tool), this only
+    /// takes a [DenseGrid]. The triangles are the same as those of
+    /// [`DenseGrid::generate_mesh`], but in no particular order, and
+    /// triangles that meet at an edge of the grid share its vertex.
+    pub fn generate_mesh_buffers(&self, grid: &DenseGrid) -> crate::Result<GpuMesh> {
+        let dims = grid.dims();
+        let params = MeshParams {
+            origin: grid.origin.extend(grid.spacing).to_array(),
+            dims: dims.extend(0).to_array(),
+        };
+        let params = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("marching cubes params"),
+            contents: bytemuck::bytes_of(&params),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        if dims.cmplt(UVec3::splat(2)).any() {
+            return self.empty_mesh();
+        }
+        let values = self.storage_buffer("marching cubes values", bytemuck::cast_slice(grid.values()), wgpu::BufferUsages::empty())?;
+        let counter = self.storage_buffer("marching cubes counter", &[0; 4], wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST)?;
+        let point_groups = (dims + 3) / 4;
+        let cell_groups = (dims - 1 + 3) / 4;
+        let params = wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() };
+        let values = wgpu::BindGroupEntry { binding: 1, resource: values.as_entire_binding() };
+        let tri_table = wgpu::BindGroupEntry { binding: 2, resource: self.tri_table.as_entire_binding() };
+        let counter_entry = wgpu::BindGroupEntry { binding: 3, resource: counter.as_entire_binding() };
+
+        // Each pass is counted first, so its buffer can be made to fit
+        self.dispatch(&self.count_vertices_pipeline, &[params.clone(), values.clone(), counter_entry.clone()], point_groups);
+        let vertex_count = bytemuck::pod_read_unaligned::<u32>(&self.read_buffer(&counter)?);
+        if vertex_count == 0 {
+            return self.empty_mesh();
+        }
+        let vertices = self.storage_buffer(
+            "marching cubes vertices",
+            &vec![0; vertex_count as usize * 16],
+            wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC,
+        )?;
+        let edge_vertices = self.storage_buffer(
+            "marching cubes edge vertices",
+            &vec![0; grid.values().len() * 3 * 4],
+            wgpu::BufferUsages::empty(),
+        )?;
+        let edge_vertices = wgpu::BindGroupEntry { binding: 5, resource: edge_vertices.as_entire_binding() };
+        self.queue.write_buffer(&counter, 0, &[0; 4]);
+        self.dispatch(&self.emit_vertices_pipeline, &[
+            params.clone(), values.clone(), counter_entry.clone(),
+            wgpu::BindGroupEntry { binding: 4, resource: vertices.as_entire_binding() },
+            edge_vertices.clone(),
+        ], point_groups);
+
+        self.queue.write_buffer(&counter, 0, &[0; 4]);
+        self.dispatch(&self.count_indices_pipeline, &[params.clone(), values.clone(), tri_table.clone(), counter_entry.clone()], cell_groups);
+        let index_count = bytemuck::pod_read_unaligned::<u32>(&self.read_buffer(&counter)?);
+        let indices = self.storage_buffer(
+            "marching cubes indices",
+            &vec![0; index_count as usize * 4],
+            wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC,
+        )?;
+        self.queue.write_buffer(&counter, 0, &[0; 4]);
+        self.dispatch(&self.emit_indices_pipeline, &[
+            params, values, tri_table, counter_entry, edge_vertices,
+            wgpu::BindGroupEntry { binding: 6, resource: indices.as_entire_binding() },
+        ], cell_groups);
+        Ok(GpuMesh { vertices, vertex_count, indices, index_count })
+    }
+
+    /// Same as [`generate_mesh_buffers`](Self::generate_mesh_buffers), but
+    /// the mesh is read back into an [IndexedMesh].
+    pub fn generate_mesh(&self, grid: &DenseGrid) -> crate::Result<IndexedMesh> {
+        let mesh = self.generate_mesh_buffers(grid)?;
+        let bytes = self.read_buffer(&mesh.vertices)?;
+        let vertices: &[[f32; 4]] = bytemuck::cast_slice(&bytes);
+        let verts = vertices[..mesh.vertex_count as usize].iter()
+            .map(|vertex| Vec3::from_slice(&vertex[..3]))
+            .collect();
+        let bytes = self.read_buffer(&mesh.indices)?;
+        let indices: &[u32] = bytemuck::cast_slice(&bytes);
+        let faces = indices[..mesh.index_count as usize].chunks_exact(3)
+            .map(|tri| [0, 1, 2].map(|index| tri[index] as usize))
+            .collect();
+        Ok(IndexedMesh {
+            verts,
+            faces,
+            normals: None,
+        })
+    }
+
+    /// A [GpuMesh] with no triangles. wgpu doesn't allow empty buffers, so
+    /// they hold a single zeroed element.
+    fn empty_mesh(&self) -> crate::Result<GpuMesh> {
+        Ok(GpuMesh {
+            vertices: self.storage_buffer("marching cubes vertices", &[0; 16], wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_SRC)?,
+            vertex_count: 0,
+            indices: self.storage_buffer("marching cubes indices", &[0; 4], wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_SRC)?,
+            index_count: 0,
+        })
+    }
+
+    /// Creates a storage buffer holding `contents`, or returns [`Error::Gpu`]
+    /// if it is too big for the device.
+    fn storage_buffer(&self, label: &str, contents: &[u8], usage: wgpu::BufferUsages) -> crate::Result<wgpu::Buffer> {
+        let limit = self.device.limits().max_storage_buffer_binding_size as usize;
+        if contents.len() > limit {
+            return Err(Error::Gpu(format!("{label} needs {} bytes, but the device allows {limit}", contents.len())));
+        }
+        Ok(self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            contents,
+            usage: wgpu::BufferUsages::STORAGE | usage,
+        }))
+    }
+
+    /// Runs `pipeline` over `groups` workgroups with the buffers in `entries`.
+    fn dispatch(&self, pipeline: &wgpu::ComputePipeline, entries: &[wgpu::BindGroupEntry], groups: UVec3) {
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.get_bind_group_layout(0),
+            entries,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None, timestamp_writes: None });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(groups.x, groups.y, groups.z);
+        }
+        self.queue.submit([encoder.finish()]);
+    }
+
+    /// Copies `buffer` back from the GPU, waiting for the queue to finish.
+    fn read_buffer(&self, buffer: &wgpu::Buffer) -> crate::Result<Vec<u8>> {
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: buffer.size(),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("readback") });
+        encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv()
+            .map_err(|err| Error::Gpu(err.to_string()))?
+            .map_err(|err| Error::Gpu(err.to_string()))?;
+        let bytes = slice.get_mapped_range().to_vec();
+        staging.unmap();
+        Ok(bytes)
+    }
+}
+
+fn compute_pipeline(device: &wgpu::Device, label: &str, source: &str, entry_point: &str) -> wgpu::ComputePipeline {
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(label),
+        layout: None,
+        module: &module,
+        entry_point,
+        compilation_options: Default::default(),
+        cache: None,
+    })
+}
+
+#[test]
+#[ignore = "needs a GPU adapter"]
+fn gpu_apply_tool_test() {
+    use glam::{ Vec3, Vec3A, Quat };
+
+    let gpu = GpuContext::new().unwrap();
+
+    let mut cpu_grid = DenseGrid::new(UVec3::splat(33), Vec3::ZERO, 0.5);
+    let mut gpu_grid = cpu_grid.clone();
+    let sphere = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::new(8.0, 7.5, 8.5));
+    let cuboid = Tool::new(Cuboid).scaled(Vec3::new(3.0, 6.0, 2.0)).rotated(Quat::from_rotation_y(0.4)).translated(Vec3A::new(6.0, 8.0, 9.0));
+    let soft = Tool::new(Weighted::new(Sphere, 0.3)).scaled(Vec3::splat(4.0)).translated(Vec3A::new(12.0, 12.0, 12.0));
+
+    cpu_grid.apply_tool(sphere, Action::Place);
+    gpu.apply_tool(&mut gpu_grid, sphere, Action::Place).unwrap();
+    cpu_grid.apply_tool(cuboid, Action::Remove);
+    gpu.apply_tool(&mut gpu_grid, cuboid, Action::Remove).unwrap();
+    cpu_grid.apply_tool(soft, Action::Place);
+    gpu.apply_tool(&mut gpu_grid, soft, Action::Place).unwrap();
+
+    assert!(cpu_grid.values().iter().zip(gpu_grid.values()).all(|(cpu, gpu)| (cpu - gpu).abs() < 1e-5));
+    assert!(gpu_grid.values().iter().any(|&value| value > 0.0));
+}
+
+#[test]
+#[ignore = "needs a GPU adapter"]
+fn gpu_generate_mesh_test() {
+    use glam::{ Vec3, Vec3A };
+    use crate::UnindexedMesh;
+
+    let gpu = GpuContext::new().unwrap();
+
+    let mut grid = DenseGrid::new(UVec3::new(20, 17, 18), Vec3::new(-1.0, 0.0, 2.0), 0.5);
+    grid.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::new(3.5, 4.0, 6.0)), Action::Place);
+    grid.apply_tool(Tool::new(Cuboid).scaled(Vec3::splat(1.5)).translated(Vec3A::new(5.0, 4.0, 6.0)), Action::Remove);
+
+    let cpu = grid.generate_mesh();
+    let indexed = gpu.generate_mesh(&grid).unwrap();
+    assert!(!cpu.faces.is_empty());
+    assert_eq!(indexed.faces.len(), cpu.faces.len());
+
+    // Every vertex is used, and shared by the triangles around it
+    let mut used = vec![false; indexed.verts.len()];
+    indexed.faces.iter().flatten().for_each(|&index| used[index] = true);
+    assert!(used.iter().all(|&used| used));
+    assert!(indexed.verts.len() < indexed.faces.len());
+    let gpu_mesh = UnindexedMesh {
+        faces: indexed.faces.iter().map(|face| face.map(|index| indexed.verts[index])).collect(),
+        normals: None,
+    };
+
+    // The triangles come out in another order, so compare their totals
+    let area = |mesh: &UnindexedMesh| mesh.faces.iter().map(|[a, b, c]| (*b - *a).cross(*c - *a).length() / 2.0).sum::<f32>();
+    let centroid = |mesh: &UnindexedMesh| mesh.faces.iter().map(|[a, b, c]| (*a + *b + *c) / 3.0).sum::<Vec3>();
+    assert!((area(&gpu_mesh) - area(&cpu)).abs() < 1e-3);
+    assert!(centroid(&gpu_mesh).abs_diff_eq(centroid(&cpu), 1e-2));
+
+    let empty = DenseGrid::new(UVec3::splat(4), Vec3::ZERO, 1.0);
+    let empty = gpu.generate_mesh_buffers(&empty).unwrap();
+    assert_eq!((empty.vertex_count, empty.index_count), (0, 0));
+    let flat = DenseGrid::new(UVec3::new(4, 1, 4), Vec3::ZERO, 1.0);
+    assert!(gpu.generate_mesh(&flat).unwrap().faces.is_empty());
+}
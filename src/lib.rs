@@ -4,10 +4,37 @@ use glam::{ Vec3, vec3 };
 
 pub use glam;
 
+#[cfg(feature = "physics")]
+pub use parry3d;
+
+#[cfg(feature = "mint")]
+pub use mint;
+
+#[cfg(feature = "nalgebra")]
+pub use nalgebra;
+
+#[cfg(feature = "image")]
+pub use image;
+
+#[cfg(feature = "gltf")]
+pub use gltf;
+
+#[cfg(feature = "gpu")]
+pub use wgpu;
+
+mod error;
+pub use error::*;
+
 mod mesh;
 pub use mesh::*;
 
-mod marching_cubes;
+#[cfg(feature = "gltf")]
+mod gltf_import;
+
+pub mod marching_cubes;
+
+#[cfg(feature = "gpu")]
+pub mod gpu;
 
 /// The corners of a unit cube in Z-index order.
 pub const CUBE_CORNERS: [Vec3; 8] = [
@@ -21,6 +48,87 @@ pub const CUBE_CORNERS: [Vec3; 8] = [
     vec3(1.0,1.0,1.0),
 ];
 
+/// Returns true if the isosurface passes through a cell with the given
+/// corner values.
+/// 
+/// Values above zero are inside the surface, matching Marching Cubes, so
+/// this is true when at least one corner is inside and one is outside.
+pub fn intersects_surface(values: &[f32; 8]) -> bool {
+    let inside = values.iter().filter(|&&val| val > 0.0).count();
+    inside != 0 && inside != values.len()
+}
+
+/// One of the three coordinate axes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// The index of the axis in a vector (0 for X, 1 for Y, 2 for Z).
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// The unit vector pointing along the axis.
+    pub fn unit(self) -> Vec3 {
+        match self {
+            Axis::X => Vec3::X,
+            Axis::Y => Vec3::Y,
+            Axis::Z => Vec3::Z,
+        }
+    }
+
+    /// The other two axes, in XYZ order.
+    pub fn others(self) -> (Axis, Axis) {
+        match self {
+            Axis::X => (Axis::Y, Axis::Z),
+            Axis::Y => (Axis::X, Axis::Z),
+            Axis::Z => (Axis::X, Axis::Y),
+        }
+    }
+}
+
 pub mod naive_octree;
 
-pub mod utils;
\ No newline at end of file
+pub mod arena_octree;
+
+mod builder;
+pub use builder::*;
+
+mod dense;
+pub use dense::*;
+
+mod chunked;
+pub use chunked::*;
+
+mod pipeline;
+pub use pipeline::*;
+
+mod lod;
+pub use lod::*;
+
+mod mesh_cache;
+pub use mesh_cache::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod double_buffered;
+pub use double_buffered::*;
+
+mod sync_terrain;
+pub use sync_terrain::*;
+
+mod worker;
+pub use worker::*;
+
+pub mod navmesh;
+
+pub mod utils;
+
+#[cfg(feature = "stats")]
+pub mod stats;
\ No newline at end of file
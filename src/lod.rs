@@ -0,0 +1,565 @@
+use glam::{ Vec2, Vec3 };
+use ahash::{ AHashMap, AHashSet };
+use arrayvec::ArrayVec;
+use std::{ cell::RefCell, collections::BTreeMap };
+use crate::{
+    naive_octree::{ NaiveOctree, NaiveOctreeCell },
+    tool::{ AABB, IntersectType::DoesNotIntersect },
+    marching_cubes::march_cube,
+    utils,
+    ChunkKey, ChunkedTerrain, UnindexedMesh, CUBE_CORNERS,
+};
+
+/// Settings for choosing the mesh depth of chunks by distance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LodSettings {
+    /// The depth of chunks within `lod_distance` of a viewer.
+    pub max_depth: u8,
+    /// The depth of the furthest chunks.
+    pub min_depth: u8,
+    /// Every time the distance to the nearest viewer doubles past this,
+    /// the depth is reduced by one.
+    pub lod_distance: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            min_depth: 2,
+            lod_distance: 32.0,
+        }
+    }
+}
+
+/// Identifies a viewer registered with a [LodScheduler].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ViewerId(u32);
+
+/// Chooses a mesh depth for each chunk of a [ChunkedTerrain] from the
+/// distance to the nearest viewer, and keeps track of which chunks need
+/// to be remeshed.
+/// 
+/// Where neighbouring chunks are meshed at different depths, the finer
+/// chunk is stitched to the coarser one by [`generate_mesh`](Self::generate_mesh),
+/// so their meshes meet without cracks. Since the seams depend on both
+/// chunks, a chunk that changes has its neighbours remeshed too.
+#[derive(Debug, Clone, Default)]
+pub struct LodScheduler {
+    pub settings: LodSettings,
+    viewers: AHashMap<ViewerId, Vec3>,
+    next_viewer: u32,
+    /// The depth each chunk was last meshed at.
+    meshed: AHashMap<ChunkKey, u8>,
+}
+
+impl LodScheduler {
+    pub fn new(settings: LodSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_viewer(&mut self, pos: Vec3) -> ViewerId {
+        let id = ViewerId(self.next_viewer);
+        self.next_viewer += 1;
+        self.viewers.insert(id, pos);
+        id
+    }
+
+    pub fn set_viewer(&mut self, id: ViewerId, pos: Vec3) {
+        self.viewers.insert(id, pos);
+    }
+
+    pub fn remove_viewer(&mut self, id: ViewerId) {
+        self.viewers.remove(&id);
+    }
+
+    /// Returns the depth the chunk at `key` should be meshed at.
+    pub fn target_depth(&self, key: ChunkKey, chunk_size: f32) -> u8 {
+        let settings = &self.settings;
+        let aabb = key.aabb(chunk_size);
+        let distance = self.viewers.values()
+            .map(|&viewer| aabb.distance_squared(viewer))
+            .fold(f32::INFINITY, f32::min)
+            .sqrt();
+
+        if distance <= settings.lod_distance {
+            return settings.max_depth;
+        }
+        let reduction = (distance / settings.lod_distance).log2().ceil().min(u8::MAX as f32) as u8;
+        settings.max_depth.saturating_sub(reduction).max(settings.min_depth)
+    }
+
+    /// Forgets the meshes of `keys`, such as the chunks returned by
+    /// [`ChunkedTerrain::apply_tool`], so they are remeshed by the next
+    /// [`update`](Self::update).
+    pub fn mark_dirty(&mut self, keys: impl IntoIterator<Item = ChunkKey>) {
+        for key in keys {
+            self.meshed.remove(&key);
+        }
+    }
+
+    /// Returns the chunks that need to be remeshed, with the depth to mesh
+    /// them at, and records them as meshed. Chunks that no longer exist
+    /// are forgotten.
+    /// 
+    /// Besides the chunks that were marked dirty or have a new target
+    /// depth, the chunks next to them are returned, as well as the chunks
+    /// next to removed ones, since their seams have changed.
+    pub fn update(&mut self, terrain: &ChunkedTerrain) -> Vec<(ChunkKey, u8)> {
+        let chunk_size = terrain.chunk_size();
+        let mut changed: AHashSet<ChunkKey> = self.meshed.keys()
+            .filter(|key| terrain.chunk(**key).is_none())
+            .copied()
+            .collect();
+        self.meshed.retain(|key, _| terrain.chunk(*key).is_some());
+        changed.extend(terrain.chunks()
+            .map(|(key, _)| key)
+            .filter(|&key| self.meshed.get(&key) != Some(&self.target_depth(key, chunk_size))));
+
+        let mut requests: Vec<(ChunkKey, u8)> = changed.into_iter()
+            .flat_map(neighbourhood)
+            .filter(|&key| terrain.chunk(key).is_some())
+            .collect::<AHashSet<ChunkKey>>()
+            .into_iter()
+            .map(|key| (key, self.target_depth(key, chunk_size)))
+            .collect();
+        requests.sort_unstable();
+
+        for &(key, depth) in requests.iter() {
+            self.meshed.insert(key, depth);
+        }
+        requests
+    }
+
+    /// Generates the mesh of the chunk at `key` at its target depth, in
+    /// world space. Where the neighbouring chunks are meshed coarser, the
+    /// cracks along the seam are filled in to match them.
+    pub fn generate_mesh(&self, terrain: &ChunkedTerrain, key: ChunkKey) -> Option<UnindexedMesh> {
+        let chunk_size = terrain.chunk_size();
+        terrain.chunk(key)?;
+        let chunks: Vec<(&NaiveOctree, u8)> = neighbourhood(key)
+            .filter_map(|key| terrain.chunk(key).map(|chunk| (chunk, self.target_depth(key, chunk_size))))
+            .collect();
+        let max_depth = chunks.iter().map(|&(_, depth)| depth).max().unwrap_or(0);
+        let mut mesher = SeamMesher::new(chunk_size / 2f32.powi(max_depth.min(NaiveOctree::MAX_DEPTH) as i32));
+        for (chunk, depth) in chunks {
+            mesher.add(chunk, move |_| depth);
+        }
+
+        // The neighbouring cells are only meshed to find the cracks
+        let aabb = key.aabb(chunk_size);
+        let mut faces = Vec::new();
+        let mut neighbours = Vec::new();
+        mesher.for_each_leaf(aabb.expanded_by(mesher.epsilon), &mut |leaf| {
+            if aabb.contains(leaf.center()) {
+                faces.extend(mesher.march(leaf));
+            }
+            else {
+                neighbours.extend(mesher.march(leaf));
+            }
+        });
+        let all: Vec<[Vec3; 3]> = faces.iter().chain(neighbours.iter()).copied().collect();
+        for (inside, tris) in mesher.fill_cracks(&all) {
+            if aabb.contains(inside) {
+                faces.extend(tris);
+            }
+        }
+        Some(UnindexedMesh { faces, normals: None })
+    }
+
+    /// Calls [`update`](Self::update) and generates the requested meshes.
+    pub fn generate_meshes(&mut self, terrain: &ChunkedTerrain) -> Vec<(ChunkKey, UnindexedMesh)> {
+        self.update(terrain).into_iter()
+            .filter_map(|(key, _)| self.generate_mesh(terrain, key).map(|mesh| (key, mesh)))
+            .collect()
+    }
+}
+
+/// The chunk at `key` and the 26 chunks around it.
+fn neighbourhood(key: ChunkKey) -> impl Iterator<Item = ChunkKey> {
+    let ChunkKey(x, y, z) = key;
+    (-1..=1).flat_map(move |dz| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| ChunkKey(x + dx, y + dy, z + dz))))
+}
+
+/// Settings for [`NaiveOctree::generate_clipmap`](crate::naive_octree::NaiveOctree::generate_clipmap).
+/// 
+/// Ring 0 is a cube of half-width `inner_extent` around the focus,
+/// and each following ring doubles that width. The last ring covers
+/// everything outside of the others.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipmapSettings {
+    /// The depth of ring 0.
+    pub max_depth: u8,
+    /// The number of rings.
+    pub levels: u8,
+    pub inner_extent: f32,
+}
+
+impl Default for ClipmapSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            levels: 4,
+            inner_extent: 16.0,
+        }
+    }
+}
+
+impl ClipmapSettings {
+    /// Returns the innermost ring that `aabb` overlaps.
+    pub fn level(&self, focus: Vec3, aabb: AABB) -> u8 {
+        let last = self.levels.saturating_sub(1);
+        let distance = (aabb.closest_point(focus) - focus).abs().max_element();
+        if distance <= self.inner_extent {
+            return 0;
+        }
+        ((distance / self.inner_extent).log2().ceil().min(u8::MAX as f32) as u8).min(last)
+    }
+}
+
+/// Gives the depth to mesh the cells of a Terrain at from their AABB.
+type DepthFn<'a> = Box<dyn Fn(AABB) -> u8 + 'a>;
+
+/// A point just inside the smaller cells next to a crack, and its edges.
+type Crack = (Vec3, Vec<(usize, usize)>);
+
+/// Meshes a Terrain with cells of different sizes next to each other,
+/// such as when the depth depends on the distance to a viewer, without
+/// leaving cracks between them.
+/// 
+/// Corners of smaller cells that lie on the face or edge of a bigger cell
+/// take the value interpolated from the bigger cell, so both sides cross
+/// it at the same points. Between those points the smaller cells can still
+/// bend where the bigger cell is flat, and the holes that leaves lie flat
+/// on the face of the bigger cell, so [`fill_cracks`](Self::fill_cracks)
+/// fills them in.
+pub(crate) struct SeamMesher<'a> {
+    /// The Terrains being meshed, with the depth of the cells to mesh
+    /// given their AABB.
+    parts: Vec<(&'a NaiveOctree, DepthFn<'a>)>,
+    /// Far smaller than any cell. Used to look past corners and to weld
+    /// vertices.
+    pub epsilon: f32,
+    /// The values of the corners seen so far.
+    values: RefCell<AHashMap<[i64; 3], f32>>,
+}
+
+impl<'a> SeamMesher<'a> {
+    /// `cell_size` is the size of the smallest cell that will be meshed.
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            parts: Vec::new(),
+            epsilon: cell_size * 1e-3,
+            values: Default::default(),
+        }
+    }
+
+    /// Adds `terrain`, meshing each of its cells at `depth(cell_aabb)`
+    /// unless it is a leaf.
+    pub fn add(&mut self, terrain: &'a NaiveOctree, depth: impl Fn(AABB) -> u8 + 'a) {
+        self.parts.push((terrain, Box::new(depth)));
+    }
+
+    /// Returns the cell meshed at `pos` and its AABB.
+    fn cell_at(&self, pos: Vec3) -> Option<(&'a NaiveOctreeCell, AABB)> {
+        let (terrain, depth) = self.parts.iter().find(|(terrain, _)| terrain.aabb().contains(pos))?;
+        let (mut cell, mut aabb) = (terrain.root(), terrain.aabb());
+        let mut current_depth = 0;
+        while current_depth < depth(aabb) {
+            let Some(children) = cell.children.as_ref() else {
+                break;
+            };
+            let center = aabb.center();
+            let index = (pos.x >= center.x) as u8 | ((pos.y >= center.y) as u8) << 1 | ((pos.z >= center.z) as u8) << 2;
+            cell = &children[index as usize];
+            aabb = aabb.octree_child(index);
+            current_depth += 1;
+        }
+        Some((cell, aabb))
+    }
+
+    /// Calls `func` with the AABB of every cell to mesh that overlaps `region`.
+    pub fn for_each_leaf(&self, region: AABB, func: &mut impl FnMut(AABB)) {
+        fn visit(cell: &NaiveOctreeCell, cell_aabb: AABB, current_depth: u8, region: AABB, depth: &dyn Fn(AABB) -> u8, func: &mut impl FnMut(AABB)) {
+            if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+                return;
+            }
+            if current_depth < depth(cell_aabb) {
+                if let Some(children) = cell.children.as_ref() {
+                    children.iter()
+                        .zip(cell_aabb.octree_subdivide())
+                        .for_each(|(child, aabb)| visit(child, aabb, current_depth+1, region, depth, func));
+                    return;
+                }
+            }
+            func(cell_aabb);
+        }
+
+        for (terrain, depth) in self.parts.iter() {
+            visit(terrain.root(), terrain.aabb(), 0, region, depth, func);
+        }
+    }
+
+    /// The value meshed at `pos`, a corner of some cell.
+    fn value(&self, pos: Vec3) -> f32 {
+        let key = (pos / self.epsilon).round().to_array().map(|x| x as i64);
+        if let Some(&value) = self.values.borrow().get(&key) {
+            return value;
+        }
+
+        // The biggest cell touching the corner decides its value
+        let (cell, aabb) = CUBE_CORNERS.iter()
+            .filter_map(|&offset| self.cell_at(pos + (offset * 2.0 - Vec3::ONE) * self.epsilon))
+            .max_by(|(_, a), (_, b)| a.size.x.total_cmp(&b.size.x))
+            .expect("Tried to find the value of a corner outside of the Terrain!");
+        let corners = aabb.calculate_corners();
+        let value = match corners.iter().position(|corner| corner.abs_diff_eq(pos, self.epsilon)) {
+            Some(corner) => cell.values[corner],
+            None => {
+                let values = corners.map(|corner| self.value(corner));
+                utils::trilinear(&values, ((pos - aabb.start) / aabb.size).clamp(Vec3::ZERO, Vec3::ONE))
+            },
+        };
+        self.values.borrow_mut().insert(key, value);
+        value
+    }
+
+    /// Uses Marching Cubes to generate the triangles of the cell at `aabb`.
+    pub fn march(&self, aabb: AABB) -> ArrayVec<[Vec3; 3], 5> {
+        let corners = aabb.calculate_corners();
+        march_cube(&corners, &corners.map(|corner| self.value(corner)))
+    }
+
+    /// Returns the triangles filling each crack between the cells that
+    /// `faces` were marched from, along with a point just inside the
+    /// smaller cells next to it. Holes on the outside of the Terrain are
+    /// left open.
+    pub fn fill_cracks(&self, faces: &[[Vec3; 3]]) -> Vec<(Vec3, Vec<[Vec3; 3]>)> {
+        let (verts, tris) = weld(faces, self.epsilon);
+
+        // Every crack lies on the face of the bigger cell next to it, so the
+        // open edges are sorted by that face before they are chained
+        let quantize = |pos: Vec3| (pos / self.epsilon).round().to_array().map(|x| x as i64);
+        let mut cracks: BTreeMap<([i64; 3], usize), Crack> = BTreeMap::new();
+        for (a, b) in boundary_edges(&tris) {
+            let (start, end) = (verts[a], verts[b]);
+            let Some(axis) = (0..3).find(|&axis| (start[axis] - end[axis]).abs() <= self.epsilon) else {
+                continue;
+            };
+            let middle = (start + end) * 0.5;
+            let mut normal = Vec3::ZERO;
+            normal[axis] = self.epsilon;
+            let (Some((_, above)), Some((_, below))) = (self.cell_at(middle + normal), self.cell_at(middle - normal)) else {
+                continue;
+            };
+            let (bigger, inside) = if above.size.x <= below.size.x { (below, normal) } else { (above, -normal) };
+            let mut face = bigger.center();
+            face[axis] = middle[axis];
+            if above == below || ((bigger.start[axis] - face[axis]).abs() > self.epsilon && (bigger.end()[axis] - face[axis]).abs() > self.epsilon) {
+                continue;
+            }
+            cracks.entry((quantize(face), axis)).or_insert((face + inside, Vec::new())).1.push((a, b));
+        }
+
+        cracks.into_iter().flat_map(|((_, axis), (inside, edges))| {
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            edge_loops(&edges).into_iter().map(|hole| {
+                let flat: Vec<Vec2> = hole.iter().map(|&index| Vec2::new(verts[index][u], verts[index][v])).collect();
+                let tris = triangulate(&flat).into_iter().map(|tri| tri.map(|index| verts[hole[index]])).collect();
+                (inside, tris)
+            }).collect::<Vec<_>>()
+        }).collect()
+    }
+}
+
+/// Merges the vertices of `faces` that are within `tolerance` of each
+/// other, and returns them with the triangles indexing them. Triangles
+/// that collapse are dropped.
+fn weld(faces: &[[Vec3; 3]], tolerance: f32) -> (Vec<Vec3>, Vec<[usize; 3]>) {
+    let mut verts: Vec<Vec3> = Vec::new();
+    let mut buckets: AHashMap<[i64; 3], Vec<usize>> = AHashMap::new();
+    let mut tris = Vec::with_capacity(faces.len());
+    for face in faces {
+        let tri = face.map(|pos| {
+            let [x, y, z] = (pos / tolerance).floor().to_array().map(|x| x as i64);
+            for key in (-1..=1).flat_map(|dz| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| [x + dx, y + dy, z + dz]))) {
+                if let Some(&index) = buckets.get(&key).and_then(|bucket| bucket.iter().find(|&&index| verts[index].distance(pos) <= tolerance)) {
+                    return index;
+                }
+            }
+            buckets.entry([x, y, z]).or_default().push(verts.len());
+            verts.push(pos);
+            verts.len() - 1
+        });
+        if tri[0] != tri[1] && tri[1] != tri[2] && tri[2] != tri[0] {
+            tris.push(tri);
+        }
+    }
+    (verts, tris)
+}
+
+/// Returns the edges of `tris` that no other triangle runs back along.
+fn boundary_edges(tris: &[[usize; 3]]) -> Vec<(usize, usize)> {
+    let mut counts: BTreeMap<(usize, usize), usize> = BTreeMap::new();
+    for tri in tris {
+        for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+            *counts.entry((a, b)).or_default() += 1;
+        }
+    }
+    counts.iter()
+        .flat_map(|(&(a, b), &count)| {
+            let back = counts.get(&(b, a)).copied().unwrap_or(0);
+            std::iter::repeat_n((a, b), count.saturating_sub(back))
+        })
+        .collect()
+}
+
+/// Chains the [boundary_edges] `edges` into the holes they surround,
+/// running the other way around than the triangles bordering them, so
+/// triangulating a hole faces the same way as its surroundings. Chains
+/// that don't close are dropped.
+fn edge_loops(edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut next: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &(a, b) in edges {
+        next.entry(b).or_default().push(a);
+    }
+
+    let mut loops = Vec::new();
+    let starts: Vec<usize> = next.keys().copied().collect();
+    for start in starts {
+        let mut hole = vec![start];
+        let mut vert = start;
+        while let Some(following) = next.get_mut(&vert).and_then(Vec::pop) {
+            if following == start {
+                loops.push(std::mem::replace(&mut hole, vec![start]));
+            }
+            // A hole touching itself is split in two
+            else if let Some(index) = hole.iter().position(|&other| other == following) {
+                loops.push(hole.split_off(index));
+                hole.push(following);
+            }
+            else {
+                hole.push(following);
+            }
+            vert = following;
+        }
+    }
+    loops
+}
+
+/// Splits the polygon `points` into triangles by ear clipping, keeping
+/// its winding.
+fn triangulate(points: &[Vec2]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+    let area: f32 = points.iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.perp_dot(*b))
+        .sum();
+    let winding = if area < 0.0 { -1.0 } else { 1.0 };
+    let turn = |a: usize, b: usize, c: usize| (points[b] - points[a]).perp_dot(points[c] - points[b]) * winding;
+    let inside = |pos: usize, [a, b, c]: [usize; 3]| turn(a, b, pos) > 0.0 && turn(b, c, pos) > 0.0 && turn(c, a, pos) > 0.0;
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut tris = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear_at = |index: usize| [remaining[(index + count - 1) % count], remaining[index], remaining[(index + 1) % count]];
+        // If no ear is found, the polygon is degenerate and any cut will do
+        let index = (0..count)
+            .find(|&index| {
+                let ear = ear_at(index);
+                turn(ear[0], ear[1], ear[2]) > 0.0 && remaining.iter().all(|&pos| ear.contains(&pos) || !inside(pos, ear))
+            })
+            .unwrap_or(0);
+        tris.push(ear_at(index));
+        remaining.remove(index);
+    }
+    tris.push([remaining[0], remaining[1], remaining[2]]);
+    tris
+}
+
+#[test]
+fn lod_scheduler_test() {
+    use crate::tool::{ Tool, Sphere, Action };
+    use glam::Vec3A;
+
+    let mut terrain = ChunkedTerrain::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 4);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::new(105.0, 5.0, 5.0)), Action::Place, 4);
+
+    let mut scheduler = LodScheduler::new(LodSettings { max_depth: 6, min_depth: 1, lod_distance: 10.0 });
+    let viewer = scheduler.add_viewer(Vec3::splat(5.0));
+    assert_eq!(scheduler.update(&terrain), vec![(ChunkKey(0, 0, 0), 6), (ChunkKey(10, 0, 0), 2)]);
+    assert!(scheduler.update(&terrain).is_empty());
+
+    // Moving the viewer swaps the detail levels
+    scheduler.set_viewer(viewer, Vec3::new(105.0, 5.0, 5.0));
+    assert_eq!(scheduler.generate_meshes(&terrain).len(), 2);
+
+    scheduler.mark_dirty([ChunkKey(0, 0, 0)]);
+    assert_eq!(scheduler.update(&terrain), vec![(ChunkKey(0, 0, 0), 2)]);
+}
+
+#[test]
+fn lod_seam_test() {
+    use crate::tool::{ Tool, Sphere, Action };
+    use glam::Vec3A;
+
+    let open_edges = |faces: &[[Vec3; 3]]| boundary_edges(&weld(faces, 1e-4).1).len();
+
+    // A sphere split between two chunks two depths apart
+    let mut terrain = ChunkedTerrain::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::new(10.0, 5.0, 5.0)), Action::Place, 5);
+    let mut scheduler = LodScheduler::new(LodSettings { max_depth: 5, min_depth: 1, lod_distance: 5.0 });
+    let viewer = scheduler.add_viewer(Vec3::new(-2.0, 5.0, 5.0));
+    assert_eq!(scheduler.update(&terrain), vec![(ChunkKey(0, 0, 0), 5), (ChunkKey(1, 0, 0), 3)]);
+
+    let unstitched: Vec<[Vec3; 3]> = [(ChunkKey(0, 0, 0), 5), (ChunkKey(1, 0, 0), 3)].into_iter()
+        .flat_map(|(key, depth)| terrain.generate_mesh(key, depth).unwrap().faces)
+        .collect();
+    assert!(open_edges(&unstitched) > 0);
+
+    let stitched: Vec<[Vec3; 3]> = [ChunkKey(0, 0, 0), ChunkKey(1, 0, 0)].into_iter()
+        .flat_map(|key| scheduler.generate_mesh(&terrain, key).unwrap().faces)
+        .collect();
+    assert_eq!(open_edges(&stitched), 0);
+
+    // Moving the viewer changes the depth of one chunk, and its neighbour's
+    // seam is remeshed along with it
+    scheduler.set_viewer(viewer, Vec3::new(-7.0, 5.0, 5.0));
+    assert_eq!(scheduler.update(&terrain), vec![(ChunkKey(0, 0, 0), 4), (ChunkKey(1, 0, 0), 3)]);
+}
+
+#[test]
+fn clipmap_test() {
+    use crate::{ naive_octree::NaiveOctree, tool::{ Tool, Sphere, Action } };
+    use glam::Vec3A;
+
+    let mut octree = NaiveOctree::new(64.0);
+    octree.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(30.0)).translated(Vec3A::splat(32.0)), Action::Place, 7);
+
+    let settings = ClipmapSettings { max_depth: 7, levels: 3, inner_extent: 8.0 };
+    let rings = octree.generate_clipmap(Vec3::new(32.0, 62.0, 32.0), &settings);
+    assert_eq!(rings.len(), 3);
+    assert!(rings.iter().all(|ring| !ring.faces.is_empty()));
+
+    // The inner ring is finer than the full mesh at the outer ring's depth
+    let coarse = octree.generate_mesh(5).faces.len();
+    let total: usize = rings.iter().map(|ring| ring.faces.len()).sum();
+    assert!(total > coarse && total < octree.generate_mesh(7).faces.len());
+
+    // The sphere is closed, and so are the rings put together
+    let open_edges = |faces: &[[Vec3; 3]]| boundary_edges(&weld(faces, 1e-4).1).len();
+    let mut unstitched = vec![Vec::new(); 3];
+    octree.root().generate_clipmap(&mut unstitched, &settings, Vec3::new(32.0, 62.0, 32.0), 0, octree.aabb());
+    assert!(open_edges(&unstitched.concat()) > 0);
+    let faces: Vec<[Vec3; 3]> = rings.iter().flat_map(|ring| ring.faces.iter().copied()).collect();
+    assert_eq!(open_edges(&faces), 0);
+}
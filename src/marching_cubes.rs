@@ -1,7 +1,14 @@
-use glam::Vec3;
-use lerp::Lerp;
+//! The Marching Cubes algorithm used to mesh each cell.
+//! 
+//! Corners are in the Z-order of [`CUBE_CORNERS`](crate::CUBE_CORNERS)
+//! and edges in the order of [EDGE_CORNERS]. Corner values above zero are
+//! inside the surface, and triangles are wound counter-clockwise when
+//! seen from outside.
+
+use glam::{ Vec3, Vec3A };
 use arrayvec::ArrayVec;
 
+/// The edges crossed by the surface for each combination of inside corners.
 pub const EDGE_TABLE: [u16; 256] = [
 	0x0  , 0x103, 0x809, 0x90a, 0x130, 0x33 , 0x939, 0x83a, 
 	0x890, 0x993, 0x99 , 0x19a, 0x9a0, 0x8a3, 0x1a9, 0xaa , 
@@ -54,6 +61,7 @@ pub const EDGE_TABLE: [u16; 256] = [
 /// |/                  |/
 /// 0---------0--------
 /// ```
+/// The triangles, as triplets of edges, for each combination of inside corners.
 pub const TRI_TABLE: [&[usize]; 256] = [
 	&[],
 	&[ 0,  8,  1],
@@ -313,6 +321,7 @@ pub const TRI_TABLE: [&[usize]; 256] = [
 	&[],
 ];
 
+/// Finds the point between two corners where the value crosses zero.
 pub fn vert_interp(point1: (Vec3, f32), point2: (Vec3, f32)) -> Vec3
 {
     if point1.1.abs() < 0.00001 { return point1.0; }
@@ -320,55 +329,75 @@ pub fn vert_interp(point1: (Vec3, f32), point2: (Vec3, f32)) -> Vec3
     if (point1.1 - point2.1).abs() < 0.00001 { return point1.0; }
 
     let t = (-point1.1 / (point2.1 - point1.1)).clamp(0.0,1.0);
-    return Lerp::lerp(point1.0, point2.0, t);
+    Vec3A::from(point1.0).lerp(Vec3A::from(point2.0), t).into()
 }
 
-pub fn march_cube(corners: &[Vec3; 8], values: &[f32; 8]) -> ArrayVec<[Vec3; 3], 5> {
-	let mut cubeindex = 0;
-        if values[0] > 0.0 { cubeindex |= 1;   }
-        if values[1] > 0.0 { cubeindex |= 2;   }
-        if values[2] > 0.0 { cubeindex |= 4;   }
-        if values[3] > 0.0 { cubeindex |= 8;   }
-        if values[4] > 0.0 { cubeindex |= 16;  }
-        if values[5] > 0.0 { cubeindex |= 32;  }
-        if values[6] > 0.0 { cubeindex |= 64;  }
-        if values[7] > 0.0 { cubeindex |= 128; }
-
-        let interp = |index1, index2| -> Vec3 {
-            vert_interp(
-                (corners[index1], values[index1]),
-                (corners[index2], values[index2])
-            )
-        };
+/// The pair of corners each of the 12 cube edges connects, using the
+/// corner order of [`CUBE_CORNERS`](crate::CUBE_CORNERS).
+pub const EDGE_CORNERS: [(usize, usize); 12] = [
+    (0, 1), (0, 4), (4, 5), (5, 1),
+    (2, 3), (2, 6), (6, 7), (7, 3),
+    (0, 2), (4, 6), (5, 7), (1, 3),
+];
 
-		let mut faces = ArrayVec::new();
+/// The result of [march_cube_indexed].
+#[derive(Debug, Clone, Default)]
+pub struct MarchedCube {
+    /// The vertex on each edge crossed by the surface, by edge index.
+    /// See [EDGE_CORNERS].
+    pub edge_verts: [Option<Vec3>; 12],
+    /// Triangles as edge indices into `edge_verts`.
+    pub faces: ArrayVec<[usize; 3], 5>,
+}
 
-        if EDGE_TABLE[cubeindex] != 0 {
-            let mut edge_verts = [None; 12];
+/// Same as [march_cube], but the vertices are keyed by the cube edge they
+/// lie on, so a grid can share them between neighbouring cubes.
+pub fn march_cube_indexed(corners: &[Vec3; 8], values: &[f32; 8]) -> MarchedCube {
+    let cubeindex = values.iter().enumerate()
+        .filter(|(_, &value)| value > 0.0)
+        .fold(0, |index, (corner, _)| index | 1 << corner);
 
-            if (EDGE_TABLE[cubeindex] & 1   ) != 0 { edge_verts[0 ] = Some(interp(0, 1)) }
-            if (EDGE_TABLE[cubeindex] & 2   ) != 0 { edge_verts[1 ] = Some(interp(0, 4)) }
-            if (EDGE_TABLE[cubeindex] & 4   ) != 0 { edge_verts[2 ] = Some(interp(4, 5)) }
-            if (EDGE_TABLE[cubeindex] & 8   ) != 0 { edge_verts[3 ] = Some(interp(5, 1)) }
+    let mut cube = MarchedCube::default();
+    let edges = EDGE_TABLE[cubeindex];
+    for (edge, &(index1, index2)) in EDGE_CORNERS.iter().enumerate() {
+        if edges & (1 << edge) != 0 {
+            cube.edge_verts[edge] = Some(vert_interp(
+                (corners[index1], values[index1]),
+                (corners[index2], values[index2])
+            ));
+        }
+    }
+    cube.faces.extend(TRI_TABLE[cubeindex].chunks_exact(3).map(|tri| [tri[0], tri[1], tri[2]]));
+    cube
+}
 
-            if (EDGE_TABLE[cubeindex] & 16  ) != 0 { edge_verts[4 ] = Some(interp(2, 3)) }
-            if (EDGE_TABLE[cubeindex] & 32  ) != 0 { edge_verts[5 ] = Some(interp(2, 6)) }
-            if (EDGE_TABLE[cubeindex] & 64  ) != 0 { edge_verts[6 ] = Some(interp(6, 7)) }
-            if (EDGE_TABLE[cubeindex] & 128 ) != 0 { edge_verts[7 ] = Some(interp(7, 3)) }
+/// Generates the triangles of the isosurface passing through one cube.
+/// 
+/// `corners` and `values` are the positions and values of the cube's
+/// corners, in the Z-order of [`CUBE_CORNERS`](crate::CUBE_CORNERS). A
+/// corner is inside of the surface if its value is above zero, and
+/// triangles are wound counter-clockwise when seen from outside.
+pub fn march_cube(corners: &[Vec3; 8], values: &[f32; 8]) -> ArrayVec<[Vec3; 3], 5> {
+    let cube = march_cube_indexed(corners, values);
+    cube.faces.iter()
+        .map(|face| face.map(|edge| cube.edge_verts[edge].expect("Tried to use invalid edge vertex!")))
+        .collect()
+}
 
-            if (EDGE_TABLE[cubeindex] & 256 ) != 0 { edge_verts[8 ] = Some(interp(0, 2)) }
-            if (EDGE_TABLE[cubeindex] & 512 ) != 0 { edge_verts[9 ] = Some(interp(4, 6)) }
-            if (EDGE_TABLE[cubeindex] & 1024) != 0 { edge_verts[10] = Some(interp(5, 7)) }
-            if (EDGE_TABLE[cubeindex] & 2048) != 0 { edge_verts[11] = Some(interp(1, 3)) }
+#[test]
+fn march_cube_indexed_test() {
+    use crate::CUBE_CORNERS;
 
-            TRI_TABLE[cubeindex].chunks_exact(3).for_each(|tri_idx| {
-                faces.push([
-					edge_verts[tri_idx[0] as usize].expect("Tried to use invalid edge vertex!"),
-					edge_verts[tri_idx[1] as usize].expect("Tried to use invalid edge vertex!"),
-					edge_verts[tri_idx[2] as usize].expect("Tried to use invalid edge vertex!"),
-				]);
-            })
-		};
+    // Only corner 0 is inside, so the surface crosses the edges touching it
+    let values = [1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0];
+    let cube = march_cube_indexed(&CUBE_CORNERS, &values);
+    assert_eq!(cube.faces.len(), 1);
+    let mut edges = cube.faces[0];
+    edges.sort_unstable();
+    assert_eq!(edges, [0, 1, 8]);
+    assert_eq!(cube.edge_verts[0], Some(Vec3::new(0.5, 0.0, 0.0)));
 
-		faces
-}
\ No newline at end of file
+    // The triangle faces away from the inside corner
+    let [a, b, c] = march_cube(&CUBE_CORNERS, &values)[0];
+    assert!((b - a).cross(c - a).dot(Vec3::ONE) > 0.0);
+}
@@ -1,9 +1,10 @@
-use glam::Vec3;
+use glam::{ Vec2, Vec3 };
 use std::{
     path::Path,
-    io::{ BufWriter, Write },
+    io::{ self, BufWriter, Write },
     fs::File,
     writeln,
+    sync::Mutex,
 };
 use ahash::AHashMap;
 use ordered_float::NotNan;
@@ -42,8 +43,104 @@ pub struct IndexedMesh {
     pub normals: Option<Normals>,
 }
 
+/// Vertex positions and `u32` triangle indices, as returned by
+/// [`IndexedMesh::to_trimesh_data`].
+pub type TriMeshData = (Vec<[f32; 3]>, Vec<[u32; 3]>);
+
+/// Recycles mesh allocations across repeated meshing calls.
+/// 
+/// Meshes passed to [`recycle`](Self::recycle) or
+/// [`recycle_indexed`](Self::recycle_indexed) give their buffers back to
+/// the pool, and later meshing calls reuse them instead of allocating.
+/// The pool can be shared between threads.
+#[derive(Debug, Default)]
+pub struct MeshBufferPool {
+    faces: Mutex<Vec<Vec<[Vec3; 3]>>>,
+    verts: Mutex<Vec<Vec<Vec3>>>,
+    indices: Mutex<Vec<Vec<[usize; 3]>>>,
+}
+
+impl MeshBufferPool {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn take<T>(buffers: &Mutex<Vec<Vec<T>>>) -> Vec<T> {
+        buffers.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    fn give<T>(buffers: &Mutex<Vec<Vec<T>>>, mut buffer: Vec<T>) {
+        if buffer.capacity() > 0 {
+            buffer.clear();
+            buffers.lock().unwrap().push(buffer);
+        }
+    }
+
+    /// Returns an empty triangle buffer, reusing a recycled one if available.
+    pub fn take_faces(&self) -> Vec<[Vec3; 3]> {
+        Self::take(&self.faces)
+    }
+
+    /// Returns an empty vertex buffer, reusing a recycled one if available.
+    pub fn take_verts(&self) -> Vec<Vec3> {
+        Self::take(&self.verts)
+    }
+
+    /// Returns an empty index buffer, reusing a recycled one if available.
+    pub fn take_indices(&self) -> Vec<[usize; 3]> {
+        Self::take(&self.indices)
+    }
+
+    /// Returns the buffers of `mesh` to the pool.
+    pub fn recycle(&self, mesh: UnindexedMesh) {
+        Self::give(&self.faces, mesh.faces);
+        if let Some(normals) = mesh.normals {
+            Self::give(&self.verts, normals.into_normals());
+        }
+    }
+
+    /// Returns the buffers of `mesh` to the pool.
+    pub fn recycle_indexed(&self, mesh: IndexedMesh) {
+        Self::give(&self.verts, mesh.verts);
+        Self::give(&self.indices, mesh.faces);
+        if let Some(normals) = mesh.normals {
+            Self::give(&self.verts, normals.into_normals());
+        }
+    }
+
+    /// The number of buffers currently held by the pool.
+    pub fn len(&self) -> usize {
+        self.faces.lock().unwrap().len() +
+        self.verts.lock().unwrap().len() +
+        self.indices.lock().unwrap().len()
+    }
+
+    /// Returns true if the pool holds no buffers.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 impl UnindexedMesh {
+    /// Returns the total area of the triangles of the mesh.
+    pub fn surface_area(&self) -> f32 {
+        self.faces.iter().map(|[a, b, c]| (*b - *a).cross(*c - *a).length() * 0.5).sum()
+    }
+
+    /// Returns the triangles of the mesh with their vertices converted to
+    /// any type constructible from [Vec3], such as `mint` or `nalgebra`
+    /// points when the respective features are enabled.
+    pub fn faces_as<T: From<Vec3>>(&self) -> Vec<[T; 3]> {
+        self.faces.iter().map(|face| face.map(T::from)).collect()
+    }
+
     pub fn index(self) -> IndexedMesh {
+        self.index_pooled(&MeshBufferPool::new())
+    }
+
+    /// Same as [`index`](Self::index), but the vertex and index buffers are
+    /// taken from `pool` and this mesh's triangle buffer is returned to it.
+    pub fn index_pooled(self, pool: &MeshBufferPool) -> IndexedMesh {
 
         #[derive(Hash, PartialEq, Eq)]
         struct NotNanVec3 {
@@ -73,14 +170,16 @@ impl UnindexedMesh {
         }
 
         let mut index_map: AHashMap<NotNanVec3, usize> = Default::default();
-        let mut face_indices: Vec<[usize; 3]> = Vec::with_capacity(self.faces.len());
-        self.faces.into_iter().for_each(|face_verts| {
+        let mut face_indices = pool.take_indices();
+        face_indices.reserve(self.faces.len());
+        self.faces.iter().for_each(|face_verts| {
             let face = face_verts.map(|vert| {
                 let size = index_map.len();
                 *index_map.entry(vert.into()).or_insert(size)
             });
             face_indices.push(face);
         });
+        MeshBufferPool::give(&pool.faces, self.faces);
 
         let normals = {
             use Normals::*;
@@ -98,135 +197,349 @@ impl UnindexedMesh {
             }
         };
         
-        let mut verts = Vec::with_capacity(index_map.len());
+        let mut verts = pool.take_verts();
         verts.resize(index_map.len(), Vec3::ZERO);
 
         index_map.into_iter().for_each(|(vert, i)| {
             verts[i] = vert.vec3();
         });
 
-        return IndexedMesh {
+        IndexedMesh {
             verts,
             faces: face_indices,
             normals,
-        };
+        }
     }
 
-    pub fn write_obj_to_file(&self, filename: impl AsRef<Path>)
+    /// Writes the mesh to `filename` in the Wavefront OBJ format.
+    pub fn write_obj_to_file(&self, filename: impl AsRef<Path>) -> crate::Result<()>
     {
-        let mut file = BufWriter::new(File::create(filename).unwrap());
-        writeln!(file, "# Mesh generated by rusty_ground\n# UnindexedMesh").unwrap();
-        self.faces.iter().flatten().for_each(|&vert| {
-            writeln!(file, "v {} {} {}", vert.x, vert.y, vert.z).unwrap();
-        });
+        let file = BufWriter::new(File::create(filename)?);
+        Ok(self.write_obj(file)?)
+    }
 
-        writeln!(file).unwrap();
+    /// Returns the mesh in the Wavefront OBJ format.
+    pub fn to_obj_string(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_obj(&mut buffer).expect("Writing to a Vec can't fail");
+        String::from_utf8(buffer).expect("OBJ output is always UTF-8")
+    }
 
-        if let Some(normals) = &self.normals {
-            use Normals::*;
-            match &normals {
-                Face(_) => writeln!(file, "# Normals: Face").unwrap(),
-                Vertex(_) => writeln!(file, "# Normals: Vertex").unwrap(),
-            }
-            let (Vertex(normals) | Face(normals)) = normals;
-            normals.iter().for_each(|&normal| {
-                writeln!(file, "vn {} {} {}", normal.x, normal.y, normal.z).unwrap();
-            });
-            writeln!(file).unwrap();
-        }
-        else
-        {
-            writeln!(file, "# Normals: None\n").unwrap();
+    /// Writes the mesh to `writer` in the Wavefront OBJ format.
+    /// 
+    /// This doesn't touch the filesystem, so it can be used to export
+    /// meshes in memory, such as on `wasm32-unknown-unknown`.
+    pub fn write_obj(&self, mut writer: impl Write) -> io::Result<()>
+    {
+        writeln!(writer, "# Mesh generated by rusty_ground\n# UnindexedMesh")?;
+        for vert in self.faces.iter().flatten() {
+            writeln!(writer, "v {} {} {}", vert.x, vert.y, vert.z)?;
         }
-        
+
+        writeln!(writer)?;
+
+        write_obj_normals(&mut writer, &self.normals)?;
+
         let face_iter = (0..self.faces.len())
-            .map(|x| ((x*3)+1, (x*3)+2, (x*3)+3))
-            .enumerate();
+            .map(|x| ((x*3)+1, (x*3)+2, (x*3)+3));
 
         match self.normals {
             Some(Normals::Face(_)) => {
-                face_iter.for_each(|(i, face)| {
-                    writeln!(file, "f {}//{3} {}//{3} {}//{3}",
+                for (i, face) in face_iter.enumerate() {
+                    writeln!(writer, "f {}//{3} {}//{3} {}//{3}",
                             face.0,
                             face.1,
                             face.2,
                             i+1
-                        ).unwrap();
-                });
+                        )?;
+                }
             },
             Some(Normals::Vertex(_)) => {
-                face_iter.for_each(|(_, face)| {
-                    writeln!(file, "f {0}//{0}, {1}//{1}, {2}//{2}",
+                for face in face_iter {
+                    writeln!(writer, "f {0}//{0} {1}//{1} {2}//{2}",
                             face.0,
                             face.1,
                             face.2,
-                        ).unwrap();
-                });
+                        )?;
+                }
             },
             None => {
-                face_iter.for_each(|(_, face)| {
-                    writeln!(file, "f {} {} {}", face.0, face.1, face.2).unwrap()
-                })
+                for face in face_iter {
+                    writeln!(writer, "f {} {} {}", face.0, face.1, face.2)?;
+                }
             }
         }
+
+        writer.flush()
     }
 }
 
-impl IndexedMesh {
-    pub fn write_obj_to_file(&self, filename: impl AsRef<Path>)
+/// A mesh with texture coordinates, such as a decal projected onto the
+/// terrain by [`NaiveOctree::project_decal`](crate::naive_octree::NaiveOctree::project_decal).
+#[derive(Debug, Clone, Default)]
+pub struct DecalMesh {
+    pub verts: Vec<Vec3>,
+    /// The texture coordinates of each vertex, from 0.0 to 1.0.
+    pub uvs: Vec<Vec2>,
+    pub faces: Vec<[usize; 3]>,
+}
+
+/// An [UnindexedMesh] with a color for each vertex, such as a debug mesh.
+#[derive(Debug, Clone)]
+pub struct ColoredMesh {
+    pub mesh: UnindexedMesh,
+    /// The RGB color of each vertex of each face, from 0.0 to 1.0.
+    pub colors: Vec<Vec3>,
+}
+
+impl ColoredMesh {
+    /// Writes the mesh to `filename` in the Wavefront OBJ format.
+    pub fn write_obj_to_file(&self, filename: impl AsRef<Path>) -> crate::Result<()>
     {
-        let mut file = BufWriter::new(File::create(filename).unwrap());
-        writeln!(file, "# Mesh generated by rusty_ground\n# IndexedMesh").unwrap();
-        self.verts.iter().for_each(|&vert| {
-            writeln!(file, "v {} {} {}", vert.x, vert.y, vert.z).unwrap();
-        });
+        let file = BufWriter::new(File::create(filename)?);
+        Ok(self.write_obj(file)?)
+    }
 
-        writeln!(file).unwrap();
+    /// Returns the mesh in the Wavefront OBJ format.
+    pub fn to_obj_string(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_obj(&mut buffer).expect("Writing to a Vec can't fail");
+        String::from_utf8(buffer).expect("OBJ output is always UTF-8")
+    }
 
-        if let Some(normals) = &self.normals {
-            use Normals::*;
-            match &normals {
-                Face(_) => writeln!(file, "# Normals: Face").unwrap(),
-                Vertex(_) => writeln!(file, "# Normals: Vertex").unwrap(),
+    /// Writes the mesh to `writer` in the Wavefront OBJ format, with the
+    /// colors following each vertex position (`v x y z r g b`), which is
+    /// understood by most OBJ importers.
+    pub fn write_obj(&self, mut writer: impl Write) -> io::Result<()>
+    {
+        writeln!(writer, "# Mesh generated by rusty_ground\n# ColoredMesh")?;
+        for (vert, color) in self.mesh.faces.iter().flatten().zip(self.colors.iter()) {
+            writeln!(writer, "v {} {} {} {} {} {}", vert.x, vert.y, vert.z, color.x, color.y, color.z)?;
+        }
+
+        writeln!(writer)?;
+
+        for x in 0..self.mesh.faces.len() {
+            writeln!(writer, "f {} {} {}", (x*3)+1, (x*3)+2, (x*3)+3)?;
+        }
+
+        writer.flush()
+    }
+}
+
+/// Writes the normals section of an OBJ file.
+fn write_obj_normals(writer: &mut impl Write, normals: &Option<Normals>) -> io::Result<()> {
+    if let Some(normals) = normals {
+        use Normals::*;
+        match &normals {
+            Face(_) => writeln!(writer, "# Normals: Face")?,
+            Vertex(_) => writeln!(writer, "# Normals: Vertex")?,
+        }
+        for normal in normals.normals() {
+            writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+        }
+        writeln!(writer)
+    }
+    else
+    {
+        writeln!(writer, "# Normals: None\n")
+    }
+}
+
+impl IndexedMesh {
+    /// Returns a hash of the vertices and faces of the mesh that is the
+    /// same on every platform and run, for comparing generated meshes
+    /// against known good ones.
+    /// 
+    /// Vertices are rounded to 1/1024 of a unit first, so tiny floating
+    /// point differences don't change the hash.
+    pub fn geometry_hash(&self) -> u64 {
+        // FNV-1a, since the std and ahash hashers aren't stable
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut write = |value: u32| {
+            for byte in value.to_le_bytes() {
+                hash ^= byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
             }
-            let (Vertex(normals) | Face(normals)) = normals;
-            normals.iter().for_each(|&normal| {
-                writeln!(file, "vn {} {} {}", normal.x, normal.y, normal.z).unwrap();
-            });
-            writeln!(file).unwrap();
+        };
+        write(self.verts.len() as u32);
+        for vert in self.verts.iter() {
+            (*vert * 1024.0).round().as_ivec3().to_array().into_iter().for_each(|coord| write(coord as u32));
         }
-        else
-        {
-            writeln!(file, "# Normals: None\n").unwrap();
+        write(self.faces.len() as u32);
+        for face in self.faces.iter() {
+            face.iter().for_each(|&index| write(index as u32));
         }
-        
-        let face_iter = self.faces.iter().enumerate();
+        hash
+    }
+
+    /// Returns the vertices of the mesh converted to any type constructible
+    /// from [Vec3], such as `mint` or `nalgebra` points when the respective
+    /// features are enabled.
+    pub fn verts_as<T: From<Vec3>>(&self) -> Vec<T> {
+        self.verts.iter().map(|&vert| T::from(vert)).collect()
+    }
+
+    /// Returns the vertex positions and triangle indices of the mesh in the
+    /// flat layout expected by physics engines for triangle mesh colliders.
+    /// 
+    /// Returns [`Error::IndexOverflow`](crate::Error::IndexOverflow) if
+    /// an index doesn't fit in a `u32`.
+    pub fn to_trimesh_data(&self) -> crate::Result<TriMeshData> {
+        let verts = self.verts.iter().map(|vert| vert.to_array()).collect();
+        Ok((verts, self.faces_u32()?))
+    }
+
+    /// Builds a parry3d [TriMesh](parry3d::shape::TriMesh) collider from
+    /// the mesh.
+    /// 
+    /// Returns [`Error::IndexOverflow`](crate::Error::IndexOverflow) if
+    /// an index doesn't fit in a `u32`.
+    #[cfg(feature = "physics")]
+    pub fn to_trimesh(&self) -> crate::Result<parry3d::shape::TriMesh> {
+        let faces = self.faces_u32()?;
+        Ok(parry3d::shape::TriMesh::new(self.parry_points(), faces))
+    }
+
+    /// Splits the mesh into approximately convex pieces using V-HACD, which
+    /// are much cheaper to simulate than a triangle mesh for dynamic bodies
+    /// such as debris chunks.
+    /// 
+    /// Returns [`Error::IndexOverflow`](crate::Error::IndexOverflow) if
+    /// an index doesn't fit in a `u32`.
+    #[cfg(feature = "physics")]
+    pub fn convex_decomposition(&self, params: &parry3d::transformation::vhacd::VHACDParameters) -> crate::Result<Vec<parry3d::shape::ConvexPolyhedron>> {
+        use parry3d::{ transformation::vhacd::VHACD, shape::ConvexPolyhedron };
+
+        let faces = self.faces_u32()?;
+        let decomposition = VHACD::decompose(params, &self.parry_points(), &faces, false);
+        Ok(decomposition.compute_convex_hulls(params.convex_hull_downsampling)
+            .into_iter()
+            .filter_map(|(points, _)| ConvexPolyhedron::from_convex_hull(&points))
+            .collect())
+    }
+
+    #[cfg(feature = "physics")]
+    fn parry_points(&self) -> Vec<parry3d::math::Point<f32>> {
+        self.verts.iter().map(|vert| parry3d::math::Point::new(vert.x, vert.y, vert.z)).collect()
+    }
+
+    fn faces_u32(&self) -> crate::Result<Vec<[u32; 3]>> {
+        let index_u32 = |index: usize| u32::try_from(index)
+            .map_err(|_| crate::Error::IndexOverflow(index));
+        self.faces.iter()
+            .map(|&[a, b, c]| Ok([index_u32(a)?, index_u32(b)?, index_u32(c)?]))
+            .collect()
+    }
+
+    /// Writes the mesh to `filename` in the Wavefront OBJ format.
+    pub fn write_obj_to_file(&self, filename: impl AsRef<Path>) -> crate::Result<()>
+    {
+        let file = BufWriter::new(File::create(filename)?);
+        Ok(self.write_obj(file)?)
+    }
+
+    /// Returns the mesh in the Wavefront OBJ format.
+    pub fn to_obj_string(&self) -> String {
+        let mut buffer = Vec::new();
+        self.write_obj(&mut buffer).expect("Writing to a Vec can't fail");
+        String::from_utf8(buffer).expect("OBJ output is always UTF-8")
+    }
+
+    /// Writes the mesh to `writer` in the Wavefront OBJ format.
+    /// 
+    /// This doesn't touch the filesystem, so it can be used to export
+    /// meshes in memory, such as on `wasm32-unknown-unknown`.
+    pub fn write_obj(&self, mut writer: impl Write) -> io::Result<()>
+    {
+        writeln!(writer, "# Mesh generated by rusty_ground\n# IndexedMesh")?;
+        for vert in self.verts.iter() {
+            writeln!(writer, "v {} {} {}", vert.x, vert.y, vert.z)?;
+        }
+
+        writeln!(writer)?;
+
+        write_obj_normals(&mut writer, &self.normals)?;
+
+        let face_iter = self.faces.iter();
 
         match self.normals {
             Some(Normals::Face(_)) => {
-                face_iter.for_each(|(i, face)| {
-                    writeln!(file, "f {}//{3} {}//{3} {}//{3}",
+                for (i, face) in face_iter.enumerate() {
+                    writeln!(writer, "f {}//{3} {}//{3} {}//{3}",
                             face[0]+1,
                             face[1]+1,
                             face[2]+1,
                             i+1
-                        ).unwrap();
-                });
+                        )?;
+                }
             },
             Some(Normals::Vertex(_)) => {
-                face_iter.for_each(|(_, face)| {
-                    writeln!(file, "f {0}//{0}, {1}//{1}, {2}//{2}",
+                for face in face_iter {
+                    writeln!(writer, "f {0}//{0} {1}//{1} {2}//{2}",
                             face[0]+1,
                             face[1]+1,
                             face[2]+1,
-                        ).unwrap();
-                });
+                        )?;
+                }
             },
             None => {
-                face_iter.for_each(|(_, face)| {
-                    writeln!(file, "f {} {} {}", face[0]+1, face[1]+1, face[2]+1).unwrap()
-                })
+                for face in face_iter {
+                    writeln!(writer, "f {} {} {}", face[0]+1, face[1]+1, face[2]+1)?;
+                }
             }
         }
+
+        writer.flush()
     }
-}
\ No newline at end of file
+}
+#[test]
+fn obj_string_test() {
+    use glam::vec3;
+
+    let mesh = UnindexedMesh {
+        faces: vec![[vec3(0.0,0.0,0.0), vec3(1.0,0.0,0.0), vec3(0.0,1.0,0.0)]],
+        normals: None,
+    };
+    let obj = mesh.index().to_obj_string();
+    let lines: Vec<&str> = obj.lines().filter(|line| !line.is_empty() && !line.starts_with('#')).collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[..3].iter().all(|line| line.starts_with("v ")));
+    assert_eq!(lines[3], "f 1 2 3");
+}
+
+#[test]
+fn mesh_buffer_pool_test() {
+    use glam::vec3;
+
+    let pool = MeshBufferPool::new();
+    let mut faces = pool.take_faces();
+    faces.push([vec3(0.0,0.0,0.0), vec3(1.0,0.0,0.0), vec3(0.0,1.0,0.0)]);
+    let capacity = faces.capacity();
+
+    let mesh = UnindexedMesh { faces, normals: None }.index_pooled(&pool);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(mesh.faces, vec![[0, 1, 2]]);
+
+    let faces = pool.take_faces();
+    assert!(faces.is_empty());
+    assert_eq!(faces.capacity(), capacity);
+
+    pool.recycle_indexed(mesh);
+    assert_eq!(pool.len(), 2);
+}
+
+#[test]
+fn write_obj_error_test() {
+    let mesh = UnindexedMesh { faces: Vec::new(), normals: None };
+    assert!(matches!(mesh.write_obj_to_file("missing_directory/mesh.obj"), Err(crate::Error::Io(_))));
+}
+
+#[test]
+fn trimesh_index_overflow_test() {
+    let mesh = IndexedMesh { verts: vec![Vec3::ZERO; 3], faces: vec![[0, 1, 2]], normals: None };
+    assert_eq!(mesh.to_trimesh_data().unwrap().1, vec![[0, 1, 2]]);
+
+    let mesh = IndexedMesh { verts: vec![Vec3::ZERO; 3], faces: vec![[0, 1, usize::MAX]], normals: None };
+    assert!(matches!(mesh.to_trimesh_data(), Err(crate::Error::IndexOverflow(usize::MAX))));
+}
@@ -0,0 +1,103 @@
+use ahash::AHashMap;
+use crate::{ ChunkKey, ChunkedTerrain, UnindexedMesh };
+
+/// A mesh stored in a [MeshCache].
+#[derive(Debug, Clone)]
+pub struct CachedMesh {
+    pub mesh: UnindexedMesh,
+    /// The depth the mesh was generated at.
+    pub depth: u8,
+    /// The cache generation the mesh was generated in.
+    pub generation: u64,
+}
+
+/// Keeps the meshes of the chunks of a [ChunkedTerrain] between frames,
+/// so only chunks that were edited have to be meshed again.
+#[derive(Debug, Clone, Default)]
+pub struct MeshCache {
+    meshes: AHashMap<ChunkKey, CachedMesh>,
+    generation: u64,
+}
+
+impl MeshCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of times [`invalidate`](Self::invalidate) has been called.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get(&self, key: ChunkKey) -> Option<&CachedMesh> {
+        self.meshes.get(&key)
+    }
+
+    /// Drops the meshes of `keys`, such as the chunks returned by
+    /// [`ChunkedTerrain::apply_tool`].
+    pub fn invalidate(&mut self, keys: impl IntoIterator<Item = ChunkKey>) {
+        self.generation += 1;
+        for key in keys {
+            self.meshes.remove(&key);
+        }
+    }
+
+    /// Drops every mesh.
+    pub fn clear(&mut self) {
+        self.generation += 1;
+        self.meshes.clear();
+    }
+
+    /// Meshes every chunk in `keys` that isn't cached at `depth`, and
+    /// returns the keys that were meshed. Keys without a chunk are skipped.
+    pub fn update(&mut self, terrain: &ChunkedTerrain, keys: impl IntoIterator<Item = ChunkKey>, depth: u8) -> Vec<ChunkKey> {
+        let mut meshed = Vec::new();
+        for key in keys {
+            if self.meshes.get(&key).is_some_and(|cached| cached.depth == depth) {
+                continue;
+            }
+            if let Some(mesh) = terrain.generate_mesh(key, depth) {
+                self.meshes.insert(key, CachedMesh { mesh, depth, generation: self.generation });
+                meshed.push(key);
+            }
+        }
+        meshed
+    }
+
+    /// Joins the cached meshes of `keys` into one mesh. Keys that aren't
+    /// cached are skipped.
+    pub fn assemble(&self, keys: impl IntoIterator<Item = ChunkKey>) -> UnindexedMesh {
+        let faces = keys.into_iter()
+            .filter_map(|key| self.meshes.get(&key))
+            .flat_map(|cached| cached.mesh.faces.iter().copied())
+            .collect();
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+}
+
+#[test]
+fn mesh_cache_test() {
+    use crate::tool::{ Tool, Sphere, Action };
+    use glam::{ Vec3, Vec3A };
+
+    let mut terrain = ChunkedTerrain::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::splat(10.0)), Action::Place, 4);
+    let keys: Vec<ChunkKey> = terrain.chunks().map(|(key, _)| key).collect();
+
+    let mut cache = MeshCache::new();
+    assert_eq!(cache.update(&terrain, keys.iter().copied(), 4).len(), keys.len());
+    assert!(cache.update(&terrain, keys.iter().copied(), 4).is_empty());
+    let full = cache.assemble(keys.iter().copied());
+    assert!(!full.faces.is_empty());
+
+    let edited = terrain.apply_tool(Tool::new(Sphere).translated(Vec3A::new(12.0, 12.0, 12.0)), Action::Remove, 4);
+    cache.invalidate(edited.iter().copied());
+    assert_eq!(cache.generation(), 1);
+    let mut remeshed = cache.update(&terrain, keys.iter().copied(), 4);
+    remeshed.sort_unstable();
+    assert_eq!(remeshed, edited);
+    assert_eq!(cache.get(edited[0]).unwrap().generation, 1);
+}
@@ -1,10 +1,14 @@
 use crate::{
-    tool::{ Tool, ToolFunc, Action, AABB, IntersectType::* },
-    utils,
+    tool::{ Tool, ToolFunc, Action, AABB, Sphere, Swept, BrushPreset, RidgedNoise, NoiseParams, IntersectType::* },
+    utils::{ self, stat, span, span_record, Seed },
+};
+use glam::{ Vec2, Vec3, Vec3A, IVec3, Affine3A, Quat };
+use crate::{ UnindexedMesh, ColoredMesh, DecalMesh, MeshBufferPool, Axis, ClipmapSettings, DenseGrid, lod::SeamMesher, marching_cubes::march_cube };
+use std::{
+    borrow::Borrow,
+    sync::{ Arc, atomic::{ AtomicBool, AtomicIsize, AtomicUsize, Ordering } },
+    time::{ Duration, Instant },
 };
-use glam::Vec3;
-use crate::{ UnindexedMesh, marching_cubes::march_cube };
-use std::borrow::Borrow;
 
 #[cfg(feature = "multi-thread")]
 use lockfree::stack::Stack;
@@ -15,16 +19,27 @@ use rayon::prelude::*;
 /// 
 /// For most cases, you shouldn't have to work with this
 /// class directly, and should use [NaiveOctree] instead.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NaiveOctreeCell {
     pub values: [f32; 8],
-    pub children: Option<Box<[NaiveOctreeCell; 8]>>
+    /// The corner values of the fluid layer, such as water. These work
+    /// the same as `values`, and are edited through [Channel::Fluid].
+    pub fluid: [f32; 8],
+    /// The material of each corner, such as an index into a list of
+    /// textures. See [`NaiveOctree::material`].
+    pub materials: [u8; 8],
+    /// Cloning a cell shares its children instead of copying them. They
+    /// are copied on write with [`Arc::make_mut`], so only the cells along
+    /// the path to an edit stop being shared.
+    pub children: Option<Arc<[NaiveOctreeCell; 8]>>
 }
 
 impl Default for NaiveOctreeCell {
     fn default() -> Self {
         Self {
             values: [-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0,-1.0],
+            fluid: [-1.0; 8],
+            materials: [0; 8],
             children: None
         }
     }
@@ -37,20 +52,34 @@ impl NaiveOctreeCell {
         if self.children.is_some() {
             return;
         }
+        stat!(SUBDIVISIONS, 1);
 
         // Subdivide 8 points into 8 cells
         let points = utils::subdivide_cell(&self.values);
+        let fluid = utils::subdivide_cell(&self.fluid);
+        // New corners take the material of the most solid of the parent
+        // corners they lie between
+        let materials: [[u8; 8]; 8] = std::array::from_fn(|cell| std::array::from_fn(|corner| {
+            (0..8).filter(|&parent: &usize| (0..3).all(|axis| {
+                let lattice = (cell >> axis & 1) + (corner >> axis & 1);
+                lattice == 1 || lattice == 2 * (parent >> axis & 1)
+            }))
+            .min_by(|&a, &b| self.values[b].total_cmp(&self.values[a]))
+            .map_or(0, |parent| self.materials[parent])
+        }));
 
         // Create new cells
         // We have constructed all the corners needed for our 8 new cells.
         let make_cell = |cell: usize| -> NaiveOctreeCell {
                 NaiveOctreeCell {
                 values: points[cell],
+                    fluid: fluid[cell],
+                    materials: materials[cell],
                     children: None,
                 }
         };
 
-        let new_cells = Box::new([
+        let new_cells = Arc::new([
             make_cell(0),
             make_cell(1),
             make_cell(2),
@@ -66,7 +95,9 @@ impl NaiveOctreeCell {
 
     /// Removes this cell's children if they exist.
     pub fn collapse_cell(&mut self) {
-        self.children = None;
+        if self.children.take().is_some() {
+            stat!(COLLAPSES, 1);
+        }
     }
 
     /// Returns true if the cell has no children.
@@ -79,63 +110,490 @@ impl NaiveOctreeCell {
         self.children.is_some()
     }
 
+    /// Returns the number of cells in this cell's subtree, including itself.
+    pub fn cell_count(&self) -> usize {
+        1 + self.children.as_ref().map_or(0, |children| children.iter().map(Self::cell_count).sum())
+    }
+
     /// Returns true if this cell intersects the isosurface.
     /// 
-    /// If all of the cell's corner values are one sign (positive or negative),
-    /// then the cell is either inside (positive) or outside (negative) of the
-    /// isosurface. Otherwise, the cell is intersected by the isosurface.
+    /// If all of the cell's corners are inside (positive) or outside (zero
+    /// or negative) of the isosurface, then so is the cell. Otherwise, the
+    /// cell is intersected by the isosurface. See [`crate::intersects_surface`].
     pub fn intersects_surface(&self) -> bool {
-        self.values.windows(2).any(|vals| vals[0].signum() != vals[1].signum())
+        crate::intersects_surface(&self.values)
+    }
+
+    /// Returns the corner values of `channel`.
+    pub fn channel(&self, channel: Channel) -> &[f32; 8] {
+        match channel {
+            Channel::Terrain => &self.values,
+            Channel::Fluid => &self.fluid,
+        }
+    }
+
+    /// Returns the corner values of `channel` mutably.
+    pub fn channel_mut(&mut self, channel: Channel) -> &mut [f32; 8] {
+        match channel {
+            Channel::Terrain => &mut self.values,
+            Channel::Fluid => &mut self.fluid,
+        }
+    }
+
+    /// Returns true if this cell or a cell below it intersects the
+    /// isosurface of `channel`.
+    pub fn has_surface_below(&self, channel: Channel) -> bool {
+        crate::intersects_surface(self.channel(channel)) ||
+            self.children.as_ref().is_some_and(|children| children.iter().any(|child| child.has_surface_below(channel)))
+    }
+
+    /// Returns true if this cell's children are all leaves that can be
+    /// merged back into it.
+    /// 
+    /// Children are merged if, for every [Channel], none of them intersect
+    /// the isosurface, or every child corner is within `tolerance` of the
+    /// value interpolated from this cell's corners.
+    pub fn is_collapsible(&self, tolerance: f32) -> bool {
+        let Some(children) = self.children.as_ref() else {
+            return false;
+        };
+        if !children.iter().all(NaiveOctreeCell::is_leaf) {
+            return false;
+        }
+        Channel::ALL.into_iter().all(|channel| {
+            if children.iter().all(|child| !crate::intersects_surface(child.channel(channel))) {
+                return true;
+            }
+            tolerance > 0.0 && children.iter().enumerate().all(|(index, child)| {
+                child.channel(channel).iter().enumerate().all(|(corner, &value)| {
+                    let t = (crate::CUBE_CORNERS[index] + crate::CUBE_CORNERS[corner]) * 0.5;
+                    (utils::trilinear(self.channel(channel), t) - value).abs() <= tolerance
+                })
+            })
+        })
+    }
+
+    /// Returns the value at `pos` by interpolating the corner values of
+    /// the leaf containing it, descending no further than `max_depth`.
+    pub fn sample(&self, pos: Vec3, cell_aabb: AABB, current_depth: u8, max_depth: u8) -> f32 {
+        let (leaf, leaf_aabb) = self.leaf_containing(pos, cell_aabb, current_depth, max_depth);
+        let t = ((pos - leaf_aabb.start) / leaf_aabb.size).clamp(Vec3::ZERO, Vec3::ONE);
+        utils::trilinear(&leaf.values, t)
+    }
+
+    /// Same as [`sample`](Self::sample), for the values of `channel`.
+    pub fn sample_channel(&self, channel: Channel, pos: Vec3, cell_aabb: AABB, current_depth: u8, max_depth: u8) -> f32 {
+        let (leaf, leaf_aabb) = self.leaf_containing(pos, cell_aabb, current_depth, max_depth);
+        let t = ((pos - leaf_aabb.start) / leaf_aabb.size).clamp(Vec3::ZERO, Vec3::ONE);
+        utils::trilinear(leaf.channel(channel), t)
+    }
+
+    /// Returns the leaf containing `pos` and its AABB, descending no
+    /// further than `max_depth`.
+    pub fn leaf_containing(&self, pos: Vec3, cell_aabb: AABB, current_depth: u8, max_depth: u8) -> (&NaiveOctreeCell, AABB) {
+        if current_depth < max_depth {
+            if let Some(children) = self.children.as_ref() {
+                let center = cell_aabb.center();
+                let index = (pos.x >= center.x) as u8 | ((pos.y >= center.y) as u8) << 1 | ((pos.z >= center.z) as u8) << 2;
+                return children[index as usize].leaf_containing(pos, cell_aabb.octree_child(index), current_depth+1, max_depth);
+            }
+        }
+        (self, cell_aabb)
+    }
+
+    /// Subdivides every leaf overlapping `region` until it is `depth`
+    /// levels deep. Cells inside of `region` that are already deeper are
+    /// collapsed back to `depth`.
+    pub fn refine(&mut self, region: AABB, cell_aabb: AABB, current_depth: u8, depth: u8) {
+        self.refine_with(region, cell_aabb, current_depth, depth, &ApplyContext::new(&ApplyOptions::default(), None, &[]));
+    }
+
+    /// Recursive step of [`refine`](Self::refine). Frozen cells are left
+    /// alone, and new cells are taken from the budget of `ctx`.
+    fn refine_with(&mut self, region: AABB, cell_aabb: AABB, current_depth: u8, depth: u8, ctx: &ApplyContext) {
+        let intersect = region.intersect(cell_aabb);
+        if matches!(intersect, DoesNotIntersect) || ctx.is_frozen(cell_aabb) {
+            return;
+        }
+        if current_depth >= depth {
+            if matches!(intersect, Contains) && self.has_children() && !ctx.touches_frozen(cell_aabb) {
+                ctx.release(self.cell_count() - 1);
+                self.collapse_cell();
+            }
+            return;
+        }
+
+        if self.is_leaf() && !ctx.reserve(8) {
+            return;
+        }
+        self.subdivide_cell();
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            children.iter_mut()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.refine_with(region, aabb, current_depth+1, depth, ctx));
+        }
+    }
+
+    /// Subdivides every leaf overlapping `region` that intersects the
+    /// isosurface until it is `depth` levels deep. Unlike [`refine`](Self::refine),
+    /// deeper cells are left alone.
+    pub fn refine_surface(&mut self, region: AABB, cell_aabb: AABB, current_depth: u8, depth: u8) {
+        self.refine_surface_with(region, cell_aabb, current_depth, depth, &ApplyContext::new(&ApplyOptions::default(), None, &[]));
+    }
+
+    /// Recursive step of [`refine_surface`](Self::refine_surface). New
+    /// cells are taken from the budget of `ctx`.
+    fn refine_surface_with(&mut self, region: AABB, cell_aabb: AABB, current_depth: u8, depth: u8, ctx: &ApplyContext) {
+        if current_depth >= depth || matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        if self.is_leaf() {
+            if !self.intersects_surface() || !ctx.reserve(8) {
+                return;
+            }
+            self.subdivide_cell();
+        }
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            children.iter_mut()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.refine_surface_with(region, aabb, current_depth+1, depth, ctx));
+        }
+    }
+
+    /// Collapses the cells overlapping `region` whose children are all
+    /// leaves that don't intersect the isosurface, from the bottom up.
+    /// 
+    /// The corner values of each cell are updated from its children
+    /// first, so this can be used after editing leaf values directly.
+    pub fn collapse_region(&mut self, region: AABB, cell_aabb: AABB) {
+        self.collapse_region_with(region, cell_aabb, &ApplyContext::new(&ApplyOptions::default(), None, &[]));
+    }
+
+    /// Recursive step of [`collapse_region`](Self::collapse_region). Cells
+    /// overlapping a frozen region of `ctx` are kept.
+    fn collapse_region_with(&mut self, region: AABB, cell_aabb: AABB, ctx: &ApplyContext) {
+        if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            children.iter_mut()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.collapse_region_with(region, aabb, ctx));
+
+            // Each child shares one corner with this cell
+            for (index, child) in children.iter().enumerate() {
+                self.values[index] = child.values[index];
+                self.fluid[index] = child.fluid[index];
+                self.materials[index] = child.materials[index];
+            }
+
+            if self.is_collapsible(0.0) && !ctx.touches_frozen(cell_aabb) {
+                self.collapse_cell();
+                ctx.release(8);
+            }
+        }
+    }
+
+    /// Adds every [Violation] in this cell and its children to `violations`.
+    pub fn validate(&self, cell_aabb: AABB, violations: &mut Vec<Violation>) {
+        let values = || self.values.iter().chain(&self.fluid);
+        if values().any(|val| !val.is_finite()) {
+            violations.push(Violation::NonFinite(cell_aabb));
+        }
+        else if values().any(|val| val.abs() > 1.0) {
+            violations.push(Violation::OutOfRange(cell_aabb));
+        }
+
+        if let Some(children) = self.children.as_ref() {
+            if children.iter().enumerate().any(|(index, child)| {
+                child.values[index] != self.values[index] || child.fluid[index] != self.fluid[index] ||
+                    child.materials[index] != self.materials[index]
+            }) {
+                violations.push(Violation::CornerMismatch(cell_aabb));
+            }
+            if self.is_collapsible(0.0) {
+                violations.push(Violation::NotCollapsed(cell_aabb));
+            }
+            children.iter()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.validate(aabb, violations));
+        }
+    }
+
+    /// Fixes every [Violation] in this cell and its children, and returns
+    /// how many cells were changed.
+    /// 
+    /// Values that aren't finite are set to empty, values out of range are
+    /// clamped, and corner values are copied up from the children.
+    pub fn repair(&mut self) -> usize {
+        let mut repaired = 0;
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            repaired += children.iter_mut().map(NaiveOctreeCell::repair).sum::<usize>();
+        }
+
+        let old = (self.values, self.fluid, self.materials);
+        for value in self.values.iter_mut().chain(self.fluid.iter_mut()) {
+            *value = if value.is_finite() { value.clamp(-1.0, 1.0) } else { -1.0 };
+        }
+        if let Some(children) = self.children.as_ref() {
+            for (index, child) in children.iter().enumerate() {
+                self.values[index] = child.values[index];
+                self.fluid[index] = child.fluid[index];
+                self.materials[index] = child.materials[index];
+            }
+        }
+        if (self.values, self.fluid, self.materials) != old {
+            repaired += 1;
+        }
+        if self.is_collapsible(0.0) {
+            self.collapse_cell();
+            repaired += 1;
+        }
+        repaired
+    }
+
+    /// Reorders the corners and children of this cell and every cell below
+    /// it, so that index `i` takes what was at index `perm[i]`.
+    pub fn permute(&mut self, perm: &[usize; 8]) {
+        let values = self.values;
+        self.values = perm.map(|index| values[index]);
+        let fluid = self.fluid;
+        self.fluid = perm.map(|index| fluid[index]);
+        let materials = self.materials;
+        self.materials = perm.map(|index| materials[index]);
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            children.iter_mut().for_each(|child| child.permute(perm));
+            let mut old = std::mem::take(children);
+            *children = perm.map(|index| std::mem::take(&mut old[index]));
+        }
+    }
+
+    /// Adds the heights where the vertical line at `x`, `z` crosses the
+    /// isosurface inside of this cell to `heights`, in no particular order.
+    pub fn column_crossings(&self, x: f32, z: f32, cell_aabb: AABB, heights: &mut Vec<f32>) {
+        let end = cell_aabb.end();
+        if x < cell_aabb.start.x || x > end.x || z < cell_aabb.start.z || z > end.z {
+            return;
+        }
+        if let Some(children) = self.children.as_ref() {
+            children.iter()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.column_crossings(x, z, aabb, heights));
+            return;
+        }
+
+        // Trilinear interpolation is linear along a vertical line
+        let t = ((Vec3::new(x, 0.0, z) - cell_aabb.start) / cell_aabb.size).clamp(Vec3::ZERO, Vec3::ONE);
+        let bottom = utils::trilinear(&self.values, Vec3::new(t.x, 0.0, t.z));
+        let top = utils::trilinear(&self.values, Vec3::new(t.x, 1.0, t.z));
+        if (bottom > 0.0) != (top > 0.0) {
+            heights.push(cell_aabb.start.y + cell_aabb.size.y * bottom / (bottom - top));
+        }
+    }
+
+    /// Calls `func` with every leaf overlapping `region` and its AABB.
+    pub fn for_each_leaf_mut(&mut self, region: AABB, cell_aabb: AABB, func: &mut impl FnMut(&mut NaiveOctreeCell, AABB)) {
+        if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        match self.children.as_mut().map(Arc::make_mut) {
+            Some(children) => children.iter_mut()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.for_each_leaf_mut(region, aabb, func)),
+            None => func(self, cell_aabb),
+        }
+    }
+
+    /// Returns true if this cell intersects the isosurface, or has a
+    /// corner exactly on it, so a mesh vertex could lie on its boundary.
+    fn touches_surface(&self) -> bool {
+        self.values.iter().any(|&val| val >= 0.0) && self.values.iter().any(|&val| val <= 0.0)
+    }
+
+    /// Calls `func` with the AABB of every leaf overlapping `region` that
+    /// [touches the isosurface](Self::touches_surface).
+    fn for_each_surface_leaf(&self, region: AABB, cell_aabb: AABB, func: &mut impl FnMut(AABB)) {
+        if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        match self.children.as_ref() {
+            Some(children) => children.iter()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.for_each_surface_leaf(region, aabb, func)),
+            None if self.touches_surface() => func(cell_aabb),
+            None => {},
+        }
+    }
+
+    /// Sets the corner materials of the leaves visited by
+    /// [`for_each_surface_leaf`](Self::for_each_surface_leaf) from
+    /// `materials`, in the same order, and copies them up to the parents.
+    fn set_surface_materials(&mut self, region: AABB, cell_aabb: AABB, materials: &mut impl Iterator<Item = [Option<u8>; 8]>) {
+        if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            children.iter_mut()
+                .zip(cell_aabb.octree_subdivide())
+                .for_each(|(child, aabb)| child.set_surface_materials(region, aabb, materials));
+            for (index, child) in children.iter().enumerate() {
+                self.materials[index] = child.materials[index];
+            }
+        }
+        else if self.touches_surface() {
+            let painted = materials.next().unwrap_or_default();
+            for (material, painted) in self.materials.iter_mut().zip(painted) {
+                *material = painted.unwrap_or(*material);
+            }
+        }
+    }
+
+    /// Returns true if [`apply_tool`](Self::apply_tool) with the same
+    /// arguments wouldn't change this cell, because no leaf inside of
+    /// `aoe_aabb` would get new corner values or be subdivided.
+    #[allow(clippy::too_many_arguments)]
+    pub fn is_unchanged_by<F: ToolFunc>(
+        &self,
+        tool: &Tool<F>,
+        tool_aabb: AABB,
+        aoe_aabb: AABB,
+        action: Action,
+        channel: Channel,
+        cell_aabb: AABB,
+        current_depth: u8,
+        max_depth: u8
+    ) -> bool {
+        if matches!(aoe_aabb.intersect(cell_aabb), DoesNotIntersect) {
+            return true;
+        }
+        if let Some(children) = self.children.as_ref() {
+            return children.iter()
+                .zip(cell_aabb.octree_subdivide())
+                .all(|(child, aabb)| child.is_unchanged_by(tool, tool_aabb, aoe_aabb, action, channel, aabb, current_depth+1, max_depth));
+        }
+
+        let values = self.channel(channel);
+        let toolvals = tool.value8(cell_aabb.calculate_corners());
+        let weight = tool.weight();
+        let mut newvals = *values;
+        newvals.iter_mut().zip(toolvals).for_each(|(value, newval)| {
+            action.apply_weighted(value, newval, weight);
+        });
+        if newvals != *values {
+            return false;
+        }
+        // Cells inside of a convex tool are never subdivided, and the children
+        // of a cell that is already completely full or empty would be collapsed
+        let filled = tool.is_convex() && weight >= 1.0 && toolvals.iter().all(|&val| val > 0.0);
+        let saturated = match action {
+            Action::Place => values.iter().all(|&val| val >= 1.0),
+            Action::Remove => values.iter().all(|&val| val <= -1.0),
+        };
+        filled || saturated || current_depth >= max_depth ||
+            !tool_needs_subdivision(tool, tool_aabb, aoe_aabb, action, cell_aabb, &newvals)
+    }
+
+    /// Returns the largest difference between the values the children of
+    /// this cell would get from the tool, and the values interpolated from
+    /// `newvals`, the corner values after applying the tool.
+    fn subdivision_error<F: ToolFunc>(&self, tool: &Tool<F>, action: Action, channel: Channel, corners: &[Vec3; 8], newvals: &[f32; 8]) -> f32 {
+        stat!(TOOL_EVALUATIONS, 27);
+        let weight = tool.weight();
+        let mut error: f32 = 0.0;
+        for index in 0..27 {
+            let t = Vec3::new((index % 3) as f32, (index / 3 % 3) as f32, (index / 9) as f32) * 0.5;
+            let mut exact = utils::trilinear(self.channel(channel), t);
+            action.apply_weighted(&mut exact, tool.value(corners[0] + (corners[7] - corners[0]) * t), weight);
+            error = error.max((exact - utils::trilinear(newvals, t)).abs());
+        }
+        error
     }
 
     /// Handles applying to the current Cell and determining if children need subdivision.
     /// This is split from apply_tool and par_apply_tool to deduplicate code.
+    #[allow(clippy::too_many_arguments)]
     fn apply_tool_impl<F: ToolFunc>(
         &mut self,
         tool: &Tool<F>,
         tool_aabb: AABB,
         aoe_aabb: AABB,
         action: Action,
-        cell_aabb: AABB,
+        corners: &[Vec3; 8],
         current_depth: u8,
-        max_depth: u8
+        max_depth: u8,
+        ctx: &ApplyContext
     ) {
+        let cell_aabb = AABB { start: corners[0], size: corners[7] - corners[0] };
+        stat!(CELLS_VISITED, 1);
+        stat!(TOOL_EVALUATIONS, 8);
+
         // Store the results of tool application
         //
         // We need to compute these before subdivision to decide if we need
         // to subdivide, but we need to apply them after subdivision so it
         // doesn't muddy up the interpolation
-        let mut newvals = self.values;
-        cell_aabb.calculate_corners().into_iter().zip(newvals.iter_mut()).for_each(|(pos, value)| {
-            let newval = tool.value(pos);
-            action.apply_value(value, newval);
+        let channel = ctx.options.channel;
+        let mut newvals = *self.channel(channel);
+        let toolvals = tool.value8(*corners);
+        let weight = tool.weight();
+        newvals.iter_mut().zip(toolvals).for_each(|(value, newval)| {
+            action.apply_weighted(value, newval, weight);
         });
 
-        // TODO: Rewrite all these conditions for performance (if needed)
-        let diff_signs = newvals.windows(2).any(|vals| vals[0].signum() != vals[1].signum());
+        // Subdivision is decided from the values the tool asks for, so cells
+        // next to locked corners still get the detail of the edit
+        let toolresult = newvals;
+        let touches_locked = ctx.touches_locked(cell_aabb, action);
+        if touches_locked {
+            for (value, (old, corner)) in newvals.iter_mut().zip(self.channel(channel).iter().zip(corners)) {
+                if ctx.is_locked(*corner, action) {
+                    *value = *old;
+                }
+            }
+        }
 
-        let check_aabb = match action {
-            Action::Remove => aoe_aabb,
-            Action::Place => tool_aabb,
+        // If every corner is inside a convex tool, then so is the whole cell.
+        // Placing fills it and removing empties it, so no surface can remain
+        // inside and the children can be dropped without visiting them. A
+        // lighter weight only blends towards that, so the children are kept,
+        // and so are children holding the surface of the other channel.
+        let other = match channel {
+            Channel::Terrain => Channel::Fluid,
+            Channel::Fluid => Channel::Terrain,
         };
-        
+        if tool.is_convex() && weight >= 1.0 && !touches_locked && toolvals.iter().all(|&val| val > 0.0) &&
+            !self.children.as_ref().is_some_and(|children| children.iter().any(|child| child.has_surface_below(other)))
+        {
+            if self.has_children() {
+                ctx.release(self.cell_count() - 1);
+            }
+            self.collapse_cell();
+            *self.channel_mut(channel) = newvals;
+            return;
+        }
+
         // Check if subdivision is needed
-        if self.children.is_none() && current_depth < max_depth {
-            if (tool.is_convex() && (diff_signs || matches!(check_aabb.intersect(cell_aabb), ContainedBy | Intersects(_)))) ||
-                (tool.is_concave() && !matches!(aoe_aabb.intersect(cell_aabb), DoesNotIntersect))
+        if self.children.is_none() && current_depth < max_depth &&
+            tool_needs_subdivision(tool, tool_aabb, aoe_aabb, action, cell_aabb, &toolresult)
+        {
+            // Tool intersects but does not contain, the cell intersects the isosurface
+            // subdivide for more detail, unless the children would only repeat
+            // what this cell already interpolates
+            if (ctx.options.subdivision_tolerance <= 0.0 ||
+                self.subdivision_error(tool, action, channel, corners, &toolresult) > ctx.options.subdivision_tolerance) &&
+                ctx.reserve(8)
             {
-                // Tool intersects but does not contain, the cell intersects the isosurface
-                // subdivide for more detail
                 self.subdivide_cell();
             }
         }
 
-        self.values = newvals;
+        *self.channel_mut(channel) = newvals;
     }
 
     /// Applies the [Tool] to the Terrain with the given [Action].
     /// Will subdivide the Terrain if needed up to `max_depth`. This
     /// method is used by [`NaiveOctree::apply_tool`].
+    #[allow(clippy::too_many_arguments)]
     pub fn apply_tool<F: ToolFunc>(
         &mut self,
         tool: &Tool<F>,
@@ -146,18 +604,60 @@ impl NaiveOctreeCell {
         current_depth: u8,
         max_depth: u8
     ) {
-        self.apply_tool_impl(tool, tool_aabb, aoe_aabb, action, cell_aabb, current_depth, max_depth);
+        self.apply_tool_with_options(tool, tool_aabb, aoe_aabb, action, cell_aabb, current_depth, max_depth, &ApplyOptions::default());
+    }
+
+    /// Same as [`apply_tool`](Self::apply_tool), with the given [ApplyOptions].
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_tool_with_options<F: ToolFunc>(
+        &mut self,
+        tool: &Tool<F>,
+        tool_aabb: AABB,
+        aoe_aabb: AABB,
+        action: Action,
+        cell_aabb: AABB,
+        current_depth: u8,
+        max_depth: u8,
+        options: &ApplyOptions
+    ) {
+        let ctx = ApplyContext::new(options, None, &[]);
+        self.apply_tool_corners(tool, tool_aabb, aoe_aabb, action, cell_aabb.calculate_corners(), current_depth, max_depth, &ctx);
+    }
+
+    /// Recursive step of [`apply_tool`](Self::apply_tool). The corner
+    /// positions of each child are interpolated from the parent's corners
+    /// rather than being recalculated from scratch.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_tool_corners<F: ToolFunc>(
+        &mut self,
+        tool: &Tool<F>,
+        tool_aabb: AABB,
+        aoe_aabb: AABB,
+        action: Action,
+        corners: [Vec3; 8],
+        current_depth: u8,
+        max_depth: u8,
+        ctx: &ApplyContext
+    ) {
+        let cell_aabb = AABB { start: corners[0], size: corners[7] - corners[0] };
+        // The tool leaves every value outside of its area of effect as it
+        // is, so those cells aren't visited and stay shared with snapshots
+        if ctx.is_frozen(cell_aabb) || matches!(aoe_aabb.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        self.apply_tool_impl(tool, tool_aabb, aoe_aabb, action, &corners, current_depth, max_depth, ctx);
 
-        if let Some(children) = self.children.as_mut() {
-            let child_aabbs = cell_aabb.octree_subdivide();
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            let child_corners = utils::subdivide_cell_positions(&corners);
             // Recursive apply to each child cell
             children.iter_mut()
-                .zip(child_aabbs.into_iter())
-                .for_each(|(child, aabb)| child.apply_tool(tool, tool_aabb, aoe_aabb, action, aabb, current_depth+1, max_depth));
+                .zip(child_corners)
+                .for_each(|(child, corners)| child.apply_tool_corners(tool, tool_aabb, aoe_aabb, action, corners, current_depth+1, max_depth, ctx));
 
             // Check if collapse is needed
-            if children.iter().all(|child| child.is_leaf() && !child.intersects_surface()) {
+            if self.is_collapsible(ctx.options.collapse_tolerance) && !ctx.touches_frozen(cell_aabb) {
                 self.collapse_cell();
+                ctx.release(8);
             }
         }
     }
@@ -166,6 +666,7 @@ impl NaiveOctreeCell {
     /// Will subdivide the Terrain if needed up to `max_depth`. This
     /// method is used by [`NaiveOctree::par_apply_tool`].
     #[cfg(feature = "multi-thread")]
+    #[allow(clippy::too_many_arguments)]
     pub fn par_apply_tool<F: ToolFunc + Sync>(
         &mut self,
         tool: &Tool<F>,
@@ -176,18 +677,40 @@ impl NaiveOctreeCell {
         current_depth: u8,
         max_depth: u8
     ) {
-        self.apply_tool_impl(tool, tool_aabb, aoe_aabb, action, cell_aabb, current_depth, max_depth);
+        self.par_apply_tool_corners(tool, tool_aabb, aoe_aabb, action, cell_aabb.calculate_corners(), current_depth, max_depth, &ApplyContext::new(&ApplyOptions::default(), None, &[]));
+    }
+
+    /// Recursive step of [`par_apply_tool`](Self::par_apply_tool).
+    #[cfg(feature = "multi-thread")]
+    #[allow(clippy::too_many_arguments)]
+    fn par_apply_tool_corners<F: ToolFunc + Sync>(
+        &mut self,
+        tool: &Tool<F>,
+        tool_aabb: AABB,
+        aoe_aabb: AABB,
+        action: Action,
+        corners: [Vec3; 8],
+        current_depth: u8,
+        max_depth: u8,
+        ctx: &ApplyContext
+    ) {
+        let cell_aabb = AABB { start: corners[0], size: corners[7] - corners[0] };
+        if ctx.is_frozen(cell_aabb) || matches!(aoe_aabb.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        self.apply_tool_impl(tool, tool_aabb, aoe_aabb, action, &corners, current_depth, max_depth, ctx);
 
-        if let Some(children) = self.children.as_mut() {
-            let child_aabbs = cell_aabb.octree_subdivide();
+        if let Some(children) = self.children.as_mut().map(Arc::make_mut) {
+            let child_corners = utils::subdivide_cell_positions(&corners);
             // Recursive apply to each child cell
             children.par_iter_mut()
-                .zip(child_aabbs.into_par_iter())
-                .for_each(|(child, aabb)| child.par_apply_tool(tool, tool_aabb, aoe_aabb, action, aabb, current_depth+1, max_depth));
+                .zip(child_corners.into_par_iter())
+                .for_each(|(child, corners)| child.par_apply_tool_corners(tool, tool_aabb, aoe_aabb, action, corners, current_depth+1, max_depth, ctx));
             
             // Check if collapse is needed
-            if children.iter().all(|child| child.is_leaf() && !child.intersects_surface()) {
+            if self.is_collapsible(ctx.options.collapse_tolerance) && !ctx.touches_frozen(cell_aabb) {
                 self.collapse_cell();
+                ctx.release(8);
             }
         }
     }
@@ -195,18 +718,86 @@ impl NaiveOctreeCell {
     /// Uses Marching Cubes to generate resulting mesh triangles and stores them in `faces`. This method
     /// is used by [`NaiveOctree::generate_mesh`].
     pub fn generate_mesh(&self, faces: &mut Vec<[Vec3; 3]>, current_depth: u8, max_depth: u8, cell_aabb: AABB) {
+        stat!(CELLS_VISITED, 1);
         if current_depth < max_depth {
             if let Some(children) = self.children.as_ref() {
                 let child_aabbs = cell_aabb.octree_subdivide();
                 children.iter()
-                .zip(child_aabbs.into_iter())
+                .zip(child_aabbs)
                 .for_each(|(child, aabb)| child.generate_mesh(faces, current_depth+1, max_depth, aabb));
                 return;
             }
         }
 
         let corners = cell_aabb.calculate_corners();
-        faces.extend(march_cube(&corners, &self.values));
+        let tris = march_cube(&corners, &self.values);
+        stat!(TRIANGLES, tris.len());
+        faces.extend(tris);
+    }
+
+    /// Same as [`generate_mesh`](Self::generate_mesh), for the fluid layer.
+    /// Fluid inside of the terrain is left out, so the surface of a lake
+    /// stops at its shore.
+    pub fn generate_fluid_mesh(&self, faces: &mut Vec<[Vec3; 3]>, current_depth: u8, max_depth: u8, cell_aabb: AABB) {
+        stat!(CELLS_VISITED, 1);
+        if current_depth < max_depth {
+            if let Some(children) = self.children.as_ref() {
+                let child_aabbs = cell_aabb.octree_subdivide();
+                children.iter()
+                .zip(child_aabbs)
+                .for_each(|(child, aabb)| child.generate_fluid_mesh(faces, current_depth+1, max_depth, aabb));
+                return;
+            }
+        }
+
+        let values: [f32; 8] = std::array::from_fn(|index| self.fluid[index].min(-self.values[index]));
+        let corners = cell_aabb.calculate_corners();
+        let tris = march_cube(&corners, &values);
+        stat!(TRIANGLES, tris.len());
+        faces.extend(tris);
+    }
+
+    /// Same as [`generate_mesh`](Self::generate_mesh), but only the leaves
+    /// overlapping `region` are meshed.
+    pub fn generate_mesh_region(&self, faces: &mut Vec<[Vec3; 3]>, region: AABB, current_depth: u8, max_depth: u8, cell_aabb: AABB) {
+        if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+            return;
+        }
+        stat!(CELLS_VISITED, 1);
+        if current_depth < max_depth {
+            if let Some(children) = self.children.as_ref() {
+                let child_aabbs = cell_aabb.octree_subdivide();
+                children.iter()
+                .zip(child_aabbs)
+                .for_each(|(child, aabb)| child.generate_mesh_region(faces, region, current_depth+1, max_depth, aabb));
+                return;
+            }
+        }
+
+        let tris = march_cube(&cell_aabb.calculate_corners(), &self.values);
+        stat!(TRIANGLES, tris.len());
+        faces.extend(tris);
+    }
+
+    /// Uses Marching Cubes to generate the triangles of each clipmap ring
+    /// and stores them in `rings`. This method is used by
+    /// [`NaiveOctree::generate_clipmap`].
+    pub fn generate_clipmap(&self, rings: &mut [Vec<[Vec3; 3]>], settings: &ClipmapSettings, focus: Vec3, current_depth: u8, cell_aabb: AABB) {
+        stat!(CELLS_VISITED, 1);
+        let level = settings.level(focus, cell_aabb);
+        if current_depth < settings.max_depth.saturating_sub(level) {
+            if let Some(children) = self.children.as_ref() {
+                let child_aabbs = cell_aabb.octree_subdivide();
+                children.iter()
+                .zip(child_aabbs)
+                .for_each(|(child, aabb)| child.generate_clipmap(rings, settings, focus, current_depth+1, aabb));
+                return;
+            }
+        }
+
+        let tris = march_cube(&cell_aabb.calculate_corners(), &self.values);
+        stat!(TRIANGLES, tris.len());
+        rings[level as usize].extend(tris);
     }
 
     /// Uses Marching Cubes to generate resulting mesh triangles and stores them in `faces`. This method
@@ -214,6 +805,7 @@ impl NaiveOctreeCell {
     #[cfg(feature = "multi-thread")]
     pub fn par_generate_mesh(&self, faces: &Stack<[Vec3; 3]>, current_depth: u8, max_depth: u8, cell_aabb: AABB) {
         use rayon::prelude::*;
+        stat!(CELLS_VISITED, 1);
 
         if current_depth < max_depth {
             if let Some(children) = self.children.as_ref() {
@@ -228,48 +820,344 @@ impl NaiveOctreeCell {
         }
         
         let tris = march_cube(&cell_aabb.calculate_corners(), &self.values);
+        stat!(TRIANGLES, tris.len());
 
         faces.extend(tris);
     }
 
     /// Debugging method to generate an Octree frame.
-    fn generate_octree_frame_mesh(&self, faces: &mut Vec<[Vec3; 3]>, max_depth: u8, cell_aabb: AABB) {
+    /// 
+    /// Each leaf within `max_depth` that passes the filters of `options`
+    /// adds its edges to `faces`. If `colors` is given, a color for each
+    /// vertex based on the depth of the leaf is added to it.
+    pub fn generate_frame_mesh(&self, faces: &mut Vec<[Vec3; 3]>, mut colors: Option<&mut Vec<Vec3>>, options: &FrameMeshOptions, current_depth: u8, cell_aabb: AABB) {
         use utils::{ line_vertices, LineDir };
-        
-        if let Some(children) = self.children.as_ref() {
-            let child_aabbs = cell_aabb.octree_subdivide();
-            children.iter().zip(child_aabbs.into_iter()).for_each(|(child, aabb)| {
-                child.generate_octree_frame_mesh(faces, max_depth, aabb);
-            })
+
+        if let Some(region) = options.region {
+            if matches!(region.intersect(cell_aabb), DoesNotIntersect) {
+                return;
+            }
+        }
+
+        if current_depth < options.max_depth {
+            if let Some(children) = self.children.as_ref() {
+                let child_aabbs = cell_aabb.octree_subdivide();
+                children.iter().zip(child_aabbs).for_each(|(child, aabb)| {
+                    child.generate_frame_mesh(faces, colors.as_deref_mut(), options, current_depth+1, aabb);
+                });
+                return;
+            }
+        }
+
+        if options.surface_only && !self.intersects_surface() {
+            return;
+        }
+
+        let cube_corners = cell_aabb.calculate_corners();
+        let cell_size = cell_aabb.size;
+        let line_scale = cell_size.x * 0.01;
+        let start_len = faces.len();
+
+        if options.surface_only || options.region.is_some() {
+            // Neighbouring leaves may be filtered out, so every edge is drawn
+            for corner in [0, 2, 4, 6] {
+                faces.extend(line_vertices(cube_corners[corner], cell_size.x, line_scale, LineDir::Right));
+            }
+            for corner in [0, 1, 4, 5] {
+                faces.extend(line_vertices(cube_corners[corner], cell_size.y, line_scale, LineDir::Up));
+            }
+            for corner in [0, 1, 2, 3] {
+                faces.extend(line_vertices(cube_corners[corner], cell_size.z, line_scale, LineDir::Forward));
+            }
         }
         else {
-            let cube_scale = cell_aabb.size.x;
-            let cube_corners = cell_aabb.calculate_corners();
-            let cell_size = cell_aabb.size;
-            let line_scale = cube_scale * 0.01;
+            // The remaining edges are drawn by the neighbouring leaves
             faces.extend(line_vertices(cube_corners[0], cell_size.x, line_scale, LineDir::Right));
             faces.extend(line_vertices(cube_corners[0], cell_size.y, line_scale, LineDir::Up));
             faces.extend(line_vertices(cube_corners[0], cell_size.z, line_scale, LineDir::Forward));
         }
+
+        if let Some(colors) = colors {
+            let color = depth_color(current_depth, options.max_depth);
+            colors.resize(colors.len() + (faces.len() - start_len) * 3, color);
+        }
     }
 }
 
-/// A naive implementation of a Sparse Voxel Octree using
-/// recursion to access the child octants.
-#[derive(Debug)]
-pub struct NaiveOctree {
-    root: NaiveOctreeCell,
-    pub scale: f32,
-}
+/// Intersects the tool AABBs to fit inside of `terrain_aabb`. Returns
+/// [None] if the tool can't affect the terrain.
+pub(crate) fn clip_tool_aabbs<F: ToolFunc>(terrain_aabb: AABB, tool: &Tool<F>, action: Action) -> Option<(AABB, AABB)> {
+    let mut tool_aabb = tool.tool_aabb();
+    let mut aoe_aabb = tool.aoe_aabb();
 
-impl NaiveOctree {
-    pub fn new(scale: f32) -> Self {
+    match terrain_aabb.intersect(aoe_aabb) {
+        DoesNotIntersect => return None,
+        Intersects(new_aabb) => aoe_aabb = new_aabb,
+        ContainedBy => aoe_aabb = terrain_aabb,
+        Contains => (),
+    }
+    match terrain_aabb.intersect(tool_aabb) {
+        DoesNotIntersect => if matches!(action, Action::Place) { return None }, 
+        Intersects(new_aabb) => tool_aabb = new_aabb,
+        ContainedBy => tool_aabb = terrain_aabb,
+        Contains => (),
+    }
+
+    Some((tool_aabb, aoe_aabb))
+}
+
+/// Returns true if a leaf at `cell_aabb` with the values `newvals` after
+/// applying the tool should be subdivided for more detail.
+pub(crate) fn tool_needs_subdivision<F: ToolFunc>(tool: &Tool<F>, tool_aabb: AABB, aoe_aabb: AABB, action: Action, cell_aabb: AABB, newvals: &[f32; 8]) -> bool {
+    // TODO: Rewrite all these conditions for performance (if needed)
+    let diff_signs = crate::intersects_surface(newvals);
+
+    let (check_aabb, check_obb, check_sphere) = match action {
+        Action::Remove => (aoe_aabb, tool.aoe_obb(), tool.aoe_bounding_sphere()),
+        Action::Place => (tool_aabb, tool.tool_obb(), tool.bounding_sphere()),
+    };
+
+    // The bounding sphere test is the cheapest and tightest for round
+    // tools. The AABB tests are loose for rotated tools, so the OBB
+    // tests are used to rule out cells the tool doesn't reach.
+    (tool.is_convex() && (diff_signs || (
+        check_sphere.straddles_aabb(cell_aabb) &&
+        matches!(check_aabb.intersect(cell_aabb), ContainedBy | Intersects(_)) &&
+        check_obb.straddles_aabb(cell_aabb)
+    ))) ||
+    (tool.is_concave() &&
+        tool.aoe_bounding_sphere().intersects_aabb(cell_aabb) &&
+        !matches!(aoe_aabb.intersect(cell_aabb), DoesNotIntersect) &&
+        tool.aoe_obb().intersects_aabb(cell_aabb)
+    )
+}
+
+/// Blends from blue at the root to red at `max_depth`.
+fn depth_color(depth: u8, max_depth: u8) -> Vec3 {
+    let t = depth as f32 / max_depth.max(1) as f32;
+    Vec3::new(t, 1.0 - (2.0 * t - 1.0).abs(), 1.0 - t)
+}
+
+/// Options for [`NaiveOctree::apply_tool_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ApplyOptions {
+    /// Children are also collapsed when every corner is within this of the
+    /// value interpolated from their parent. See [`NaiveOctreeCell::is_collapsible`].
+    pub collapse_tolerance: f32,
+    /// Cells are only subdivided if the tool changes some child corner by
+    /// more than this from the value interpolated from the cell, so flat
+    /// parts of a tool stop subdividing early. Zero disables the check.
+    pub subdivision_tolerance: f32,
+    /// Check whether the tool could change the Terrain before applying it,
+    /// and skip it if not, such as when placing into a region that is
+    /// already solid. This is cheaper than applying the tool when it
+    /// would do nothing, but costs a little extra when it does something.
+    pub skip_unchanged: bool,
+    /// The values the tool is applied to.
+    pub channel: Channel,
+}
+
+/// The density fields stored in every [NaiveOctreeCell]. They share one
+/// tree, so every channel is subdivided and collapsed together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Channel {
+    /// The solid terrain, in [`NaiveOctreeCell::values`].
+    #[default]
+    Terrain,
+    /// The fluid layer, in [`NaiveOctreeCell::fluid`]. Fluid is meshed
+    /// separately, by [`NaiveOctree::generate_fluid_mesh`].
+    Fluid,
+}
+
+impl Channel {
+    /// Every channel.
+    pub const ALL: [Channel; 2] = [Channel::Terrain, Channel::Fluid];
+}
+
+/// The outcome of [`NaiveOctree::apply_tool_with_options`] and the other
+/// ways of applying a tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct EditReport {
+    /// The Terrain's `node_budget` ran out, so some cells weren't
+    /// subdivided as far as the tool asked for.
+    pub budget_exceeded: bool,
+    /// The tool couldn't change the Terrain, so it wasn't applied. See
+    /// [`ApplyOptions::skip_unchanged`].
+    pub skipped: bool,
+}
+
+/// The state shared by the recursive steps of applying a tool.
+struct ApplyContext<'a> {
+    options: &'a ApplyOptions,
+    /// How many more cells may be created, if limited.
+    budget: Option<AtomicUsize>,
+    budget_exceeded: AtomicBool,
+    /// How many cells were created, less the ones removed, to keep
+    /// [`NaiveOctree::cell_count`] up to date.
+    created: AtomicIsize,
+    /// See [`NaiveOctree::frozen`].
+    frozen: &'a [AABB],
+    /// The AABB and thickness of the [`NaiveOctree::boundary_shell`], if set.
+    shell: Option<(AABB, f32)>,
+}
+
+impl<'a> ApplyContext<'a> {
+    fn new(options: &'a ApplyOptions, budget: Option<usize>, frozen: &'a [AABB]) -> Self {
+        Self {
+            options,
+            budget: budget.map(AtomicUsize::new),
+            budget_exceeded: AtomicBool::new(false),
+            created: AtomicIsize::new(0),
+            frozen,
+            shell: None,
+        }
+    }
+
+    /// Returns true if `cell_aabb` is completely inside of a frozen region.
+    fn is_frozen(&self, cell_aabb: AABB) -> bool {
+        self.frozen.iter().any(|region| matches!(region.intersect(cell_aabb), Contains))
+    }
+
+    /// Returns true if `cell_aabb` overlaps a frozen region.
+    fn touches_frozen(&self, cell_aabb: AABB) -> bool {
+        self.frozen.iter().any(|region| !matches!(region.intersect(cell_aabb), DoesNotIntersect))
+    }
+
+    /// Returns true if the corner at `corner` must keep its value when
+    /// applying `action`.
+    fn is_locked(&self, corner: Vec3, action: Action) -> bool {
+        let in_shell = |(bounds, thickness): (AABB, f32)| {
+            (corner - bounds.start).min(bounds.end() - corner).min_element() <= thickness
+        };
+        self.frozen.iter().any(|region| region.contains(corner)) ||
+            (self.keeps_shell(action) && self.shell.is_some_and(in_shell))
+    }
+
+    /// Returns true if some corner inside of `cell_aabb` must keep its
+    /// value when applying `action`.
+    fn touches_locked(&self, cell_aabb: AABB, action: Action) -> bool {
+        let in_shell = |(bounds, thickness): (AABB, f32)| {
+            (cell_aabb.start - bounds.start).min(bounds.end() - cell_aabb.end()).min_element() <= thickness
+        };
+        self.touches_frozen(cell_aabb) ||
+            (self.keeps_shell(action) && self.shell.is_some_and(in_shell))
+    }
+
+    /// Returns true if the boundary shell has to be kept when applying
+    /// `action`. Only removing terrain can breach it.
+    fn keeps_shell(&self, action: Action) -> bool {
+        matches!(action, Action::Remove) && matches!(self.options.channel, Channel::Terrain)
+    }
+
+    /// Takes `cells` from the budget. Returns false if there isn't enough.
+    fn reserve(&self, cells: usize) -> bool {
+        let reserved = self.budget.as_ref().is_none_or(|budget| {
+            budget.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| left.checked_sub(cells)).is_ok()
+        });
+        if reserved {
+            self.created.fetch_add(cells as isize, Ordering::Relaxed);
+        }
+        else {
+            self.budget_exceeded.store(true, Ordering::Relaxed);
+        }
+        reserved
+    }
+
+    /// Gives `cells` back to the budget.
+    fn release(&self, cells: usize) {
+        if let Some(budget) = self.budget.as_ref() {
+            budget.fetch_add(cells, Ordering::Relaxed);
+        }
+        self.created.fetch_sub(cells as isize, Ordering::Relaxed);
+    }
+
+    /// The net number of cells [reserved](Self::reserve) and
+    /// [released](Self::release) so far.
+    fn created(&self) -> isize {
+        self.created.load(Ordering::Relaxed)
+    }
+}
+
+/// Options for [`NaiveOctree::generate_colored_frame_mesh`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameMeshOptions {
+    /// Cells deeper than this are drawn as part of their parent. This is
+    /// also the depth that is colored red.
+    pub max_depth: u8,
+    /// Only draw leaves that intersect the isosurface.
+    pub surface_only: bool,
+    /// Only draw leaves that overlap this AABB.
+    pub region: Option<AABB>,
+}
+
+impl Default for FrameMeshOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: u8::MAX,
+            surface_only: false,
+            region: None,
+        }
+    }
+}
+
+/// A naive implementation of a Sparse Voxel Octree using
+/// recursion to access the child octants.
+#[derive(Debug, Clone)]
+pub struct NaiveOctree {
+    root: NaiveOctreeCell,
+    pub scale: f32,
+    /// The minimum corner of the Terrain.
+    pub origin: Vec3,
+    /// The maximum depth used by [`apply`](Self::apply).
+    pub default_depth: u8,
+    /// The most cells the Terrain may have. Tools stop subdividing cells
+    /// once it is reached. See [`apply_tool_with_options`](Self::apply_tool_with_options).
+    pub node_budget: Option<usize>,
+    /// Regions that tools can't change. Corners inside of a frozen region
+    /// keep their values, and cells overlapping one are never collapsed,
+    /// so finished areas keep their detail.
+    pub frozen: Vec<AABB>,
+    /// Corners within this distance of the faces of the Terrain can't be
+    /// removed, so digging can't breach the edge of the world. Octants
+    /// from [`into_octants`](Self::into_octants) only protect the faces of
+    /// the Terrain they were split from.
+    pub boundary_shell: Option<f32>,
+    /// The AABB [`boundary_shell`](Self::boundary_shell) is measured from,
+    /// if this Terrain is an octant of a bigger one.
+    shell_bounds: Option<AABB>,
+    /// The number of cells in `root`, kept up to date by every edit so the
+    /// node budget doesn't have to count them.
+    cells: usize,
+}
+
+impl NaiveOctree {
+    /// The deepest cells can be. Below this, cells are smaller than an f32
+    /// can tell apart across the Terrain, so every `max_depth` or `depth`
+    /// passed to a method that subdivides is clamped to it.
+    pub const MAX_DEPTH: u8 = 24;
+
+    pub fn new(scale: f32) -> Self {
         Self {
             root: Default::default(),
             scale,
+            origin: Vec3::ZERO,
+            default_depth: 8,
+            node_budget: None,
+            frozen: Vec::new(),
+            boundary_shell: None,
+            shell_bounds: None,
+            cells: 1,
         }
     }
 
+    /// Applies the [Tool] to the Terrain with the given [Action], up to
+    /// the Terrain's `default_depth`.
+    pub fn apply<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action) {
+        self._apply_tool(tool.borrow(), action, self.default_depth);
+    }
+
     /// Applies the [Tool] to the Terrain with the given [Action].
     /// Will subdivide the Terrain if needed up to `max_depth`.
     pub fn apply_tool<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action, max_depth: u8) {
@@ -277,67 +1165,1181 @@ impl NaiveOctree {
     }
     
     pub fn _apply_tool<F: ToolFunc>(&mut self, tool: &Tool<F>, action: Action, max_depth: u8) {
-        let mut tool_aabb = tool.tool_aabb();
-        let mut aoe_aabb = tool.aoe_aabb();
+        self.apply_tool_with_options(tool, action, max_depth, &ApplyOptions::default());
+    }
 
-        let terrain_aabb = AABB{ start: Vec3::ZERO, size: Vec3::splat(self.scale) };
-        
-        // Intersect the tool AABBs to fit inside the terrain
-        match terrain_aabb.intersect(aoe_aabb) {
-            DoesNotIntersect => return,
-            Intersects(new_aabb) => aoe_aabb = new_aabb,
-            ContainedBy => aoe_aabb = terrain_aabb,
-            Contains => (),
+    /// Same as [`apply_tool`](Self::apply_tool), but cells whose children
+    /// stay within `collapse_tolerance` of the parent's interpolated values
+    /// are also collapsed. This keeps soft falloff tools from subdividing
+    /// cells that don't add any detail.
+    pub fn apply_tool_with_tolerance<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action, max_depth: u8, collapse_tolerance: f32) {
+        self.apply_tool_with_options(tool, action, max_depth, &ApplyOptions { collapse_tolerance, ..Default::default() });
+    }
+
+    /// Same as [`apply_tool`](Self::apply_tool), with the given [ApplyOptions].
+    /// Returns an [EditReport] describing how the edit went.
+    pub fn apply_tool_with_options<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action, max_depth: u8, options: &ApplyOptions) -> EditReport {
+        let max_depth = max_depth.min(Self::MAX_DEPTH);
+        span!("apply_tool", ?action, max_depth, cells_before = tracing::field::Empty, cells = tracing::field::Empty);
+        let tool = tool.borrow();
+        let Some((tool_aabb, aoe_aabb)) = self.clip_tool_aabbs(tool, action) else {
+            return EditReport::default();
+        };
+
+        if options.skip_unchanged && self.root.is_unchanged_by(tool, tool_aabb, aoe_aabb, action, options.channel, self.aabb(), 0, max_depth) {
+            return EditReport { skipped: true, ..Default::default() };
+        }
+
+        span_record!("cells_before", self.cell_count());
+        let budget = self.node_budget.map(|budget| budget.saturating_sub(self.cell_count()));
+        let mut ctx = ApplyContext::new(options, budget, &self.frozen);
+        ctx.shell = self.shell();
+        self.root.apply_tool_corners(tool, tool_aabb, aoe_aabb, action, self.aabb().calculate_corners(), 0, max_depth, &ctx);
+        self.cells = self.cells.saturating_add_signed(ctx.created());
+        span_record!("cells", self.cell_count());
+
+        EditReport {
+            budget_exceeded: ctx.budget_exceeded.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    /// Same as [`apply_tool`](Self::apply_tool), but the tool is applied
+    /// to the fluid layer instead of the terrain. See [Channel::Fluid].
+    pub fn apply_fluid_tool<T: Borrow<Tool<F>>, F: ToolFunc>(&mut self, tool: T, action: Action, max_depth: u8) -> EditReport {
+        self.apply_tool_with_options(tool, action, max_depth, &ApplyOptions { channel: Channel::Fluid, ..Default::default() })
+    }
+
+    /// The AABB covered by the Terrain.
+    pub fn aabb(&self) -> AABB {
+        AABB { start: self.origin, size: Vec3::splat(self.scale) }
+    }
+
+    pub(crate) fn root(&self) -> &NaiveOctreeCell {
+        &self.root
+    }
+
+    /// The AABB and thickness of the [`boundary_shell`](Self::boundary_shell), if set.
+    pub(crate) fn shell(&self) -> Option<(AABB, f32)> {
+        self.boundary_shell.map(|thickness| (self.shell_bounds.unwrap_or(self.aabb()), thickness))
+    }
+
+    /// Intersects the tool AABBs to fit inside the terrain. Returns [None]
+    /// if the tool can't affect the terrain.
+    fn clip_tool_aabbs<F: ToolFunc>(&self, tool: &Tool<F>, action: Action) -> Option<(AABB, AABB)> {
+        clip_tool_aabbs(self.aabb(), tool, action)
+    }
+
+    /// Applies the [Tool] of a [BrushPreset] with its [Action] and
+    /// maximum depth.
+    pub fn apply_preset<F: ToolFunc>(&mut self, preset: &BrushPreset<F>) {
+        self._apply_tool(&preset.tool, preset.action, preset.max_depth);
+    }
+
+    /// Applies `func` to the Terrain as if it were swept from the `from`
+    /// transform to the `to` transform, using the given [Action].
+    /// Will subdivide the Terrain if needed up to `max_depth`.
+    /// 
+    /// See also: [`Swept`]
+    pub fn apply_tool_swept<F: ToolFunc + Clone>(&mut self, func: F, from: Affine3A, to: Affine3A, action: Action, max_depth: u8) {
+        self.apply_tool(Tool::new(Swept::new(func, from, to)), action, max_depth);
+    }
+
+    /// Subdivides the cells crossing the surface inside of `region` down to
+    /// `depth`, interpolating their values. This lets tools be applied at a
+    /// coarse depth first, with later tools adding detail at `depth`.
+    /// 
+    /// Like applying a tool, cells are only subdivided while the
+    /// [`node_budget`](Self::node_budget) lasts. Returns an [EditReport]
+    /// describing how the edit went.
+    pub fn refine_region(&mut self, region: AABB, depth: u8) -> EditReport {
+        let depth = depth.min(Self::MAX_DEPTH);
+        span!("refine_region", depth, cells = tracing::field::Empty);
+        let terrain_aabb = self.aabb();
+        let options = ApplyOptions::default();
+        let budget = self.node_budget.map(|budget| budget.saturating_sub(self.cells));
+        let ctx = ApplyContext::new(&options, budget, &self.frozen);
+        self.root.refine_surface_with(region, terrain_aabb, 0, depth, &ctx);
+        self.cells = self.cells.saturating_add_signed(ctx.created());
+        span_record!("cells", self.cell_count());
+
+        EditReport {
+            budget_exceeded: ctx.budget_exceeded.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    /// Smooths the surface inside of `region` by box-blurring the corner
+    /// values `iterations` times.
+    /// 
+    /// The region is first subdivided to a consistent `depth`, so the
+    /// blur spans cell boundaries evenly. Values on the border of the
+    /// region are left alone so it blends into its surroundings, and
+    /// cells left without any surface are collapsed afterwards.
+    /// 
+    /// Like applying a tool, frozen regions and the
+    /// [`boundary_shell`](Self::boundary_shell) keep their values, and
+    /// cells are only subdivided while the [`node_budget`](Self::node_budget)
+    /// lasts. Returns an [EditReport] describing how the edit went.
+    pub fn smooth_region(&mut self, region: AABB, iterations: u32, depth: u8) -> EditReport {
+        span!("smooth_region", iterations, depth);
+        let terrain_aabb = self.aabb();
+        let Some(region) = terrain_aabb.get_intersect_aabb(region) else {
+            return EditReport::default();
+        };
+        let depth = depth.min(Self::MAX_DEPTH);
+
+        let options = ApplyOptions::default();
+        let budget = self.node_budget.map(|budget| budget.saturating_sub(self.cell_count()));
+        let mut ctx = ApplyContext::new(&options, budget, &self.frozen);
+        ctx.shell = self.shell();
+        self.root.refine_with(region, terrain_aabb, 0, depth, &ctx);
+
+        let spacing = self.scale / (1u32 << depth) as f32;
+        let resolution = 1i32 << depth;
+        let low = ((region.start - self.origin) / spacing).floor().as_ivec3().clamp(IVec3::ZERO, IVec3::splat(resolution));
+        let high = ((region.end() - self.origin) / spacing).ceil().as_ivec3().clamp(IVec3::ZERO, IVec3::splat(resolution));
+        let dims = high - low + 1;
+        let index = |point: IVec3| {
+            let local = point - low;
+            (local.x + local.y * dims.x + local.z * dims.x * dims.y) as usize
+        };
+        let is_interior = |point: IVec3| point.cmpgt(low).all() && point.cmplt(high).all();
+        let origin = self.origin;
+        let is_locked = |point: IVec3, old: f32, new: f32| {
+            let action = if new < old { Action::Remove } else { Action::Place };
+            ctx.is_locked(origin + point.as_vec3() * spacing, action)
+        };
+
+        let mut grid = vec![0.0; (dims.x * dims.y * dims.z) as usize];
+        for z in low.z..=high.z {
+            for y in low.y..=high.y {
+                for x in low.x..=high.x {
+                    let point = IVec3::new(x, y, z);
+                    grid[index(point)] = self.sample(self.origin + point.as_vec3() * spacing);
+                }
+            }
+        }
+
+        for _ in 0..iterations {
+            let mut blurred = grid.clone();
+            for z in low.z+1..high.z {
+                for y in low.y+1..high.y {
+                    for x in low.x+1..high.x {
+                        let point = IVec3::new(x, y, z);
+                        let mut total = 0.0;
+                        for offset in utils::neighborhood() {
+                            total += grid[index(point + offset)];
+                        }
+                        if !is_locked(point, grid[index(point)], total / 27.0) {
+                            blurred[index(point)] = total / 27.0;
+                        }
+                    }
+                }
+            }
+            grid = blurred;
+        }
+
+        self.root.for_each_leaf_mut(region, terrain_aabb, &mut |leaf, leaf_aabb| {
+            leaf.values.iter_mut().zip(leaf_aabb.calculate_corners()).for_each(|(value, corner)| {
+                let lattice = (corner - origin) / spacing;
+                let point = lattice.round().as_ivec3();
+                // Corners of deeper leaves on the border can be off the lattice
+                if is_interior(point) && lattice.abs_diff_eq(point.as_vec3(), 0.001) && !is_locked(point, *value, grid[index(point)]) {
+                    *value = grid[index(point)];
+                }
+            });
+        });
+
+        {
+            span!("collapse_region", cells = tracing::field::Empty);
+            self.root.collapse_region_with(region, terrain_aabb, &ctx);
+            self.cells = self.cells.saturating_add_signed(ctx.created());
+            span_record!("cells", self.cell_count());
+        }
+
+        EditReport {
+            budget_exceeded: ctx.budget_exceeded.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    /// Wears down steep slopes inside of `region` with `iterations` steps
+    /// of thermal erosion.
+    /// 
+    /// The top of the surface is treated as a heightfield, with a column at
+    /// every corner of a cell at `depth`. Wherever a column stands more than
+    /// `talus` times the column spacing above its neighbours, half of the
+    /// largest difference past that slides down onto them. Columns on the
+    /// border of the region keep their height so it blends into its
+    /// surroundings, and anything below the top of the surface, such as
+    /// caves, is left alone.
+    /// 
+    /// Like [`smooth_region`](Self::smooth_region), frozen regions, the
+    /// [`boundary_shell`](Self::boundary_shell) and the
+    /// [`node_budget`](Self::node_budget) are respected. Returns an
+    /// [EditReport] describing how the edit went.
+    pub fn erode_region(&mut self, region: AABB, iterations: u32, talus: f32, depth: u8) -> EditReport {
+        span!("erode_region", iterations, depth);
+        let terrain_aabb = self.aabb();
+        let Some(region) = terrain_aabb.get_intersect_aabb(region) else {
+            return EditReport::default();
+        };
+        let depth = depth.min(Self::MAX_DEPTH);
+
+        let spacing = self.scale / (1u32 << depth) as f32;
+        let resolution = 1i32 << depth;
+        let low = ((region.start - self.origin) / spacing).floor().as_ivec3().clamp(IVec3::ZERO, IVec3::splat(resolution));
+        let high = ((region.end() - self.origin) / spacing).ceil().as_ivec3().clamp(IVec3::ZERO, IVec3::splat(resolution));
+        let width = high.x - low.x + 1;
+        let index = |x: i32, z: i32| ((x - low.x) + (z - low.z) * width) as usize;
+        let is_interior = |x: i32, z: i32| x > low.x && x < high.x && z > low.z && z < high.z;
+
+        // The top of the surface in each column, if it is inside the region
+        let mut heights = Vec::with_capacity(index(high.x, high.z) + 1);
+        for z in low.z..=high.z {
+            for x in low.x..=high.x {
+                let pos = self.origin + IVec3::new(x, 0, z).as_vec3() * spacing;
+                let top = self.column_surface_heights(pos.x, pos.z).last().copied();
+                heights.push(top.filter(|&top| top >= region.start.y && top <= region.end().y));
+            }
+        }
+
+        let threshold = talus * spacing;
+        let mut eroded = heights.clone();
+        for _ in 0..iterations {
+            let mut next = eroded.clone();
+            for z in low.z+1..high.z {
+                for x in low.x+1..high.x {
+                    let Some(height) = eroded[index(x, z)] else {
+                        continue;
+                    };
+                    let neighbours = [(x-1, z), (x+1, z), (x, z-1), (x, z+1)];
+                    let drops = neighbours.map(|(x, z)| eroded[index(x, z)].map_or(0.0, |other| (height - other - threshold).max(0.0)));
+                    let total = drops.iter().sum::<f32>();
+                    if total <= 0.0 {
+                        continue;
+                    }
+                    let moved = 0.5 * drops.iter().copied().fold(0.0, f32::max);
+                    next[index(x, z)] = Some(next[index(x, z)].unwrap() - moved);
+                    // Whatever slides onto the border is lost, so the border keeps its height
+                    for ((x, z), drop) in neighbours.into_iter().zip(drops) {
+                        if drop > 0.0 && is_interior(x, z) {
+                            next[index(x, z)] = next[index(x, z)].map(|other| other + moved * drop / total);
+                        }
+                    }
+                }
+            }
+            eroded = next;
+        }
+
+        // Only the band of each column between its old and new top is rewritten
+        let changed = |x: i32, z: i32| match (heights[index(x, z)], eroded[index(x, z)]) {
+            (Some(old), Some(new)) if is_interior(x, z) && old != new => Some((old.min(new) - spacing, old.max(new) + spacing, new)),
+            _ => None,
+        };
+        let Some((bottom, top)) = (low.z..=high.z)
+            .flat_map(|z| (low.x..=high.x).filter_map(move |x| changed(x, z)))
+            .map(|(bottom, top, _)| (bottom, top))
+            .reduce(|(a, b), (c, d)| (a.min(c), b.max(d)))
+        else {
+            return EditReport::default();
+        };
+        let (start, end) = (region.start, region.end());
+        let band = AABB::from_min_max(Vec3::new(start.x, bottom, start.z), Vec3::new(end.x, top, end.z));
+        let Some(band) = region.get_intersect_aabb(band) else {
+            return EditReport::default();
+        };
+
+        let options = ApplyOptions::default();
+        let budget = self.node_budget.map(|budget| budget.saturating_sub(self.cells));
+        let mut ctx = ApplyContext::new(&options, budget, &self.frozen);
+        ctx.shell = self.shell();
+        self.root.refine_with(band, terrain_aabb, 0, depth, &ctx);
+
+        let origin = self.origin;
+        self.root.for_each_leaf_mut(band, terrain_aabb, &mut |leaf, leaf_aabb| {
+            leaf.values.iter_mut().zip(leaf_aabb.calculate_corners()).for_each(|(value, corner)| {
+                let lattice = (corner - origin) / spacing;
+                let point = lattice.round().as_ivec3();
+                if !lattice.abs_diff_eq(point.as_vec3(), 0.001) {
+                    return;
+                }
+                let Some((bottom, top, height)) = changed(point.x, point.z) else {
+                    return;
+                };
+                let new = ((height - corner.y) / spacing).clamp(-1.0, 1.0);
+                let action = if new < *value { Action::Remove } else { Action::Place };
+                if corner.y >= bottom && corner.y <= top && !ctx.is_locked(corner, action) {
+                    *value = new;
+                }
+            });
+        });
+
+        {
+            span!("collapse_region", cells = tracing::field::Empty);
+            self.root.collapse_region_with(band, terrain_aabb, &ctx);
+            self.cells = self.cells.saturating_add_signed(ctx.created());
+            span_record!("cells", self.cell_count());
+        }
+
+        EditReport {
+            budget_exceeded: ctx.budget_exceeded.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    /// Replaces the value of every corner on the faces of the Terrain with
+    /// `func(corner, value)`. Frozen regions and the
+    /// [`boundary_shell`](Self::boundary_shell) keep their values.
+    ///
+    /// Corners added by subdivision are interpolated from their parent, so
+    /// two chunks that subdivided the cells along their shared face at
+    /// different times can disagree about it. Setting the faces from
+    /// something that only depends on the position makes them line up.
+    pub fn map_face_values(&mut self, mut func: impl FnMut(Vec3, f32) -> f32) {
+        let terrain_aabb = self.aabb();
+        let options = ApplyOptions::default();
+        let mut ctx = ApplyContext::new(&options, None, &self.frozen);
+        ctx.shell = self.shell();
+        let epsilon = self.scale * 1e-5;
+        let on_face = |corner: Vec3| {
+            (corner - terrain_aabb.start).min(terrain_aabb.end() - corner).min_element() <= epsilon
+        };
+        self.root.for_each_leaf_mut(terrain_aabb, terrain_aabb, &mut |leaf, leaf_aabb| {
+            if !on_face(leaf_aabb.start) && !on_face(leaf_aabb.end()) {
+                return;
+            }
+            leaf.values.iter_mut().zip(leaf_aabb.calculate_corners()).for_each(|(value, corner)| {
+                if !on_face(corner) {
+                    return;
+                }
+                let new = func(corner, *value);
+                let action = if new < *value { Action::Remove } else { Action::Place };
+                if !ctx.is_locked(corner, action) {
+                    *value = new;
+                }
+            });
+        });
+    }
+
+    /// Reflects the Terrain across the plane through its center that is
+    /// perpendicular to `axis`. Only the order of the cells changes, so
+    /// nothing is resampled.
+    pub fn mirrored(self, axis: Axis) -> Self {
+        let bit = 1 << axis.index();
+        let center = self.aabb().center();
+        self.transformed(|index| index ^ bit, |mut pos| {
+            pos[axis.index()] = 2.0 * center[axis.index()] - pos[axis.index()];
+            pos
+        })
+    }
+
+    /// Rotates the Terrain by 90 degrees around `axis` through its center,
+    /// counterclockwise when looking down `axis` towards the origin. Only
+    /// the order of the cells changes, so nothing is resampled.
+    pub fn rotated_90(self, axis: Axis) -> Self {
+        let rotation = Quat::from_axis_angle(axis.unit(), std::f32::consts::FRAC_PI_2);
+        let center = self.aabb().center();
+        self.transformed(
+            |index| {
+                let corner = crate::CUBE_CORNERS[index] - 0.5;
+                let rotated = (rotation * corner + 0.5).round();
+                rotated.x as usize | (rotated.y as usize) << 1 | (rotated.z as usize) << 2
+            },
+            |pos| center + rotation * (pos - center),
+        )
+    }
+
+    /// Moves each corner index to `corner_map(index)`, and each frozen
+    /// region to where `point_map` moves its corners.
+    fn transformed(mut self, corner_map: impl Fn(usize) -> usize, point_map: impl Fn(Vec3) -> Vec3) -> Self {
+        let mut perm = [0; 8];
+        for index in 0..8 {
+            perm[corner_map(index)] = index;
+        }
+        self.root.permute(&perm);
+        for region in self.frozen.iter_mut() {
+            let (a, b) = (point_map(region.start), point_map(region.end()));
+            *region = AABB { start: a.min(b), size: (a - b).abs() };
+        }
+        self
+    }
+
+    /// Splits the Terrain into the 8 octants of its root, in Z-order.
+    pub fn into_octants(mut self) -> [NaiveOctree; 8] {
+        self.root.subdivide_cell();
+        let children = self.root.children.take().unwrap();
+        let aabbs = self.aabb().octree_subdivide();
+        let default_depth = self.default_depth.saturating_sub(1);
+        let node_budget = self.node_budget.map(|budget| budget / 8);
+        let shell_bounds = Some(self.shell_bounds.unwrap_or(self.aabb()));
+        let mut octants = Arc::unwrap_or_clone(children).into_iter().zip(aabbs).map(|(root, aabb)| NaiveOctree {
+            cells: root.cell_count(),
+            root,
+            scale: self.scale * 0.5,
+            origin: aabb.start,
+            default_depth,
+            node_budget,
+            frozen: self.frozen.clone(),
+            boundary_shell: self.boundary_shell,
+            shell_bounds,
+        });
+        [(); 8].map(|_| octants.next().unwrap())
+    }
+
+    /// Joins 8 octants split by [`into_octants`](Self::into_octants) back
+    /// into one Terrain.
+    pub fn from_octants(octants: [NaiveOctree; 8]) -> Self {
+        let scale = octants[0].scale * 2.0;
+        let origin = octants[0].origin;
+        let default_depth = octants[0].default_depth + 1;
+        let node_budget = octants[0].node_budget.map(|budget| budget * 8);
+        // Every octant keeps the whole list from into_octants
+        let frozen = octants[0].frozen.clone();
+        let boundary_shell = octants[0].boundary_shell;
+        let aabb = AABB { start: origin, size: Vec3::splat(scale) };
+        let shell_bounds = octants[0].shell_bounds.filter(|&bounds| bounds != aabb);
+        let octants_cells = octants.iter().map(|octant| octant.cells).sum::<usize>();
+        let children = Arc::new(octants.map(|octant| octant.root));
+        let mut root = NaiveOctreeCell { values: [0.0; 8], fluid: [0.0; 8], materials: [0; 8], children: Some(children) };
+        for index in 0..8 {
+            let child = &root.children.as_ref().unwrap()[index];
+            root.values[index] = child.values[index];
+            root.fluid[index] = child.fluid[index];
+            root.materials[index] = child.materials[index];
+        }
+        let mut cells = 1 + octants_cells;
+        if root.is_collapsible(0.0) {
+            root.collapse_cell();
+            cells = 1;
+        }
+        Self { root, scale, origin, default_depth, node_budget, frozen, boundary_shell, shell_bounds, cells }
+    }
+
+    /// Builds a Terrain covering `grid` from its values.
+    /// 
+    /// Cells are subdivided until they are as small as the grid spacing or
+    /// `max_depth` is reached, and then collapsed wherever their children
+    /// are within `tolerance` of their interpolated values.
+    pub fn from_dense(grid: &DenseGrid, max_depth: u8, tolerance: f32) -> Self {
+        fn build(grid: &DenseGrid, cell_aabb: AABB, depth: u8, max_depth: u8, tolerance: f32) -> NaiveOctreeCell {
+            let mut cell = NaiveOctreeCell {
+                values: cell_aabb.calculate_corners().map(|corner| grid.sample(corner)),
+                ..Default::default()
+            };
+            if depth < max_depth && cell_aabb.size.x > grid.spacing {
+                let children = cell_aabb.octree_subdivide().map(|aabb| build(grid, aabb, depth+1, max_depth, tolerance));
+                cell.children = Some(Arc::new(children));
+                if cell.is_collapsible(tolerance) {
+                    cell.children = None;
+                }
+            }
+            cell
+        }
+
+        let mut terrain = NaiveOctree::new(grid.aabb().size.max_element());
+        terrain.origin = grid.origin;
+        terrain.root = build(grid, terrain.aabb(), 0, max_depth.min(Self::MAX_DEPTH), tolerance);
+        terrain.cells = terrain.root.cell_count();
+        terrain
+    }
+
+    /// Builds a new Terrain covering `new_bounds` by sampling this one, up
+    /// to `max_depth`. Like [TerrainBuilder](crate::TerrainBuilder), the new
+    /// Terrain starts at `new_bounds.start` and covers the longest side of
+    /// `new_bounds` on every axis. Space outside of this Terrain is empty.
+    /// 
+    /// Cells are only subdivided where this Terrain has more detail than
+    /// one of its leaves can hold. The other settings are copied over.
+    pub fn resample(&self, new_bounds: AABB, max_depth: u8) -> Self {
+        /// Returns true if the values inside of `region` are interpolated
+        /// from one leaf, so a cell covering it doesn't need children.
+        fn is_smooth(cell: &NaiveOctreeCell, cell_aabb: AABB, region: AABB) -> bool {
+            match cell.children.as_ref() {
+                None => true,
+                Some(children) => children.iter()
+                    .zip(cell_aabb.octree_subdivide())
+                    .find(|(_, aabb)| matches!(aabb.intersect(region), Contains))
+                    .is_some_and(|(child, aabb)| is_smooth(child, aabb, region)),
+            }
+        }
+
+        fn build(old: &NaiveOctree, cell_aabb: AABB, depth: u8, max_depth: u8) -> NaiveOctreeCell {
+            let mut cell = NaiveOctreeCell {
+                values: cell_aabb.calculate_corners().map(|corner| old.sample(corner)),
+                fluid: cell_aabb.calculate_corners().map(|corner| old.sample_fluid(corner)),
+                materials: cell_aabb.calculate_corners().map(|corner| old.material(corner)),
+                children: None,
+            };
+            let old_aabb = old.aabb();
+            let outside = cell_aabb.end().cmplt(old_aabb.start).any() || cell_aabb.start.cmpgt(old_aabb.end()).any();
+            let smooth = outside ||
+                (matches!(old_aabb.intersect(cell_aabb), Contains) && is_smooth(&old.root, old_aabb, cell_aabb));
+            if depth < max_depth && !smooth {
+                let children = cell_aabb.octree_subdivide().map(|aabb| build(old, aabb, depth+1, max_depth));
+                cell.children = Some(Arc::new(children));
+                if cell.is_collapsible(0.0) {
+                    cell.children = None;
+                }
+            }
+            cell
+        }
+
+        let aabb = AABB { start: new_bounds.start, size: Vec3::splat(new_bounds.size.max_element()) };
+        let root = build(self, aabb, 0, max_depth.min(Self::MAX_DEPTH));
+        NaiveOctree {
+            cells: root.cell_count(),
+            root,
+            scale: aabb.size.x,
+            origin: aabb.start,
+            default_depth: self.default_depth,
+            node_budget: self.node_budget,
+            frozen: self.frozen.clone(),
+            boundary_shell: self.boundary_shell,
+            shell_bounds: None,
+        }
+    }
+
+    /// Samples the Terrain into a [DenseGrid] covering `aabb`, with points
+    /// `spacing` apart.
+    pub fn to_dense(&self, aabb: AABB, spacing: f32) -> DenseGrid {
+        let dims = (aabb.size / spacing).ceil().as_uvec3() + 1;
+        let mut grid = DenseGrid::new(dims, aabb.start, spacing);
+        for z in 0..dims.z {
+            for y in 0..dims.y {
+                for x in 0..dims.x {
+                    let point = glam::UVec3::new(x, y, z);
+                    grid.set(point, self.sample(grid.position(point)));
+                }
+            }
+        }
+        grid
+    }
+
+    /// Checks that the values of the Terrain are finite and in range, that
+    /// parents share their corner values with their children, and that no
+    /// cell was left uncollapsed. Returns every [Violation] found.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.root.validate(self.aabb(), &mut violations);
+        violations
+    }
+
+    /// Fixes every [Violation] that [`validate`](Self::validate) would
+    /// find, and returns how many cells were changed.
+    pub fn repair(&mut self) -> usize {
+        let repaired = self.root.repair();
+        self.cells = self.root.cell_count();
+        repaired
+    }
+
+    /// Counts the corner values of every leaf in `bins` equal ranges
+    /// between -1 and 1. Corners shared by several leaves are counted once
+    /// per leaf.
+    pub fn value_histogram(&self, bins: usize) -> Vec<usize> {
+        let mut histogram = vec![0; bins];
+        if bins == 0 {
+            return histogram;
+        }
+        let mut stack = vec![&self.root];
+        while let Some(cell) = stack.pop() {
+            match cell.children.as_ref() {
+                Some(children) => stack.extend(children.iter()),
+                None => for value in cell.values {
+                    let bin = ((value.clamp(-1.0, 1.0) + 1.0) * 0.5 * bins as f32) as usize;
+                    histogram[bin.min(bins - 1)] += 1;
+                },
+            }
+        }
+        histogram
+    }
+
+    /// Returns the number of leaves intersecting the isosurface at each
+    /// depth, indexed by depth.
+    pub fn surface_cells_per_depth(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.depth() as usize + 1];
+        let mut stack = vec![(&self.root, 0)];
+        while let Some((cell, depth)) = stack.pop() {
+            match cell.children.as_ref() {
+                Some(children) => stack.extend(children.iter().map(|child| (child, depth + 1))),
+                None => if cell.intersects_surface() {
+                    counts[depth] += 1;
+                },
+            }
+        }
+        counts
+    }
+
+    /// Returns the number of cells in the Terrain, including the root.
+    /// 
+    /// This is counted as the Terrain is edited, so it takes constant time.
+    pub fn cell_count(&self) -> usize {
+        self.cells
+    }
+
+    /// Returns the depth of the deepest leaf.
+    pub fn depth(&self) -> u8 {
+        fn cell_depth(cell: &NaiveOctreeCell) -> u8 {
+            cell.children.as_ref().map_or(0, |children| 1 + children.iter().map(cell_depth).max().unwrap_or(0))
         }
-        match terrain_aabb.intersect(tool_aabb) {
-            DoesNotIntersect => if matches!(action, Action::Place) { return }, 
-            Intersects(new_aabb) => tool_aabb = new_aabb,
-            ContainedBy => tool_aabb = terrain_aabb,
-            Contains => (),
+        cell_depth(&self.root)
+    }
+
+    /// Applies the values of `other` to the Terrain as if it were a tool
+    /// placed with `transform`, using the given [Action].
+    /// 
+    /// The Terrain is subdivided until its cells are about as small as
+    /// the smallest cells of `other` after being transformed.
+    /// 
+    /// See also: [`OctreeStamp`]
+    pub fn stamp(&mut self, other: &NaiveOctree, transform: Affine3A, action: Action) {
+        let stamp_scale = transform.matrix3.determinant().abs().cbrt();
+        let leaf_size = other.scale / (1u64 << other.depth().min(63)) as f32 * stamp_scale;
+        let max_depth = (self.scale / leaf_size).log2().ceil().clamp(0.0, 30.0) as u8;
+        self.apply_tool(Tool::new(OctreeStamp::new(other)).transformed(transform), action, max_depth);
+    }
+
+    /// Blasts a crater of `radius` into the Terrain at `point`, up to the
+    /// Terrain's `default_depth`.
+    /// 
+    /// Before the crater is removed, a flattened mound is placed around
+    /// it so a raised rim is left behind. `strength` scales the height of
+    /// the rim, and a `strength` of 0.0 leaves no rim.
+    pub fn impact(&mut self, point: Vec3, radius: f32, strength: f32) {
+        let point = Vec3A::from(point);
+        if strength > 0.0 {
+            let rim = Tool::new(Sphere)
+                .scaled(Vec3::new(radius * 1.4, radius * 0.5 * strength, radius * 1.4))
+                .translated(point);
+            self.apply(rim, Action::Place);
         }
+        self.apply(Tool::new(Sphere).scaled(Vec3::splat(radius)).translated(point), Action::Remove);
+    }
+
+    /// Same as [`impact`](Self::impact), but also returns the bounds of
+    /// any floating pieces of debris left near the crater.
+    /// 
+    /// See also: [`find_islands`](Self::find_islands)
+    pub fn impact_with_debris(&mut self, point: Vec3, radius: f32, strength: f32) -> Vec<AABB> {
+        self.impact(point, radius, strength);
+        self.find_islands(AABB::from_radius(point, radius * 2.0), self.default_depth)
+    }
+
+    /// Finds pieces of solid terrain inside of `region` that aren't
+    /// connected to anything outside of it, such as debris left floating
+    /// by an explosion, and returns their bounds.
+    /// 
+    /// The region is sampled on a grid with the spacing of cells at
+    /// `depth`, but no finer than 64 samples along each axis. Pieces
+    /// thinner than the spacing may be missed.
+    pub fn find_islands(&self, region: AABB, depth: u8) -> Vec<AABB> {
+        let Some(region) = self.aabb().get_intersect_aabb(region) else {
+            return Vec::new();
+        };
+        let spacing = (self.scale / 2f32.powi(depth as i32)).max(region.size.max_element() / 63.0);
+        let dims = (region.size / spacing).ceil().as_ivec3() + 1;
+        let index = |point: IVec3| (point.x + point.y * dims.x + point.z * dims.x * dims.y) as usize;
+        let position = |point: IVec3| (region.start + point.as_vec3() * spacing).min(region.end());
 
-        println!("Applying");
-        self.root.apply_tool(tool, tool_aabb, aoe_aabb, action, terrain_aabb, 0, max_depth);
+        let mut solid = vec![false; (dims.x * dims.y * dims.z) as usize];
+        for z in 0..dims.z {
+            for y in 0..dims.y {
+                for x in 0..dims.x {
+                    let point = IVec3::new(x, y, z);
+                    solid[index(point)] = self.sample(position(point)) > 0.0;
+                }
+            }
+        }
+
+        // Flood fill each connected piece, keeping the ones that don't
+        // touch the border of the region
+        let mut visited = vec![false; solid.len()];
+        let mut islands = Vec::new();
+        let mut stack = Vec::new();
+        for z in 0..dims.z {
+            for y in 0..dims.y {
+                for x in 0..dims.x {
+                    let start = IVec3::new(x, y, z);
+                    if !solid[index(start)] || visited[index(start)] {
+                        continue;
+                    }
+                    visited[index(start)] = true;
+                    stack.push(start);
+
+                    let mut touches_border = false;
+                    let mut bounds = AABB { start: position(start), size: Vec3::ZERO };
+                    while let Some(point) = stack.pop() {
+                        touches_border |= point.cmpeq(IVec3::ZERO).any() || point.cmpeq(dims - 1).any();
+                        bounds.expand(position(point));
+                        // Diagonal neighbours count, so thin slivers stay attached
+                        for offset in utils::neighborhood() {
+                            let neighbor = point + offset;
+                            if neighbor.cmplt(IVec3::ZERO).any() || neighbor.cmpge(dims).any() {
+                                continue;
+                            }
+                            if solid[index(neighbor)] && !visited[index(neighbor)] {
+                                visited[index(neighbor)] = true;
+                                stack.push(neighbor);
+                            }
+                        }
+                    }
+
+                    if !touches_border {
+                        islands.push(bounds);
+                    }
+                }
+            }
+        }
+
+        islands
+    }
+
+    /// Removes a ridged noise field from the Terrain inside of `region`,
+    /// carving out networks of caves.
+    /// Will subdivide the Terrain if needed up to `max_depth`.
+    /// 
+    /// See also: [`RidgedNoise`]
+    pub fn carve_caves(&mut self, params: &NoiseParams, region: AABB, max_depth: u8) {
+        self.apply_tool(Tool::new(RidgedNoise::new(*params, region)), Action::Remove, max_depth);
     }
 
     /// Applies the [Tool] to the Terrain with the given [Action].
     /// Will subdivide the Terrain if needed up to `max_depth`.
+    /// Returns an [EditReport] describing how the edit went.
     #[cfg(feature = "multi-thread")]
-    pub fn par_apply_tool<T: Borrow<Tool<F>> + Sync + Send + Copy, F: ToolFunc + Sync>(&mut self, tool: T, action: Action, max_depth: u8) {
-        self._par_apply_tool(tool.borrow(), action, max_depth);
+    pub fn par_apply_tool<T: Borrow<Tool<F>> + Sync + Send + Copy, F: ToolFunc + Sync>(&mut self, tool: T, action: Action, max_depth: u8) -> EditReport {
+        self._par_apply_tool(tool.borrow(), action, max_depth)
     }
 
     #[cfg(feature = "multi-thread")]
-    fn _par_apply_tool<F: ToolFunc + Sync>(&mut self, tool: &Tool<F>, action: Action, max_depth: u8) {
-        let mut tool_aabb = tool.tool_aabb();
-        let mut aoe_aabb = tool.aoe_aabb();
+    fn _par_apply_tool<F: ToolFunc + Sync>(&mut self, tool: &Tool<F>, action: Action, max_depth: u8) -> EditReport {
+        let max_depth = max_depth.min(Self::MAX_DEPTH);
+        let Some((tool_aabb, aoe_aabb)) = self.clip_tool_aabbs(tool, action) else {
+            return EditReport::default();
+        };
+        let terrain_aabb = self.aabb();
 
-        let terrain_aabb = AABB{ start: Vec3::ZERO, size: Vec3::splat(self.scale) };
-        
-        // Try to intersect the tool AABBs to fit inside the terrain
-        match terrain_aabb.intersect(tool_aabb) {
-            DoesNotIntersect => if matches!(action, Action::Place) { return }, 
-            Intersects(new_aabb) => tool_aabb = new_aabb,
-            ContainedBy => tool_aabb = terrain_aabb,
-            Contains => (),
+        let options = ApplyOptions::default();
+        let budget = self.node_budget.map(|budget| budget.saturating_sub(self.cell_count()));
+        let mut ctx = ApplyContext::new(&options, budget, &self.frozen);
+        ctx.shell = self.shell();
+        rayon::in_place_scope(|_| {
+            self.root.par_apply_tool_corners(tool, tool_aabb, aoe_aabb, action, terrain_aabb.calculate_corners(), 0, max_depth, &ctx);
+        });
+        self.cells = self.cells.saturating_add_signed(ctx.created());
+
+        EditReport {
+            budget_exceeded: ctx.budget_exceeded.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the value of the terrain at `pos`. Positions outside of
+    /// the terrain are empty.
+    pub fn sample(&self, pos: Vec3) -> f32 {
+        if !self.aabb().contains(pos) {
+            return -1.0;
         }
-        match terrain_aabb.intersect(aoe_aabb) {
-            DoesNotIntersect => return,
-            Intersects(new_aabb) => aoe_aabb = new_aabb,
-            ContainedBy => aoe_aabb = terrain_aabb,
-            Contains => (),
+        self.root.sample(pos, self.aabb(), 0, u8::MAX)
+    }
+
+    /// Same as [`sample`](Self::sample), for the fluid layer.
+    pub fn sample_fluid(&self, pos: Vec3) -> f32 {
+        if !self.aabb().contains(pos) {
+            return -1.0;
         }
+        self.root.sample_channel(Channel::Fluid, pos, self.aabb(), 0, u8::MAX)
+    }
 
-        rayon::in_place_scope(|_| {
-            self.root.par_apply_tool(tool.borrow(), tool_aabb, aoe_aabb, action, AABB { start: Vec3::ZERO, size: Vec3::splat(self.scale) }, 0, max_depth);
+    /// Returns the material at `pos`, taken from the nearest solid corner
+    /// of the leaf containing it, or the nearest corner if none are solid.
+    /// Positions outside of the terrain are 0.
+    /// 
+    /// The vertices of a mesh lie between a solid and an empty corner, so
+    /// they get the material of the solid one.
+    pub fn material(&self, pos: Vec3) -> u8 {
+        if !self.aabb().contains(pos) {
+            return 0;
+        }
+        let (leaf, leaf_aabb) = self.root.leaf_containing(pos, self.aabb(), 0, u8::MAX);
+        let t = ((pos - leaf_aabb.start) / leaf_aabb.size).clamp(Vec3::ZERO, Vec3::ONE);
+        let weight = |corner: usize| {
+            let corner = crate::CUBE_CORNERS[corner];
+            let weights = corner * t + (Vec3::ONE - corner) * (Vec3::ONE - t);
+            weights.x * weights.y * weights.z
+        };
+        (0..8).max_by(|&a, &b| {
+            (leaf.values[a] > 0.0).cmp(&(leaf.values[b] > 0.0)).then(weight(a).total_cmp(&weight(b)))
+        })
+        .map_or(0, |corner| leaf.materials[corner])
+    }
+
+    /// Sets the material of each corner of the leaves overlapping `region`
+    /// that touch the isosurface to the one `paint` returns for its
+    /// position, if any. Corners inside of a frozen region are left alone.
+    /// 
+    /// See [`GenStage::Materials`](crate::GenStage::Materials).
+    pub fn paint_materials(&mut self, region: AABB, mut paint: impl FnMut(&NaiveOctree, Vec3) -> Option<u8>) {
+        // Every material is worked out before any are written, so `paint`
+        // can look at the whole Terrain
+        let mut painted = Vec::new();
+        self.root.for_each_surface_leaf(region, self.aabb(), &mut |leaf_aabb| {
+            painted.push(leaf_aabb.calculate_corners().map(|corner| {
+                if self.frozen.iter().any(|frozen| frozen.contains(corner)) { None } else { paint(self, corner) }
+            }));
         });
+        let aabb = self.aabb();
+        self.root.set_surface_materials(region, aabb, &mut painted.into_iter());
+    }
+
+    /// Returns the heights where the vertical line at `x`, `z` crosses the
+    /// isosurface, from the bottom up. Only the leaves the line passes
+    /// through are visited.
+    pub fn column_surface_heights(&self, x: f32, z: f32) -> Vec<f32> {
+        let mut heights = Vec::new();
+        self.root.column_crossings(x, z, self.aabb(), &mut heights);
+        heights.sort_unstable_by(f32::total_cmp);
+        // Lines along the faces of cells can see the same crossing twice
+        heights.dedup_by(|a, b| (*a - *b).abs() < 1e-4);
+        heights
+    }
+
+    /// Same as [`sample`](Self::sample), but the value is interpolated from
+    /// the corners of the cell at `depth` containing `pos`, as if the
+    /// Terrain had been collapsed to that depth.
+    pub fn sample_at_depth(&self, pos: Vec3, depth: u8) -> f32 {
+        if !self.aabb().contains(pos) {
+            return -1.0;
+        }
+        self.root.sample(pos, self.aabb(), 0, depth)
+    }
+
+    /// Returns the gradient of the terrain values at `pos`, pointing
+    /// towards increasing values (i.e. into the terrain).
+    pub fn gradient(&self, pos: Vec3) -> Vec3 {
+        let epsilon = self.scale / 4096.0;
+        let diff = |offset: Vec3| {
+            (self.sample(pos + offset) - self.sample(pos - offset)) / (2.0 * epsilon)
+        };
+        Vec3::new(
+            diff(Vec3::X * epsilon),
+            diff(Vec3::Y * epsilon),
+            diff(Vec3::Z * epsilon),
+        )
+    }
+
+    /// Checks if a sphere overlaps the solid part of the terrain.
+    /// 
+    /// The distance to the surface is estimated from the value and
+    /// gradient at `center`, so the result is approximate and works best
+    /// for spheres that are small compared to the surface's features.
+    pub fn overlap_sphere(&self, center: Vec3, radius: f32) -> Option<Penetration> {
+        let value = self.sample(center);
+        let gradient = self.gradient(center);
+        let slope = gradient.length();
+
+        if slope < f32::EPSILON {
+            // Too far from the surface to tell which way is out
+            return (value > 0.0).then_some(Penetration { normal: Vec3::ZERO, depth: radius });
+        }
+
+        let distance = -value / slope;
+        (distance < radius).then(|| Penetration {
+            normal: -gradient / slope,
+            depth: radius - distance,
+        })
+    }
+
+    /// Finds a point on the surface near `pos`, and the surface normal
+    /// at that point, by stepping along the gradient towards the surface.
+    /// Returns [None] if no surface is found within `max_dist` of `pos`.
+    /// 
+    /// The point found is usually, but not always, the closest one.
+    pub fn closest_surface_point(&self, pos: Vec3, max_dist: f32) -> Option<(Vec3, Vec3)> {
+        const MAX_STEPS: usize = 32;
+        let tolerance = self.scale / 65536.0;
+
+        let mut point = pos;
+        for _ in 0..MAX_STEPS {
+            let value = self.sample(point);
+            let gradient = self.gradient(point);
+            let slope_squared = gradient.length_squared();
+            if slope_squared < f32::EPSILON {
+                return None;
+            }
+
+            if value.abs() <= tolerance * slope_squared.sqrt() {
+                return (point.distance(pos) <= max_dist).then(|| (point, -gradient.normalize()));
+            }
+
+            // Newton step towards the isosurface, limited so a flat
+            // gradient can't throw the point away
+            let step = (gradient * (value / slope_squared)).clamp_length_max(max_dist);
+            point -= step;
+            if point.distance(pos) > max_dist {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Returns true if no solid part of the terrain lies on the line
+    /// between `a` and `b`.
+    pub fn is_visible(&self, a: Vec3, b: Vec3) -> bool {
+        let length = a.distance(b);
+        if length == 0.0 {
+            return self.sample(a) <= 0.0;
+        }
+        self.first_solid(a, (b - a) / length, length).is_none()
+    }
+
+    /// Projects a rectangle onto the surface of the Terrain, returning a
+    /// grid of `resolution` by `resolution` quads that follows the surface.
+    /// 
+    /// Rays are cast along `projection.direction` from each vertex of the
+    /// rectangle. Vertices whose rays miss are left out, along with the
+    /// quads that use them. The faces point back towards the projector.
+    /// 
+    /// If `projection.right` is parallel to the direction, an arbitrary
+    /// perpendicular U axis is used instead. A zero direction projects
+    /// nothing.
+    pub fn project_decal(&self, projection: &DecalProjection) -> DecalMesh {
+        let Some(dir) = projection.direction.try_normalize() else {
+            return DecalMesh::default();
+        };
+        let right = (projection.right - dir * projection.right.dot(dir))
+            .try_normalize()
+            .unwrap_or_else(|| dir.any_orthonormal_vector());
+        let up = right.cross(dir);
+        let resolution = projection.resolution.max(1);
+
+        let mut decal = DecalMesh::default();
+        let mut indices = vec![None; (resolution + 1) * (resolution + 1)];
+        for j in 0..=resolution {
+            for i in 0..=resolution {
+                let uv = Vec2::new(i as f32, j as f32) / resolution as f32;
+                let offset = (uv - 0.5) * projection.size;
+                let origin = projection.center + right * offset.x + up * offset.y;
+                if let Some(t) = self.first_solid(origin, dir, projection.max_distance) {
+                    indices[i + j * (resolution + 1)] = Some(decal.verts.len());
+                    decal.verts.push(origin + dir * (t - projection.offset));
+                    decal.uvs.push(Vec2::new(uv.x, 1.0 - uv.y));
+                }
+            }
+        }
+
+        let index = |i: usize, j: usize| indices[i + j * (resolution + 1)];
+        for j in 0..resolution {
+            for i in 0..resolution {
+                if let (Some(a), Some(b), Some(c), Some(d)) = (index(i, j), index(i+1, j), index(i+1, j+1), index(i, j+1)) {
+                    decal.faces.push([a, b, c]);
+                    decal.faces.push([a, c, d]);
+                }
+            }
+        }
+        decal
+    }
+
+    /// Returns the distance along the ray from `origin` in direction `dir`
+    /// where it first enters the solid part of the terrain, up to
+    /// `max_dist`.
+    /// 
+    /// Empty leaves are skipped over, and other leaves are sampled at
+    /// intervals of half their size. The crossing is then refined by
+    /// bisection.
+    fn first_solid(&self, origin: Vec3, dir: Vec3, max_dist: f32) -> Option<f32> {
+        let terrain_aabb = self.aabb();
+        let (near, far) = terrain_aabb.ray_intersection(origin, dir)?;
+        let (start, end) = (near.max(0.0), far.min(max_dist));
+
+        let mut last_t = start;
+        let mut t = start;
+        while t <= end {
+            let point = origin + dir * t;
+            let (leaf, leaf_aabb) = self.root.leaf_containing(point, terrain_aabb, 0, u8::MAX);
+            let local = ((point - leaf_aabb.start) / leaf_aabb.size).clamp(Vec3::ZERO, Vec3::ONE);
+            if utils::trilinear(&leaf.values, local) > 0.0 {
+                return Some(self.refine_crossing(origin, dir, last_t, t));
+            }
+            last_t = t;
+            // Empty leaves can't contain solid samples, so skip to their
+            // far side (nudged over the boundary)
+            let leaf_size = leaf_aabb.size.min_element();
+            t += if leaf.values.iter().all(|&val| val <= 0.0) {
+                leaf_aabb.ray_intersection(point, dir).map_or(0.0, |(_, exit)| exit) + leaf_size * 0.001
+            }
+            else {
+                leaf_size * 0.5
+            };
+        }
+
+        (self.sample(origin + dir * end) > 0.0).then(|| self.refine_crossing(origin, dir, last_t, end))
+    }
+
+    /// Bisects between an empty sample at `empty_t` and a solid sample at
+    /// `solid_t` to find where the ray crosses the surface.
+    fn refine_crossing(&self, origin: Vec3, dir: Vec3, mut empty_t: f32, mut solid_t: f32) -> f32 {
+        if self.sample(origin + dir * empty_t) > 0.0 {
+            return empty_t;
+        }
+        for _ in 0..12 {
+            let mid = (empty_t + solid_t) / 2.0;
+            if self.sample(origin + dir * mid) > 0.0 {
+                solid_t = mid;
+            }
+            else {
+                empty_t = mid;
+            }
+        }
+        (empty_t + solid_t) / 2.0
+    }
+
+    /// Samples the highest surface crossing of each column of a
+    /// `resolution` by `resolution` grid, looking down along `up_axis`.
+    /// 
+    /// The heights are measured along `up_axis` from the bottom of the
+    /// Terrain, and indexed as
+    /// `u + v * resolution`, where `u` and `v` are the other two axes in
+    /// XYZ order, sampled at the center of each grid cell. Columns without
+    /// any solid part are [None], so they can't be mistaken for ground at
+    /// the bottom of the Terrain.
+    /// 
+    /// Overhangs are lost, since only the highest crossing is kept.
+    pub fn to_heightfield(&self, resolution: usize, up_axis: Axis) -> Vec<Option<f32>> {
+        let up = up_axis.unit();
+        let (u_axis, v_axis) = up_axis.others();
+        let cell_size = self.scale / resolution as f32;
+
+        (0..resolution * resolution).map(|index| {
+            let (u, v) = (index % resolution, index / resolution);
+            let top = self.origin +
+                u_axis.unit() * ((u as f32 + 0.5) * cell_size) +
+                v_axis.unit() * ((v as f32 + 0.5) * cell_size) +
+                up * self.scale;
+            self.first_solid(top, -up, self.scale).map(|t| self.scale - t)
+        })
+        .collect()
+    }
+
+    /// Returns the outward surface normal at `pos`, or zero if the
+    /// terrain is flat there.
+    pub fn normal(&self, pos: Vec3) -> Vec3 {
+        -self.gradient(pos).normalize_or_zero()
+    }
+
+    /// Classifies the surface at each of `verts`, for blending materials
+    /// by steepness and shape.
+    /// 
+    /// See [SurfaceAttributes] for how slope and curvature are measured.
+    pub fn surface_attributes(&self, verts: &[Vec3], up: Vec3) -> Vec<SurfaceAttributes> {
+        let up = up.normalize();
+        verts.iter().map(|&vert| {
+            // Normals are only smooth across a few leaves, so the
+            // differences are taken at the scale of the local leaf
+            let epsilon = self.root.leaf_containing(vert, self.aabb(), 0, u8::MAX).1.size.min_element();
+            let normal = self.normal(vert);
+            let diff = |axis: Vec3| {
+                (self.normal(vert + axis * epsilon) - self.normal(vert - axis * epsilon)) / (2.0 * epsilon)
+            };
+            let curvature = diff(Vec3::X).x + diff(Vec3::Y).y + diff(Vec3::Z).z;
+            SurfaceAttributes {
+                slope: normal.dot(up).clamp(-1.0, 1.0).acos(),
+                curvature,
+            }
+        })
+        .collect()
+    }
+
+    /// Scatters points over the surface inside of `region`, returning
+    /// each point with its outward surface normal.
+    /// 
+    /// About `density` points are placed per unit of surface area. Points
+    /// closer together than half the average spacing are rejected, so they
+    /// are spread evenly without looking like a grid. The result only
+    /// depends on the terrain, `region` and `seed`, and is sorted by
+    /// position. Only the leaves overlapping `region` are meshed, up to
+    /// `max_depth`.
+    pub fn scatter_surface_points(&self, region: AABB, density: f32, seed: Seed, max_depth: u8) -> Vec<(Vec3, Vec3)> {
+        let mut faces = Vec::new();
+        self.root.generate_mesh_region(&mut faces, region, 0, max_depth, self.aabb());
+
+        let mut candidates: Vec<Vec3> = Vec::new();
+        for [a, b, c] in faces {
+            let centroid = (a + b + c) / 3.0;
+            if !region.contains(centroid) {
+                continue;
+            }
+            let area = (b - a).cross(c - a).length() / 2.0;
+            let mut rng = seed.rng_at(centroid);
+            let expected = area * density;
+            let count = expected as usize + (rng.next_f32() < expected.fract()) as usize;
+            for _ in 0..count {
+                // Uniform point in the triangle
+                let (r1, r2) = (rng.next_f32().sqrt(), rng.next_f32());
+                let point = a * (1.0 - r1) + b * (r1 * (1.0 - r2)) + c * (r1 * r2);
+                if region.contains(point) {
+                    candidates.push(point);
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| a.to_array().partial_cmp(&b.to_array()).unwrap());
+
+        // Reject candidates that are too close to already accepted points
+        let min_dist = 0.5 / density.sqrt();
+        let mut grid: ahash::AHashMap<IVec3, Vec<Vec3>> = Default::default();
+        let cell_of = |point: Vec3| (point / min_dist).floor().as_ivec3();
+        let mut points = Vec::new();
+        for point in candidates {
+            let cell = cell_of(point);
+            let too_close = utils::neighborhood()
+                .filter_map(|offset| grid.get(&(cell + offset)))
+                .flatten()
+                .any(|other| other.distance_squared(point) < min_dist * min_dist);
+            if !too_close {
+                grid.entry(cell).or_default().push(point);
+                points.push((point, self.normal(point)));
+            }
+        }
+
+        points
     }
 
     /// Uses Marching Cubes to generate an [UnindexedMesh].
     pub fn generate_mesh(&self, max_depth: u8) -> UnindexedMesh {
+        span!("generate_mesh", max_depth, triangles = tracing::field::Empty);
         let mut faces = Vec::new();
-        self.root.generate_mesh(&mut faces, 0, max_depth, AABB { start: Vec3::ZERO, size: Vec3::splat(self.scale) });
-        return UnindexedMesh {
+        self.root.generate_mesh(&mut faces, 0, max_depth, self.aabb());
+        span_record!("triangles", faces.len());
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+
+    /// Uses Marching Cubes to generate an [UnindexedMesh] of the fluid
+    /// layer. See [`NaiveOctreeCell::generate_fluid_mesh`].
+    pub fn generate_fluid_mesh(&self, max_depth: u8) -> UnindexedMesh {
+        span!("generate_fluid_mesh", max_depth, triangles = tracing::field::Empty);
+        let mut faces = Vec::new();
+        self.root.generate_fluid_mesh(&mut faces, 0, max_depth, self.aabb());
+        span_record!("triangles", faces.len());
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+
+    /// Same as [`generate_mesh`](Self::generate_mesh), but the triangle
+    /// buffer is taken from `pool`.
+    pub fn generate_mesh_pooled(&self, max_depth: u8, pool: &MeshBufferPool) -> UnindexedMesh {
+        span!("generate_mesh", max_depth, triangles = tracing::field::Empty);
+        let mut faces = pool.take_faces();
+        self.root.generate_mesh(&mut faces, 0, max_depth, self.aabb());
+        span_record!("triangles", faces.len());
+        UnindexedMesh {
             faces,
             normals: None,
         }
@@ -346,9 +2348,10 @@ impl NaiveOctree {
     /// Uses Marching Cubes to generate an [UnindexedMesh].
     #[cfg(feature = "multi-thread")]
     pub fn par_generate_mesh(&self, max_depth: u8) -> UnindexedMesh {
+        span!("par_generate_mesh", max_depth);
         let faces = Stack::new();
         rayon::in_place_scope(|_| {
-            self.root.par_generate_mesh(&faces, 0, max_depth, AABB { start: Vec3::ZERO, size: Vec3::splat(self.scale) });
+            self.root.par_generate_mesh(&faces, 0, max_depth, self.aabb());
         });
 
         UnindexedMesh {
@@ -357,14 +2360,309 @@ impl NaiveOctree {
         }
     }
 
-    /// Debugging method to generate an Octree frame.
-    pub fn generate_octree_frame_mesh(&self, max_depth: u8) -> UnindexedMesh {
-        let mut faces = Vec::new();
-        self.root.generate_octree_frame_mesh(&mut faces, max_depth, AABB { start: Vec3::ZERO, size: Vec3::splat(self.scale) });
-        return UnindexedMesh {
-            faces,
-            normals: None,
+    /// Generates one mesh per ring of `settings` around `focus`, with
+    /// each ring meshed one depth coarser than the ring inside it.
+    ///
+    /// The cracks where rings of different depths meet are filled in, so
+    /// the rings join up without gaps. The triangles filling a crack go to
+    /// the finer ring.
+    pub fn generate_clipmap(&self, focus: Vec3, settings: &ClipmapSettings) -> Vec<UnindexedMesh> {
+        span!("generate_clipmap", max_depth = settings.max_depth, levels = settings.levels);
+        let mut rings = vec![Vec::new(); settings.levels.max(1) as usize];
+        let level = |aabb: AABB| settings.level(focus, aabb) as usize;
+        let depth = |aabb: AABB| settings.max_depth.saturating_sub(settings.level(focus, aabb));
+
+        let mut mesher = SeamMesher::new(self.scale / 2f32.powi(settings.max_depth.min(Self::MAX_DEPTH) as i32));
+        mesher.add(self, depth);
+        mesher.for_each_leaf(self.aabb(), &mut |aabb| rings[level(aabb)].extend(mesher.march(aabb)));
+
+        let faces: Vec<[Vec3; 3]> = rings.iter().flatten().copied().collect();
+        for (inside, tris) in mesher.fill_cracks(&faces) {
+            rings[level(AABB { start: inside, size: Vec3::ZERO })].extend(tris);
+        }
+        rings.into_iter()
+            .map(|faces| UnindexedMesh { faces, normals: None })
+            .collect()
+    }
+
+    /// Debugging method to generate an Octree frame.
+    pub fn generate_octree_frame_mesh(&self, max_depth: u8) -> UnindexedMesh {
+        let mut faces = Vec::new();
+        let options = FrameMeshOptions { max_depth, ..Default::default() };
+        self.root.generate_frame_mesh(&mut faces, None, &options, 0, self.aabb());
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+
+    /// Debugging method to generate an Octree frame, with each leaf colored
+    /// by its depth and optionally filtered.
+    pub fn generate_colored_frame_mesh(&self, options: &FrameMeshOptions) -> ColoredMesh {
+        let mut faces = Vec::new();
+        let mut colors = Vec::new();
+        self.root.generate_frame_mesh(&mut faces, Some(&mut colors), options, 0, self.aabb());
+        ColoredMesh {
+            mesh: UnindexedMesh {
+                faces,
+                normals: None,
+            },
+            colors,
+        }
+    }
+}
+
+/// A ToolFunc that samples the values of a [NaiveOctree], so one
+/// terrain can be stamped into another.
+/// 
+/// See also: [`NaiveOctree::stamp`]
+#[derive(Debug, Clone, Copy)]
+pub struct OctreeStamp<'a> {
+    terrain: &'a NaiveOctree,
+}
+
+impl<'a> OctreeStamp<'a> {
+    pub fn new(terrain: &'a NaiveOctree) -> Self {
+        Self { terrain }
+    }
+}
+
+impl ToolFunc for OctreeStamp<'_> {
+    fn value(&self, pos: Vec3) -> f32 {
+        self.terrain.sample(pos)
+    }
+
+    fn gradient(&self, pos: Vec3) -> Vec3 {
+        self.terrain.gradient(pos)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        self.terrain.aabb()
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        self.terrain.aabb()
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        true
+    }
+}
+
+/// A broken invariant found by [`NaiveOctree::validate`], with the AABB
+/// of the cell it was found in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Violation {
+    /// A corner value is NaN or infinite.
+    NonFinite(AABB),
+    /// A corner value is outside of `-1.0..=1.0`.
+    OutOfRange(AABB),
+    /// A corner value differs from the child sharing that corner.
+    CornerMismatch(AABB),
+    /// The cell's children should have been collapsed.
+    NotCollapsed(AABB),
+}
+
+/// Describes how [`NaiveOctree::project_decal`] projects a rectangle onto
+/// the Terrain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecalProjection {
+    /// The center of the rectangle the rays start from.
+    pub center: Vec3,
+    /// The direction the decal is projected in.
+    pub direction: Vec3,
+    /// The direction of the U texture axis. It is made perpendicular to
+    /// `direction`, and the V axis is perpendicular to both.
+    pub right: Vec3,
+    /// The width and height of the rectangle.
+    pub size: Vec2,
+    /// The number of quads along each side.
+    pub resolution: usize,
+    /// How far the rays are cast.
+    pub max_distance: f32,
+    /// How far the vertices are lifted off of the surface, back towards
+    /// the projector, to keep the decal from clipping into it.
+    pub offset: f32,
+}
+
+impl Default for DecalProjection {
+    fn default() -> Self {
+        Self {
+            center: Vec3::ZERO,
+            direction: Vec3::NEG_Y,
+            right: Vec3::X,
+            size: Vec2::ONE,
+            resolution: 8,
+            max_distance: 100.0,
+            offset: 0.01,
+        }
+    }
+}
+
+/// How far a shape penetrates the terrain.
+/// 
+/// Moving the shape by `normal * depth` resolves the overlap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Penetration {
+    /// The direction out of the terrain. This is zero if the shape is
+    /// too deep inside the terrain to find the surface.
+    pub normal: Vec3,
+    pub depth: f32,
+}
+
+/// The shape of the terrain surface at a point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceAttributes {
+    /// The angle between the surface normal and the up direction, in
+    /// radians. Flat ground is 0.0 and vertical cliffs are about PI/2.
+    pub slope: f32,
+    /// The divergence of the surface normal, which is twice the mean
+    /// curvature. This is positive on ridges and bumps, negative in
+    /// crevices and dips, and 0.0 on flat ground.
+    pub curvature: f32,
+}
+
+/// The state of an [ApplyTask] after a call to [`ApplyTask::run_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    /// There are cells left to visit.
+    InProgress,
+    /// The tool has been fully applied.
+    Complete,
+}
+
+/// A cell waiting to be visited by an [ApplyTask].
+#[derive(Debug, Clone)]
+struct TaskFrame {
+    /// The child indices leading from the root to this cell.
+    path: Vec<u8>,
+    corners: [Vec3; 8],
+    /// True once this cell has been applied to and its children queued.
+    visited: bool,
+}
+
+/// An incremental application of a [Tool] to a [NaiveOctree].
+/// 
+/// The traversal is checkpointed between calls to [`run_for`](Self::run_for),
+/// so a large edit can be spread across multiple frames. The end result is
+/// the same as [`NaiveOctree::apply_tool`]. The terrain shouldn't be edited
+/// by other means until the task is complete.
+#[derive(Debug, Clone)]
+pub struct ApplyTask<F: ToolFunc> {
+    tool: Tool<F>,
+    action: Action,
+    max_depth: u8,
+    tool_aabb: AABB,
+    aoe_aabb: AABB,
+    stack: Vec<TaskFrame>,
+    started: bool,
+    budget_exceeded: bool,
+}
+
+impl<F: ToolFunc> ApplyTask<F> {
+    pub fn new(tool: Tool<F>, action: Action, max_depth: u8) -> Self {
+        Self {
+            tool,
+            action,
+            max_depth: max_depth.min(NaiveOctree::MAX_DEPTH),
+            tool_aabb: AABB::default(),
+            aoe_aabb: AABB::default(),
+            stack: Vec::new(),
+            started: false,
+            budget_exceeded: false,
+        }
+    }
+
+    /// Returns true if the task has finished applying its tool.
+    pub fn is_complete(&self) -> bool {
+        self.started && self.stack.is_empty()
+    }
+
+    /// Describes how the edit has gone so far, over every call to
+    /// [`run_for`](Self::run_for).
+    pub fn report(&self) -> EditReport {
+        EditReport {
+            budget_exceeded: self.budget_exceeded,
+            ..Default::default()
+        }
+    }
+
+    /// Continues applying the tool to `terrain` until it is complete or
+    /// `budget` has elapsed. At least one cell is visited per call so
+    /// the task always makes progress.
+    pub fn run_for(&mut self, terrain: &mut NaiveOctree, budget: Duration) -> Progress {
+        let deadline = Instant::now() + budget;
+
+        if !self.started {
+            self.started = true;
+            let Some((tool_aabb, aoe_aabb)) = terrain.clip_tool_aabbs(&self.tool, self.action) else {
+                return Progress::Complete;
+            };
+            self.tool_aabb = tool_aabb;
+            self.aoe_aabb = aoe_aabb;
+            self.stack.push(TaskFrame {
+                path: Vec::new(),
+                corners: terrain.aabb().calculate_corners(),
+                visited: false,
+            });
+        }
+
+        let options = ApplyOptions::default();
+        // The Terrain may have been edited in between calls, so the budget is counted again
+        let budget = terrain.node_budget.map(|budget| budget.saturating_sub(terrain.cell_count()));
+        let mut ctx = ApplyContext::new(&options, budget, &terrain.frozen);
+        ctx.shell = terrain.shell();
+        while let Some(frame) = self.stack.last_mut() {
+            // The path may no longer exist if the terrain was edited in between calls
+            let Some(cell) = frame.path.iter().try_fold(&mut terrain.root, |cell, &index| {
+                cell.children.as_mut().map(|children| &mut Arc::make_mut(children)[index as usize])
+            }) else {
+                self.stack.pop();
+                continue;
+            };
+
+            let cell_aabb = AABB { start: frame.corners[0], size: frame.corners[7] - frame.corners[0] };
+            if frame.visited {
+                // All children are done, check if collapse is needed
+                if cell.is_collapsible(0.0) && !ctx.touches_frozen(cell_aabb) {
+                    cell.collapse_cell();
+                    ctx.release(8);
+                }
+                self.stack.pop();
+            }
+            else if ctx.is_frozen(cell_aabb) || matches!(self.aoe_aabb.intersect(cell_aabb), DoesNotIntersect) {
+                self.stack.pop();
+            }
+            else {
+                let depth = frame.path.len() as u8;
+                cell.apply_tool_impl(&self.tool, self.tool_aabb, self.aoe_aabb, self.action, &frame.corners, depth, self.max_depth, &ctx);
+
+                if cell.has_children() {
+                    frame.visited = true;
+                    let parent = frame.clone();
+                    let child_corners = utils::subdivide_cell_positions(&parent.corners);
+                    // Pushed in reverse so children are visited in order
+                    for (index, corners) in child_corners.into_iter().enumerate().rev() {
+                        let mut path = parent.path.clone();
+                        path.push(index as u8);
+                        self.stack.push(TaskFrame { path, corners, visited: false });
+                    }
+                }
+                else {
+                    self.stack.pop();
+                }
+            }
+
+            if Instant::now() >= deadline {
+                break;
+            }
         }
+
+        terrain.cells = terrain.cells.saturating_add_signed(ctx.created());
+        self.budget_exceeded |= ctx.budget_exceeded.load(Ordering::Relaxed);
+        if self.stack.is_empty() { Progress::Complete } else { Progress::InProgress }
     }
 }
 
@@ -382,19 +2680,19 @@ fn terrain_test() {
         .translated(Vec3A::splat(50.0));
     println!("Rotated AABB: {:?}", tool.tool_aabb());
     
-    time_test!(terrain.apply_tool(&tool, Action::Place, 5), "NaiveOctree Apply Tool");
+    time_test!(terrain.apply_tool(tool, Action::Place, 5), "NaiveOctree Apply Tool");
     
-    tool = Tool::new(tool.func).scaled(Vec3::splat(20.0)).translated(vec3a(50.0,70.0,50.0));
+    tool = Tool::new(tool.into_func()).scaled(Vec3::splat(20.0)).translated(vec3a(50.0,70.0,50.0));
     time_test!(terrain.apply_tool(tool, Action::Remove, 5), "NaiveOctree Remove Tool");
 
     let mesh = time_test!(terrain.generate_mesh(255), "NaiveOctree Generate UnindexedMesh");
 
-    time_test!(mesh.write_obj_to_file("naive_octree_unindexed.obj"), "NaiveOctree UnindexedMesh To File");
+    time_test!(mesh.write_obj_to_file("naive_octree_unindexed.obj").unwrap(), "NaiveOctree UnindexedMesh To File");
 
     let mesh = time_test!(mesh.index(), "NaiveOctree Mesh Indexing");
     
-    time_test!(mesh.write_obj_to_file("naive_octree_indexed.obj"), "NaiveOctree IndexedMesh To File");
-    terrain.generate_octree_frame_mesh(255).index().write_obj_to_file("naive_octree_frame.obj");
+    time_test!(mesh.write_obj_to_file("naive_octree_indexed.obj").unwrap(), "NaiveOctree IndexedMesh To File");
+    terrain.generate_octree_frame_mesh(255).index().write_obj_to_file("naive_octree_frame.obj").unwrap();
 }
 
 #[test]
@@ -412,19 +2710,19 @@ fn par_terrain_test() {
         .translated(Vec3A::splat(50.0));
     println!("Rotated AABB: {:?}", tool.tool_aabb());
     
-    time_test!(terrain.par_apply_tool(&tool, Action::Place, 5), "NaiveOctree Apply Tool");
+    time_test!(terrain.par_apply_tool(tool, Action::Place, 5), "NaiveOctree Apply Tool");
     
-    tool = Tool::new(tool.func).scaled(Vec3::splat(20.0)).translated(vec3a(50.0,70.0,50.0));
+    tool = Tool::new(tool.into_func()).scaled(Vec3::splat(20.0)).translated(vec3a(50.0,70.0,50.0));
     time_test!(terrain.par_apply_tool(tool, Action::Remove, 5), "NaiveOctree Remove Tool");
 
     let mesh = time_test!(terrain.par_generate_mesh(255), "NaiveOctree Generate UnindexedMesh");
 
-    time_test!(mesh.write_obj_to_file("naive_octree_unindexed.obj"), "NaiveOctree UnindexedMesh To File");
+    time_test!(mesh.write_obj_to_file("naive_octree_unindexed.obj").unwrap(), "NaiveOctree UnindexedMesh To File");
 
     let mesh = time_test!(mesh.index(), "NaiveOctree Mesh Indexing");
     
-    time_test!(mesh.write_obj_to_file("naive_octree_indexed.obj"), "NaiveOctree IndexedMesh To File");
-    terrain.generate_octree_frame_mesh(255).index().write_obj_to_file("naive_octree_frame.obj");
+    time_test!(mesh.write_obj_to_file("naive_octree_indexed.obj").unwrap(), "NaiveOctree IndexedMesh To File");
+    terrain.generate_octree_frame_mesh(255).index().write_obj_to_file("naive_octree_frame.obj").unwrap();
 }
 
 #[test]
@@ -437,12 +2735,12 @@ fn edge_tool_test() {
     let mut terrain = NaiveOctree::new(100.0);
     let tool = Tool::new(Sphere).scaled(Vec3::splat(24.583)).translated(vec3a(0.0,50.0,50.0));
 
-    time_test!(terrain.apply_tool(&tool, Action::Place, 3), "Edge Tool Place");
+    time_test!(terrain.apply_tool(tool, Action::Place, 3), "Edge Tool Place");
 
     let mesh = time_test!(terrain.generate_mesh(255), "Edge Tool Generate Mesh");
     let mesh = time_test!(mesh.index(), "Edge Tool Index Mesh");
 
-    mesh.write_obj_to_file("edge_tool.obj");
+    mesh.write_obj_to_file("edge_tool.obj").unwrap();
 }
 
 #[test]
@@ -461,5 +2759,834 @@ fn cell_mesh_test() {
         faces,
         normals: None,
     };
-    mesh.write_obj_to_file("cell_mesh_test.obj");
-}
\ No newline at end of file
+    mesh.write_obj_to_file("cell_mesh_test.obj").unwrap();
+}
+#[test]
+fn contained_cell_test() {
+    use crate::tool::Sphere;
+
+    let mut terrain = NaiveOctree::new(1.0);
+    let small = Tool::new(Sphere).scaled(Vec3::splat(0.1)).translated(glam::Vec3A::splat(0.5));
+    terrain.apply_tool(small, Action::Place, 4);
+    assert!(terrain.root.has_children());
+
+    // A sphere enclosing the whole terrain fills it in without recursing
+    let big = Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(glam::Vec3A::splat(0.5));
+    terrain.apply_tool(big, Action::Place, 4);
+    assert!(terrain.root.is_leaf());
+    assert!(terrain.root.values.iter().all(|&val| val > 0.0));
+
+    terrain.apply_tool(big, Action::Remove, 4);
+    assert!(terrain.root.is_leaf());
+    assert!(terrain.root.values.iter().all(|&val| val < 0.0));
+}
+
+#[test]
+fn apply_task_test() {
+    use crate::tool::Sphere;
+
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(0.3)).translated(glam::Vec3A::splat(0.5));
+
+    let mut expected = NaiveOctree::new(1.0);
+    expected.apply_tool(tool, Action::Place, 4);
+
+    let mut terrain = NaiveOctree::new(1.0);
+    let mut task = ApplyTask::new(tool, Action::Place, 4);
+    let mut steps = 0;
+    while task.run_for(&mut terrain, Duration::ZERO) == Progress::InProgress {
+        steps += 1;
+    }
+    assert!(steps > 1);
+    assert!(task.is_complete());
+    assert_eq!(terrain.generate_mesh(255).faces, expected.generate_mesh(255).faces);
+}
+
+#[test]
+fn overlap_sphere_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 6);
+
+    assert!(terrain.sample(Vec3::splat(5.0)) > 0.0);
+    assert!(terrain.sample(Vec3::splat(1.0)) < 0.0);
+    assert_eq!(terrain.sample(Vec3::splat(-1.0)), -1.0);
+
+    // Overlapping the top of the sphere by about 0.5
+    let penetration = terrain.overlap_sphere(vec3(5.0, 7.5, 5.0), 1.0).unwrap();
+    assert!(penetration.normal.abs_diff_eq(Vec3::Y, 0.1));
+    assert!((penetration.depth - 0.5).abs() < 0.2);
+
+    assert!(terrain.overlap_sphere(vec3(5.0, 9.0, 5.0), 1.0).is_none());
+}
+
+#[test]
+fn closest_surface_point_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 6);
+
+    let (point, normal) = terrain.closest_surface_point(vec3(5.0, 7.5, 5.0), 1.0).unwrap();
+    assert!(point.abs_diff_eq(vec3(5.0, 7.0, 5.0), 0.05));
+    assert!(normal.abs_diff_eq(Vec3::Y, 0.05));
+
+    assert!(terrain.closest_surface_point(vec3(5.0, 7.5, 5.0), 0.1).is_none());
+}
+
+#[test]
+fn is_visible_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 6);
+
+    assert!(!terrain.is_visible(vec3(1.0, 5.0, 5.0), vec3(9.0, 5.0, 5.0)));
+    assert!(terrain.is_visible(vec3(1.0, 8.0, 5.0), vec3(9.0, 8.0, 5.0)));
+    assert!(terrain.is_visible(vec3(-5.0, 5.0, 5.0), vec3(2.0, 5.0, 5.0)));
+    assert!(!terrain.is_visible(vec3(-5.0, 5.0, 5.0), vec3(15.0, 5.0, 5.0)));
+}
+
+#[test]
+fn heightfield_test() {
+    use crate::tool::Sphere;
+    use glam::Vec3A;
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 6);
+
+    let heights = terrain.to_heightfield(5, Axis::Y);
+    assert_eq!(heights.len(), 25);
+    // The center column passes through the top of the sphere
+    assert!((heights[12].unwrap() - 7.0).abs() < 0.1);
+    assert_eq!(heights[0], None);
+
+    let heights = terrain.to_heightfield(5, Axis::X);
+    assert!((heights[12].unwrap() - 7.0).abs() < 0.1);
+
+    // Ground at the very bottom is still a height
+    let mut floor = NaiveOctree::new(10.0);
+    floor.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::new(5.0, -2.0, 5.0)), Action::Place, 6);
+    let heights = floor.to_heightfield(5, Axis::Y);
+    assert!((heights[12].unwrap() - 1.0).abs() < 0.1);
+    assert_eq!(heights[0], None);
+}
+
+#[test]
+fn surface_attributes_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 6);
+
+    let attributes = terrain.surface_attributes(&[vec3(5.0, 7.0, 5.0), vec3(7.0, 5.0, 5.0)], Vec3::Y);
+    assert!(attributes[0].slope < 0.1);
+    assert!((attributes[1].slope - std::f32::consts::FRAC_PI_2).abs() < 0.1);
+    // A sphere of radius 2 has a mean curvature of 0.5
+    assert!(attributes.iter().all(|attr| (attr.curvature - 1.0).abs() < 0.1));
+}
+
+#[test]
+fn scatter_surface_points_test() {
+    use crate::tool::Sphere;
+    use glam::Vec3A;
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 5);
+
+    let top_half = AABB::from_min_max(Vec3::new(0.0, 5.0, 0.0), Vec3::splat(10.0));
+    let points = terrain.scatter_surface_points(top_half, 4.0, Seed(7), 5);
+    assert!(!points.is_empty());
+    assert!(points.iter().all(|(point, normal)| point.y >= 5.0 && normal.y > -0.1));
+    assert!(points.iter().all(|(point, _)| (point.distance(Vec3::splat(5.0)) - 2.0).abs() < 0.1));
+
+    assert_eq!(points, terrain.scatter_surface_points(top_half, 4.0, Seed(7), 5));
+    assert_ne!(points, terrain.scatter_surface_points(top_half, 4.0, Seed(8), 5));
+
+    // Meshing only the region gives the same triangles as the whole Terrain
+    let mut faces = Vec::new();
+    terrain.root.generate_mesh_region(&mut faces, top_half, 0, 5, terrain.aabb());
+    let whole = terrain.generate_mesh(5).faces.into_iter()
+        .filter(|[a, b, c]| top_half.contains((*a + *b + *c) / 3.0))
+        .count();
+    assert_eq!(faces.iter().filter(|[a, b, c]| top_half.contains((*a + *b + *c) / 3.0)).count(), whole);
+    assert!(faces.len() < terrain.generate_mesh(5).faces.len());
+
+    // A coarser depth still finds the surface
+    assert!(!terrain.scatter_surface_points(top_half, 4.0, Seed(7), 3).is_empty());
+}
+
+#[test]
+fn carve_caves_test() {
+    use crate::tool::Sphere;
+    use glam::Vec3A;
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(20.0)).translated(Vec3A::splat(5.0)), Action::Place, 5);
+    assert!(terrain.generate_mesh(255).faces.is_empty());
+
+    let region = AABB::from_min_max(Vec3::splat(2.0), Vec3::splat(8.0));
+    let params = NoiseParams { frequency: 0.3, ..Default::default() };
+    terrain.carve_caves(&params, region, 5);
+
+    let mesh = terrain.generate_mesh(255);
+    assert!(!mesh.faces.is_empty());
+    assert!(mesh.faces.iter().flatten().all(|&vert| region.expanded_by(0.5).contains(vert)));
+    assert!(terrain.sample(Vec3::splat(1.0)) > 0.0);
+}
+
+#[test]
+fn smooth_region_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, vec3a, Vec3A };
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 5);
+    // A small bump on top of the sphere
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(0.6)).translated(vec3a(5.0, 7.2, 5.0)), Action::Place, 5);
+
+    let outside = vec3(5.0, 5.0, 3.1);
+    let before = terrain.sample(outside);
+    let bump = vec3(5.0, 7.5, 5.0);
+    assert!(terrain.sample(bump) > 0.0);
+
+    let region = AABB::from_min_max(vec3(3.0, 6.0, 3.0), vec3(7.0, 9.0, 7.0));
+    let unsmoothed = terrain.clone();
+    terrain.smooth_region(region, 4, 5);
+
+    assert!(terrain.sample(bump) < 0.0);
+    assert_eq!(terrain.sample(outside), before);
+    assert!(!terrain.generate_mesh(255).faces.is_empty());
+
+    // Frozen regions keep their values
+    let mut frozen = unsmoothed.clone();
+    frozen.frozen.push(AABB::from_min_max(vec3(4.0, 7.0, 4.0), vec3(6.0, 8.0, 6.0)));
+    frozen.smooth_region(region, 4, 5);
+    assert_eq!(frozen.sample(bump), unsmoothed.sample(bump));
+
+    // The boundary shell keeps smoothing from wearing anything away
+    let mut shelled = unsmoothed.clone();
+    shelled.boundary_shell = Some(5.0);
+    shelled.smooth_region(region, 4, 5);
+    assert!(shelled.sample(bump) > 0.0);
+
+    // And the node budget limits the subdivision
+    let mut limited = unsmoothed.clone();
+    limited.node_budget = Some(limited.cell_count());
+    assert!(limited.smooth_region(region, 4, 6).budget_exceeded);
+    assert!(limited.cell_count() <= unsmoothed.cell_count());
+    assert!(!unsmoothed.clone().smooth_region(region, 4, 5).budget_exceeded);
+}
+
+#[test]
+fn erode_region_test() {
+    use crate::tool::Heightmap;
+    use glam::{ vec3, Vec3A };
+
+    // Low ground on one side and a plateau on the other, with a cliff between
+    let heights = (0..64).map(|index| if index % 8 < 4 { 0.25 } else { 0.75 }).collect();
+    let mut terrain = NaiveOctree::new(32.0);
+    terrain.apply_tool(Tool::new(Heightmap::new(8, 8, heights)).scaled(vec3(32.0, 16.0, 32.0)), Action::Place, 5);
+    let top = |terrain: &NaiveOctree, x: f32| *terrain.column_surface_heights(x, 16.0).last().unwrap();
+    let steepest = |terrain: &NaiveOctree| (1..30).map(|x| (top(terrain, x as f32 + 1.0) - top(terrain, x as f32)).abs()).fold(0.0, f32::max);
+    let volume = |terrain: &NaiveOctree| (1..31).map(|x| top(terrain, x as f32)).sum::<f32>();
+
+    let uneroded = terrain.clone();
+    terrain.erode_region(terrain.aabb(), 40, 0.5, 5);
+    assert!(terrain.validate().is_empty());
+    assert!(steepest(&uneroded) > 1.5);
+    assert!(steepest(&terrain) < 0.8, "{}", steepest(&terrain));
+    // The cliff slid down, and the material was kept
+    assert!(top(&terrain, 18.0) < top(&uneroded, 18.0) - 1.0);
+    assert!(top(&terrain, 12.0) > top(&uneroded, 12.0) + 1.0);
+    assert!((volume(&terrain) - volume(&uneroded)).abs() < 1.0);
+    // The border keeps its height, so neighbouring chunks still line up
+    assert_eq!(top(&terrain, 1.0), top(&uneroded, 1.0));
+    assert_eq!(terrain.sample(vec3(32.0, 8.0, 5.0)), uneroded.sample(vec3(32.0, 8.0, 5.0)));
+
+    // Frozen regions keep their values
+    let mut frozen = uneroded.clone();
+    frozen.frozen.push(AABB::from_min_max(vec3(0.0, 0.0, 0.0), vec3(32.0, 16.0, 32.0)));
+    frozen.erode_region(frozen.aabb(), 40, 0.5, 5);
+    assert_eq!(top(&frozen, 18.0), top(&uneroded, 18.0));
+
+    // Flat ground doesn't change
+    let mut flat = NaiveOctree::new(32.0);
+    flat.apply_tool(Tool::new(Heightmap::new(2, 2, vec![0.5; 4])).scaled(vec3(32.0, 16.0, 32.0)).translated(Vec3A::ZERO), Action::Place, 5);
+    let cells = flat.cell_count();
+    flat.erode_region(flat.aabb(), 10, 0.5, 5);
+    assert_eq!(flat.cell_count(), cells);
+}
+
+#[test]
+fn stamp_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, Vec3A };
+
+    let mut rock = NaiveOctree::new(2.0);
+    rock.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(0.5)).translated(Vec3A::splat(1.0)), Action::Place, 4);
+    assert_eq!(rock.depth(), 4);
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.stamp(&rock, Affine3A::from_translation(vec3(4.0, 4.0, 4.0)), Action::Place);
+    assert!(terrain.sample(Vec3::splat(5.0)) > 0.0);
+    assert!(terrain.sample(Vec3::splat(3.0)) < 0.0);
+
+    terrain.stamp(&rock, Affine3A::from_translation(vec3(4.0, 4.0, 4.0)), Action::Remove);
+    assert!(terrain.sample(Vec3::splat(5.0)) < 0.0);
+}
+
+#[test]
+fn colored_frame_mesh_test() {
+    use crate::tool::Sphere;
+    use glam::Vec3A;
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 4);
+
+    let all = terrain.generate_colored_frame_mesh(&FrameMeshOptions { max_depth: 4, ..Default::default() });
+    assert_eq!(all.colors.len(), all.mesh.faces.len() * 3);
+    assert_eq!(all.mesh.faces, terrain.generate_octree_frame_mesh(4).faces);
+
+    let surface = terrain.generate_colored_frame_mesh(&FrameMeshOptions { max_depth: 4, surface_only: true, ..Default::default() });
+    assert!(!surface.mesh.faces.is_empty());
+    // Only the deepest cells intersect the surface
+    assert!(surface.colors.iter().all(|&color| color == depth_color(4, 4)));
+
+    let region = AABB::from_min_max(Vec3::ZERO, Vec3::splat(1.0));
+    let corner = terrain.generate_colored_frame_mesh(&FrameMeshOptions { max_depth: 4, region: Some(region), ..Default::default() });
+    assert_eq!(corner.mesh.faces.len(), 12 * 12);
+    assert!(corner.to_obj_string().lines().any(|line| line.starts_with("v ") && line.split(' ').count() == 7));
+}
+
+#[test]
+fn impact_test() {
+    use glam::{ vec3, vec3a };
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.default_depth = 5;
+    // A slab of ground 8 units deep
+    terrain.apply(Tool::new(Sphere).scaled(vec3(100.0, 8.0, 100.0)).translated(vec3a(8.0, 0.0, 8.0)), Action::Place);
+    assert!(terrain.sample(vec3(8.0, 7.0, 8.0)) > 0.0);
+
+    terrain.impact(vec3(8.0, 8.0, 8.0), 3.0, 1.0);
+    assert!(terrain.sample(vec3(8.0, 7.0, 8.0)) < 0.0);
+    // The rim rises above the ground
+    assert!(terrain.sample(vec3(11.5, 8.3, 8.0)) > 0.0);
+    assert!(terrain.find_islands(AABB::from_radius(vec3(8.0, 8.0, 8.0), 6.0), 5).is_empty());
+
+    // A floating rock is found as debris
+    terrain.apply(Tool::new(Sphere).translated(vec3a(8.0, 12.0, 8.0)), Action::Place);
+    let islands = terrain.impact_with_debris(vec3(2.0, 8.0, 2.0), 1.0, 0.0);
+    assert!(islands.is_empty());
+    let islands = terrain.find_islands(AABB::from_radius(vec3(8.0, 12.0, 8.0), 3.0), 5);
+    assert_eq!(islands.len(), 1);
+    assert!(islands[0].contains(vec3(8.0, 12.0, 8.0)));
+}
+
+#[test]
+fn collapse_tolerance_test() {
+    use glam::vec3;
+
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(6.0)).translated(Vec3A::splat(8.0));
+    let mut exact = NaiveOctree::new(16.0);
+    exact.apply_tool(tool, Action::Place, 6);
+    let mut merged = NaiveOctree::new(16.0);
+    merged.apply_tool_with_tolerance(tool, Action::Place, 6, 0.05);
+
+    assert!(merged.cell_count() < exact.cell_count());
+    let pos = vec3(8.0, 8.0, 13.8);
+    assert!((merged.sample(pos) - exact.sample(pos)).abs() < 0.05);
+}
+
+#[test]
+fn intersects_surface_test() {
+    assert!(!crate::intersects_surface(&[-1.0; 8]));
+    assert!(!crate::intersects_surface(&[0.5; 8]));
+    // Zero is outside of the surface, like in Marching Cubes
+    assert!(!crate::intersects_surface(&[0.0, -0.0, -1.0, 0.0, -1.0, -1.0, 0.0, -1.0]));
+    assert!(crate::intersects_surface(&[-1.0, -1.0, -1.0, -1.0, -1.0, -1.0, -1.0, 0.1]));
+    assert!(NaiveOctreeCell { values: [1.0, -1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0], ..Default::default() }.intersects_surface());
+}
+
+#[test]
+fn refine_region_test() {
+    use glam::vec3;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 3);
+    let pos = vec3(8.0, 8.0, 12.5);
+    let before = terrain.sample(pos);
+    assert_eq!(terrain.root.leaf_containing(pos, terrain.aabb(), 0, u8::MAX).1.size, Vec3::splat(2.0));
+
+    terrain.refine_region(AABB::from_radius(pos, 1.0), 6);
+    assert_eq!(terrain.root.leaf_containing(pos, terrain.aabb(), 0, u8::MAX).1.size, Vec3::splat(0.25));
+    assert!((terrain.sample(pos) - before).abs() < 1e-5);
+    // Cells away from the region are untouched
+    assert_eq!(terrain.root.leaf_containing(vec3(8.0, 8.0, 3.5), terrain.aabb(), 0, u8::MAX).1.size, Vec3::splat(2.0));
+}
+
+#[test]
+fn validate_test() {
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 4);
+    assert!(terrain.validate().is_empty());
+
+    let children = Arc::make_mut(terrain.root.children.as_mut().unwrap());
+    children[0].values[3] = f32::NAN;
+    children[7].values[7] = 0.5;
+    terrain.root.values[1] = 2.0;
+    let violations = terrain.validate();
+    assert!(violations.contains(&Violation::OutOfRange(terrain.aabb())));
+    assert!(violations.contains(&Violation::CornerMismatch(terrain.aabb())));
+    assert!(violations.iter().any(|violation| matches!(violation, Violation::NonFinite(_))));
+
+    assert!(terrain.repair() > 0);
+    assert!(terrain.validate().is_empty());
+    assert!(terrain.root.children.as_ref().unwrap()[0].values[3].is_finite());
+}
+
+/// Fails if the meshes generated for a set of scenarios change. If a change
+/// to the output is intended, update the hashes from the failure message.
+#[test]
+fn golden_mesh_test() {
+    use glam::{ vec3, vec3a, Quat };
+
+    let sphere = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0));
+    let ellipsoid = Tool::new(Sphere).scaled(vec3(3.0, 4.0, 5.0)).rotated(Quat::from_rotation_y(0.5)).translated(vec3a(7.0, 9.0, 8.0));
+    let carve = Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(vec3a(11.0, 8.0, 8.0));
+
+    type Build<'a> = &'a dyn Fn(&mut NaiveOctree);
+    let scenarios: [(&str, Build, u8, u64); 4] = [
+        ("sphere", &|terrain| terrain.apply_tool(sphere, Action::Place, 5), 5, 0x11977049423dc96c),
+        ("rotated ellipsoid", &|terrain| terrain.apply_tool(ellipsoid, Action::Place, 5), 5, 0xf12f73d54f4cf6de),
+        ("carved sphere", &|terrain| {
+            terrain.apply_tool(sphere, Action::Place, 6);
+            terrain.apply_tool(carve, Action::Remove, 6);
+        }, 6, 0x848fbfecb898fab7),
+        ("coarse mesh", &|terrain| terrain.apply_tool(sphere, Action::Place, 6), 3, 0xa02ad75e18c349fd),
+    ];
+
+    let mut failures = Vec::new();
+    for (name, build, depth, golden) in scenarios {
+        let mut terrain = NaiveOctree::new(16.0);
+        build(&mut terrain);
+        let hash = terrain.generate_mesh(depth).index().geometry_hash();
+        if hash != golden {
+            failures.push(format!("{name}: {hash:#018x}"));
+        }
+    }
+    assert!(failures.is_empty(), "Meshes changed:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn dense_conversion_test() {
+    use glam::UVec3;
+
+    let mut grid = DenseGrid::new(UVec3::splat(33), Vec3::ZERO, 0.5);
+    grid.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::new(8.0, 7.5, 8.5)), Action::Place);
+
+    let exact = NaiveOctree::from_dense(&grid, 8, 0.0);
+    assert_eq!(exact.depth(), 5);
+    let back = exact.to_dense(grid.aabb(), grid.spacing);
+    assert_eq!(back.dims(), grid.dims());
+    assert!(back.values().iter().zip(grid.values()).all(|(a, b)| (a > &0.0) == (b > &0.0)));
+
+    let merged = NaiveOctree::from_dense(&grid, 8, 0.05);
+    assert!(merged.cell_count() < exact.cell_count());
+    let pos = Vec3::new(8.0, 7.5, 13.4);
+    assert!((merged.sample(pos) - grid.sample(pos)).abs() < 0.05);
+}
+
+#[test]
+fn analysis_test() {
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+
+    let histogram = terrain.value_histogram(4);
+    let leaves = terrain.cell_count() - (terrain.cell_count() - 1) / 8;
+    assert_eq!(histogram.iter().sum::<usize>(), leaves * 8);
+    assert!(histogram[0] > 0 && histogram[3] > 0);
+
+    let surface_cells = terrain.surface_cells_per_depth();
+    assert_eq!(surface_cells.len(), 6);
+    assert!(surface_cells[5] > 0 && surface_cells[..5].iter().all(|&count| count == 0));
+
+    // Close to the area of the sphere
+    let area = terrain.generate_mesh(5).surface_area();
+    assert!((area - 4.0 * std::f32::consts::PI * 25.0).abs() < 10.0, "{area}");
+}
+
+#[test]
+fn subdivision_tolerance_test() {
+    use glam::{ vec3, vec3a };
+
+    // A slab is flat everywhere but its edges
+    let tool = Tool::new(Sphere).scaled(vec3(100.0, 8.0, 100.0)).translated(vec3a(8.0, 0.0, 8.0));
+    let mut exact = NaiveOctree::new(16.0);
+    exact.apply_tool(tool, Action::Place, 6);
+    let mut adaptive = NaiveOctree::new(16.0);
+    adaptive.apply_tool_with_options(tool, Action::Place, 6, &ApplyOptions { subdivision_tolerance: 0.01, ..Default::default() });
+
+    assert!(adaptive.cell_count() * 4 < exact.cell_count());
+    let pos = vec3(5.3, 7.9, 9.1);
+    assert!((adaptive.sample(pos) - exact.sample(pos)).abs() < 0.01);
+}
+
+#[test]
+fn max_depth_test() {
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(0.25)).translated(Vec3A::splat(0.5));
+    let mut terrain = NaiveOctree::new(1.0);
+    terrain.apply_tool(tool, Action::Place, 4);
+
+    // Refining a thin line through the surface stops at the deepest cells
+    let line = AABB { start: Vec3::new(0.7, 0.51, 0.51), size: Vec3::new(0.1, 1e-6, 1e-6) };
+    terrain.refine_region(line, 40);
+    assert_eq!(terrain.depth(), NaiveOctree::MAX_DEPTH);
+    assert!(terrain.validate().is_empty());
+
+    assert_eq!(ApplyTask::new(tool, Action::Place, 40).max_depth, NaiveOctree::MAX_DEPTH);
+}
+
+#[test]
+fn node_budget_test() {
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0));
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.node_budget = Some(500);
+    let report = terrain.apply_tool_with_options(tool, Action::Place, 7, &ApplyOptions::default());
+    assert!(report.budget_exceeded);
+    assert!(terrain.cell_count() <= 500);
+    // The surface is still there, just coarser
+    assert!(terrain.sample(Vec3::splat(8.0)) > 0.0);
+
+    terrain.node_budget = None;
+    assert!(!terrain.apply_tool_with_options(tool, Action::Place, 7, &ApplyOptions::default()).budget_exceeded);
+    assert!(terrain.cell_count() > 500);
+}
+
+#[test]
+fn node_budget_task_test() {
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0));
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.node_budget = Some(500);
+
+    // Spread over many calls, the budget is still kept
+    let mut task = ApplyTask::new(tool, Action::Place, 7);
+    while task.run_for(&mut terrain, Duration::ZERO) == Progress::InProgress {}
+    assert!(task.report().budget_exceeded);
+    assert!(terrain.cell_count() <= 500);
+    assert!(terrain.sample(Vec3::splat(8.0)) > 0.0);
+
+    #[cfg(feature = "multi-thread")]
+    {
+        let mut terrain = NaiveOctree::new(16.0);
+        terrain.node_budget = Some(500);
+        assert!(terrain.par_apply_tool(tool, Action::Place, 7).budget_exceeded);
+        assert!(terrain.cell_count() <= 500);
+        assert!(terrain.sample(Vec3::splat(8.0)) > 0.0);
+    }
+}
+
+#[test]
+fn cell_count_test() {
+    let counted = |terrain: &NaiveOctree| terrain.cell_count() == terrain.root.cell_count();
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 6);
+    assert!(counted(&terrain));
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::new(8.0, 13.0, 8.0)), Action::Remove, 6);
+    assert!(counted(&terrain));
+    // Filling the whole sphere drops its cells without visiting them
+    let before = terrain.cell_count();
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(12.0)).translated(Vec3A::splat(8.0)), Action::Place, 6);
+    assert!(counted(&terrain));
+    assert!(terrain.cell_count() < before / 2);
+
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Remove, 4);
+    terrain.refine_region(terrain.aabb(), 6);
+    assert!(counted(&terrain));
+    terrain.smooth_region(AABB::from_radius(Vec3::splat(8.0), 4.0), 2, 6);
+    assert!(counted(&terrain));
+    let mut task = ApplyTask::new(Tool::new(Sphere).translated(Vec3A::splat(4.0)), Action::Place, 6);
+    while task.run_for(&mut terrain, Duration::ZERO) == Progress::InProgress {}
+    assert!(counted(&terrain));
+    #[cfg(feature = "multi-thread")]
+    {
+        terrain.par_apply_tool(Tool::new(Sphere).translated(Vec3A::splat(12.0)), Action::Place, 6);
+        assert!(counted(&terrain));
+    }
+    let octants = terrain.into_octants();
+    assert!(octants.iter().all(counted));
+    let mut terrain = NaiveOctree::from_octants(octants);
+    assert!(counted(&terrain));
+
+    // Refining stops at the node budget too
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Remove, 4);
+    terrain.node_budget = Some(terrain.cell_count() + 100);
+    assert!(terrain.refine_region(terrain.aabb(), 8).budget_exceeded);
+    assert!(counted(&terrain));
+    assert!(terrain.cell_count() <= terrain.node_budget.unwrap());
+}
+
+#[test]
+fn skip_unchanged_test() {
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0));
+    let options = ApplyOptions { skip_unchanged: true, ..Default::default() };
+    let mut terrain = NaiveOctree::new(16.0);
+    assert!(!terrain.apply_tool_with_options(tool, Action::Place, 5, &options).skipped);
+
+    // Repeating the same edit does nothing
+    assert!(terrain.apply_tool_with_options(tool, Action::Place, 5, &options).skipped);
+    let mut repeated = terrain.clone();
+    repeated.apply_tool(tool, Action::Place, 5);
+    assert_eq!(repeated.generate_mesh(5).index().geometry_hash(), terrain.generate_mesh(5).index().geometry_hash());
+    // But asking for more detail does
+    assert!(!terrain.apply_tool_with_options(tool, Action::Place, 6, &options).skipped);
+
+    // Removing from empty space does nothing either
+    assert!(terrain.apply_tool_with_options(Tool::new(Sphere).translated(Vec3A::splat(2.0)), Action::Remove, 6, &options).skipped);
+    assert!(!terrain.apply_tool_with_options(Tool::new(Sphere).translated(Vec3A::splat(8.0)), Action::Remove, 6, &options).skipped);
+}
+
+#[test]
+fn frozen_test() {
+    use glam::vec3;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+    terrain.frozen.push(AABB { start: Vec3::ZERO, size: vec3(8.0, 16.0, 16.0) });
+    let frozen_value = terrain.sample(vec3(4.0, 8.0, 8.0));
+    assert!(frozen_value > 0.0);
+
+    // Removing everything only changes the unfrozen half
+    let eraser = Tool::new(Sphere).scaled(Vec3::splat(20.0)).translated(Vec3A::splat(8.0));
+    terrain.apply_tool(eraser, Action::Remove, 5);
+    assert_eq!(terrain.sample(vec3(4.0, 8.0, 8.0)), frozen_value);
+    assert!(terrain.sample(vec3(12.0, 8.0, 8.0)) < 0.0);
+    assert!(terrain.validate().is_empty());
+
+    // The mask survives splitting the Terrain
+    let mut octants = terrain.into_octants();
+    octants.iter_mut().for_each(|octant| octant.apply_tool(eraser, Action::Remove, 4));
+    let terrain = NaiveOctree::from_octants(octants);
+    assert_eq!(terrain.sample(vec3(4.0, 8.0, 8.0)), frozen_value);
+}
+
+#[test]
+fn octants_frozen_test() {
+    use glam::vec3;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.frozen.push(AABB { start: Vec3::ZERO, size: vec3(4.0, 4.0, 4.0) });
+    terrain.frozen.push(AABB { start: Vec3::splat(10.0), size: vec3(2.0, 6.0, 2.0) });
+    let frozen = terrain.frozen.clone();
+
+    // Round trips don't duplicate the regions
+    for _ in 0..3 {
+        terrain = NaiveOctree::from_octants(terrain.into_octants());
+    }
+    assert_eq!(terrain.frozen, frozen);
+}
+
+#[test]
+fn boundary_shell_test() {
+    use glam::{ vec3, vec3a };
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(30.0)).translated(Vec3A::splat(8.0)), Action::Place, 4);
+    let mut unprotected = terrain.clone();
+    terrain.boundary_shell = Some(1.0);
+
+    let digger = Tool::new(Sphere).scaled(Vec3::splat(4.0)).translated(vec3a(0.0, 8.0, 8.0));
+    terrain.apply_tool(digger, Action::Remove, 4);
+    unprotected.apply_tool(digger, Action::Remove, 4);
+    assert!(unprotected.sample(vec3(0.0, 8.0, 8.0)) < 0.0);
+    assert!(terrain.sample(vec3(0.0, 8.0, 8.0)) > 0.0);
+    assert!(terrain.sample(vec3(1.0, 8.0, 8.0)) > 0.0);
+    assert!(terrain.sample(vec3(3.0, 8.0, 8.0)) < 0.0);
+
+    // Placing inside of the shell still works
+    terrain.boundary_shell = Some(16.0);
+    terrain.apply_tool(digger, Action::Place, 4);
+    assert!(terrain.sample(vec3(3.0, 8.0, 8.0)) > 0.0);
+}
+
+#[test]
+fn fluid_test() {
+    use glam::{ vec3, vec3a };
+    use crate::tool::Cuboid;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(vec3(100.0, 6.0, 100.0)).translated(vec3a(8.0, 0.0, 8.0)), Action::Place, 5);
+    let ground = terrain.generate_mesh(5).index().geometry_hash();
+
+    // Flooding leaves the terrain alone
+    let pool = Tool::new(Cuboid).scaled(vec3(4.0, 3.0, 4.0)).translated(vec3a(8.0, 6.0, 8.0));
+    terrain.apply_fluid_tool(pool, Action::Place, 5);
+    assert_eq!(terrain.generate_mesh(5).index().geometry_hash(), ground);
+    assert!(terrain.sample_fluid(vec3(8.0, 8.0, 8.0)) > 0.0);
+    assert!(terrain.sample(vec3(8.0, 8.0, 8.0)) < 0.0);
+    assert!(terrain.sample_fluid(vec3(8.0, 12.0, 8.0)) < 0.0);
+    assert!(terrain.validate().is_empty());
+
+    // Fluid under the ground isn't meshed
+    let water = terrain.generate_fluid_mesh(5);
+    assert!(!water.faces.is_empty());
+    assert!(water.faces.iter().flatten().all(|vert| vert.y > 5.5 && vert.y < 9.5));
+
+    // Clearing the terrain keeps the detail of the fluid
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(30.0)).translated(Vec3A::splat(8.0)), Action::Remove, 5);
+    assert!(terrain.generate_mesh(5).faces.is_empty());
+    assert!(terrain.sample_fluid(vec3(11.5, 8.0, 8.0)) > 0.0);
+    assert!(terrain.sample_fluid(vec3(12.5, 8.0, 8.0)) < 0.0);
+    assert!(terrain.validate().is_empty());
+
+    // The boundary shell only protects the terrain
+    terrain.boundary_shell = Some(16.0);
+    terrain.apply_fluid_tool(Tool::new(Sphere).scaled(Vec3::splat(30.0)).translated(Vec3A::splat(8.0)), Action::Remove, 5);
+    assert!(terrain.generate_fluid_mesh(5).faces.is_empty());
+    assert_eq!(terrain.cell_count(), 1);
+}
+
+#[test]
+fn mirror_rotate_test() {
+    use glam::{ vec3, vec3a };
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(vec3(6.0, 3.0, 2.0)).translated(vec3a(5.0, 6.0, 9.0)), Action::Place, 5);
+    let points = [vec3(5.0, 6.0, 9.0), vec3(8.5, 6.5, 9.0), vec3(5.0, 8.0, 9.5), vec3(3.0, 6.0, 10.0)];
+
+    let mirrored = terrain.clone().mirrored(Axis::X);
+    for point in points {
+        assert_eq!(mirrored.sample(vec3(16.0 - point.x, point.y, point.z)), terrain.sample(point));
+    }
+
+    // Counterclockwise around Y moves +X towards -Z
+    let rotated = terrain.clone().rotated_90(Axis::Y);
+    for point in points {
+        assert_eq!(rotated.sample(vec3(point.z, point.y, 16.0 - point.x)), terrain.sample(point));
+    }
+
+    let hash = terrain.generate_mesh(5).index().geometry_hash();
+    let turned = (0..4).fold(terrain, |terrain, _| terrain.rotated_90(Axis::Z));
+    assert_eq!(turned.generate_mesh(5).index().geometry_hash(), hash);
+}
+
+#[test]
+fn resample_test() {
+    use glam::vec3;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+
+    // Doubling the size at one more depth keeps the same surface
+    let bounds = AABB { start: Vec3::splat(-8.0), size: Vec3::splat(32.0) };
+    let resampled = terrain.resample(bounds, 6);
+    assert_eq!(resampled.aabb(), bounds);
+    assert!((resampled.generate_mesh(6).surface_area() - terrain.generate_mesh(5).surface_area()).abs() < 1.0);
+    assert!(resampled.validate().is_empty());
+    for point in [vec3(3.2, 8.0, 8.0), vec3(8.0, 12.7, 9.0), vec3(12.4, 9.0, 7.5)] {
+        assert_eq!(resampled.sample(point), terrain.sample(point));
+    }
+}
+
+#[test]
+fn sample_at_depth_test() {
+    use glam::vec3;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 6);
+
+    let pos = vec3(8.3, 8.1, 12.9);
+    assert_eq!(terrain.sample_at_depth(pos, u8::MAX), terrain.sample(pos));
+    // The root only sees its corners, which are all outside
+    assert_eq!(terrain.sample_at_depth(pos, 0), -1.0);
+    let coarse = terrain.sample_at_depth(pos, 3);
+    assert!(coarse != terrain.sample(pos) && (coarse - terrain.sample(pos)).abs() < 0.2);
+}
+
+#[test]
+fn column_surface_heights_test() {
+    use glam::vec3a;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.default_depth = 6;
+    terrain.apply(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place);
+    terrain.apply(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(vec3a(8.3, 8.0, 8.1)), Action::Remove);
+
+    // The line enters the sphere, crosses the hollow, and leaves again
+    let heights = terrain.column_surface_heights(8.3, 8.1);
+    assert_eq!(heights.len(), 4, "{heights:?}");
+    for (height, expected) in heights.iter().zip([3.0, 6.0, 10.0, 13.0]) {
+        assert!((height - expected).abs() < 0.1, "{heights:?}");
+    }
+    assert!(terrain.column_surface_heights(1.0, 1.0).is_empty());
+}
+
+#[test]
+fn project_decal_test() {
+    use glam::{ vec2, vec3, vec3a };
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.default_depth = 5;
+    terrain.apply(Tool::new(Sphere).scaled(vec3(100.0, 8.0, 100.0)).translated(vec3a(8.0, 0.0, 8.0)), Action::Place);
+    terrain.apply(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(vec3a(9.0, 8.0, 8.0)), Action::Place);
+
+    let projection = DecalProjection {
+        center: vec3(8.0, 14.0, 8.0),
+        size: vec2(4.0, 4.0),
+        resolution: 4,
+        ..Default::default()
+    };
+    let decal = terrain.project_decal(&projection);
+    assert_eq!(decal.verts.len(), 25);
+    assert_eq!(decal.faces.len(), 32);
+    assert_eq!(decal.uvs[0], vec2(0.0, 1.0));
+
+    // The decal follows the bump on top of the ground
+    assert!((decal.verts[0].y - 8.0).abs() < 0.1);
+    assert!(decal.verts[12].y > 9.0);
+    let [a, b, c] = decal.faces[0].map(|index| decal.verts[index]);
+    assert!((b - a).cross(c - a).y > 0.0);
+
+    // Rays that miss leave holes
+    let missing = terrain.project_decal(&DecalProjection { max_distance: 5.0, ..projection });
+    assert!(missing.verts.len() < 25 && missing.faces.len() < 32);
+
+    // A U axis parallel to the direction still gives a finite decal
+    let parallel = terrain.project_decal(&DecalProjection { right: Vec3::Y, ..projection });
+    assert_eq!(parallel.verts.len(), 25);
+    assert!(parallel.verts.iter().all(|vert| vert.is_finite()));
+    assert!((parallel.verts[0].y - 8.0).abs() < 0.1);
+    assert!(terrain.project_decal(&DecalProjection { direction: Vec3::ZERO, ..projection }).verts.is_empty());
+}
+
+#[test]
+fn snapshot_sharing_test() {
+    use crate::Snapshots;
+    use glam::Vec3A;
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+    let mut snapshots = Snapshots::new();
+    snapshots.snapshot("before", &terrain);
+    terrain.apply_tool(Tool::new(Sphere).translated(Vec3A::splat(5.0)), Action::Remove, 5);
+    snapshots.snapshot("after", &terrain);
+
+    let shared = |a: &NaiveOctreeCell, b: &NaiveOctreeCell| match (a.children.as_ref(), b.children.as_ref()) {
+        (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+        _ => false,
+    };
+    let before = &snapshots.get("before").unwrap().root;
+    let after = &snapshots.get("after").unwrap().root;
+    assert!(!shared(before, after));
+
+    // Only the octant that was edited was copied
+    let (before, after) = (before.children.as_ref().unwrap(), after.children.as_ref().unwrap());
+    assert!(!shared(&before[0], &after[0]));
+    assert!((1..8).all(|index| shared(&before[index], &after[index])));
+    assert!(shared(&terrain.root, &snapshots.get("after").unwrap().root));
+}
@@ -0,0 +1,112 @@
+//! Walkable surface extraction for navigation.
+//! 
+//! The generated terrain mesh is filtered by slope, and the remaining
+//! triangles are linked to their neighbours so they can be used for
+//! pathfinding directly, or passed on to a navmesh builder such as recast.
+
+use glam::Vec3;
+use ahash::AHashMap;
+use crate::IndexedMesh;
+
+/// Settings used to decide which triangles are walkable.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NavMeshSettings {
+    /// The direction pointing away from gravity.
+    pub up: Vec3,
+    /// The steepest walkable slope, in radians.
+    pub max_slope: f32,
+}
+
+impl Default for NavMeshSettings {
+    fn default() -> Self {
+        Self {
+            up: Vec3::Y,
+            max_slope: 45f32.to_radians(),
+        }
+    }
+}
+
+/// A walkable triangle mesh with triangle adjacency.
+#[derive(Debug, Clone)]
+pub struct NavMesh {
+    pub mesh: IndexedMesh,
+    /// For each triangle, the triangle sharing each of its edges, if any.
+    /// Edge `i` goes from vertex `i` to vertex `(i + 1) % 3`.
+    pub neighbors: Vec<[Option<usize>; 3]>,
+}
+
+/// Returns, for each triangle of `mesh`, whether it is walkable.
+/// 
+/// Triangles are expected to be wound counter-clockwise when viewed from
+/// outside of the terrain, as Marching Cubes generates them.
+pub fn walkable_triangles(mesh: &IndexedMesh, settings: &NavMeshSettings) -> Vec<bool> {
+    let up = settings.up.normalize();
+    let min_cos = settings.max_slope.cos();
+    mesh.faces.iter().map(|face| {
+        let [a, b, c] = face.map(|index| mesh.verts[index]);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        normal.dot(up) >= min_cos
+    })
+    .collect()
+}
+
+impl NavMesh {
+    /// Builds a [NavMesh] from the walkable triangles of `mesh`. Unused
+    /// vertices are removed.
+    pub fn from_mesh(mesh: &IndexedMesh, settings: &NavMeshSettings) -> Self {
+        let walkable = walkable_triangles(mesh, settings);
+
+        let mut remap: AHashMap<usize, usize> = Default::default();
+        let mut verts = Vec::new();
+        let faces: Vec<[usize; 3]> = mesh.faces.iter()
+            .zip(walkable)
+            .filter(|(_, walkable)| *walkable)
+            .map(|(face, _)| face.map(|index| {
+                *remap.entry(index).or_insert_with(|| {
+                    verts.push(mesh.verts[index]);
+                    verts.len() - 1
+                })
+            }))
+            .collect();
+
+        let mut edges: AHashMap<(usize, usize), usize> = Default::default();
+        faces.iter().enumerate().for_each(|(face_index, face)| {
+            for edge in 0..3 {
+                edges.insert((face[edge], face[(edge + 1) % 3]), face_index);
+            }
+        });
+
+        // Neighbours share the same edge, wound in the opposite direction
+        let neighbors = faces.iter().map(|face| {
+            [0, 1, 2].map(|edge| edges.get(&(face[(edge + 1) % 3], face[edge])).copied())
+        })
+        .collect();
+
+        Self {
+            mesh: IndexedMesh {
+                verts,
+                faces,
+                normals: None,
+            },
+            neighbors,
+        }
+    }
+}
+
+#[test]
+fn navmesh_test() {
+    use crate::{ naive_octree::NaiveOctree, tool::{ Tool, Sphere, Action } };
+    use glam::Vec3A;
+
+    let mut terrain = NaiveOctree::new(10.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(5.0)), Action::Place, 5);
+    let mesh = terrain.generate_mesh(255).index();
+
+    let navmesh = NavMesh::from_mesh(&mesh, &NavMeshSettings::default());
+    assert!(!navmesh.mesh.faces.is_empty());
+    assert!(navmesh.mesh.faces.len() < mesh.faces.len() / 2);
+    // Only the top cap of the sphere is walkable
+    assert!(navmesh.mesh.verts.iter().all(|vert| vert.y > 6.0));
+    assert!(navmesh.neighbors.iter().flatten().any(Option::is_some));
+}
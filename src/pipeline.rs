@@ -0,0 +1,258 @@
+use glam::{ Vec3, Vec3A };
+use std::ops::Range;
+use crate::{
+    naive_octree::NaiveOctree,
+    tool::{ Tool, ToolFunc, Action, AABB, Heightmap, NoiseParams, RidgedNoise },
+    utils::{ span, Seed },
+    ChunkKey, ChunkedTerrain,
+};
+
+/// One step of a [GenPipeline]. Every stage is defined in world space, so
+/// it produces the same values no matter which part of the world is being
+/// generated.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GenStage {
+    /// Places `heightmap`, scaled to fill `bounds`.
+    Heightmap { heightmap: Heightmap, bounds: AABB },
+    /// Applies [RidgedNoise] inside of `bounds` with `action`. Removing
+    /// carves caves, and placing a thin layer roughens the surface.
+    Noise { params: NoiseParams, bounds: AABB, action: Action },
+    /// Blurs the values `iterations` times, wearing down sharp ridges. See
+    /// [`NaiveOctree::smooth_region`]. The faces of the Terrain are left
+    /// alone, so neighbouring chunks still line up.
+    Smooth { iterations: u32 },
+    /// Thermal erosion: slopes steeper than `talus` slide down onto their
+    /// neighbours `iterations` times, turning cliffs into scree. See
+    /// [`NaiveOctree::erode_region`]. Like [`GenStage::Smooth`], the faces
+    /// of the Terrain are left alone.
+    Erode { iterations: u32, talus: f32 },
+    /// Paints the surface with the material of the first of `rules` that
+    /// matches each corner. Corners no rule matches keep their material.
+    /// See [`NaiveOctree::paint_materials`].
+    Materials { rules: Vec<MaterialRule> },
+}
+
+/// Where a material goes in a [`GenStage::Materials`]. Every condition
+/// that is set has to hold for the rule to match.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MaterialRule {
+    pub material: u8,
+    /// The heights the rule matches at.
+    pub height: Option<Range<f32>>,
+    /// The slopes the rule matches at, measured like
+    /// [`SurfaceAttributes::slope`](crate::naive_octree::SurfaceAttributes::slope)
+    /// with Y up.
+    pub slope: Option<Range<f32>>,
+    /// The rule only matches inside of this noise, like a [`GenStage::Noise`]
+    /// placing it. The seed is mixed with the pipeline's like it is there.
+    pub noise: Option<RidgedNoise>,
+}
+
+impl MaterialRule {
+    /// Returns true if the rule matches at `pos`, where the surface has
+    /// the given `slope`. `seed` is mixed into the seed of the noise.
+    pub fn matches(&self, pos: Vec3, slope: f32, seed: Seed) -> bool {
+        self.height.as_ref().is_none_or(|height| height.contains(&pos.y)) &&
+            self.slope.as_ref().is_none_or(|range| range.contains(&slope)) &&
+            self.noise.is_none_or(|noise| {
+                let params = NoiseParams { seed: Seed(noise.params.seed.0 ^ seed.0), ..noise.params };
+                RidgedNoise { params, ..noise }.value(pos) > 0.0
+            })
+    }
+}
+
+/// The tool a [GenStage] applies, if any.
+enum StageTool {
+    Heightmap(Tool<Heightmap>),
+    Noise(Tool<RidgedNoise>, Action),
+}
+
+impl StageTool {
+    /// Applies the tool to `value`, the value at `pos`.
+    fn apply(&self, value: &mut f32, pos: Vec3) {
+        match self {
+            StageTool::Heightmap(tool) => Action::Place.apply_weighted(value, tool.value(pos), tool.weight()),
+            StageTool::Noise(tool, action) => action.apply_weighted(value, tool.value(pos), tool.weight()),
+        }
+    }
+}
+
+/// The tool of a [`GenStage::Heightmap`].
+fn heightmap_tool(heightmap: &Heightmap, bounds: &AABB) -> Tool<Heightmap> {
+    Tool::new(heightmap.clone())
+        .scaled(bounds.size)
+        .translated(Vec3A::from(bounds.start))
+}
+
+/// A list of [GenStage]s applied in order to generate a Terrain.
+///
+/// Noise stages are seeded from `seed` and their position in the list, and
+/// every stage only depends on world positions, so each chunk of a
+/// [ChunkedTerrain] comes out the same no matter when it is generated.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenPipeline {
+    pub seed: Seed,
+    pub stages: Vec<GenStage>,
+}
+
+impl GenPipeline {
+    pub fn new(seed: Seed) -> Self {
+        Self { seed, stages: Vec::new() }
+    }
+
+    /// Adds `stage` to the end of the pipeline.
+    pub fn stage(mut self, stage: GenStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Runs every stage on `terrain`, subdividing up to `max_depth`.
+    pub fn apply(&self, terrain: &mut NaiveOctree, max_depth: u8) {
+        span!("gen_pipeline", stages = self.stages.len(), max_depth);
+        let initial = terrain.clone();
+        for (index, stage) in self.stages.iter().enumerate() {
+            match stage {
+                GenStage::Heightmap { heightmap, bounds } => {
+                    terrain.apply_tool(heightmap_tool(heightmap, bounds), Action::Place, max_depth);
+                },
+                GenStage::Noise { params, bounds, action } => {
+                    terrain.apply_tool(self.noise_tool(index, params, bounds), *action, max_depth);
+                },
+                GenStage::Smooth { iterations } => {
+                    terrain.smooth_region(terrain.aabb(), *iterations, max_depth);
+                },
+                GenStage::Erode { iterations, talus } => {
+                    terrain.erode_region(terrain.aabb(), *iterations, *talus, max_depth);
+                },
+                GenStage::Materials { rules } => {
+                    let seed = self.seed.derive(index as u64);
+                    terrain.paint_materials(terrain.aabb(), |terrain, pos| {
+                        let slope = terrain.normal(pos).dot(Vec3::Y).clamp(-1.0, 1.0).acos();
+                        rules.iter().find(|rule| rule.matches(pos, slope, seed)).map(|rule| rule.material)
+                    });
+                },
+            }
+        }
+
+        // Each chunk subdivides along its faces on its own, so the faces are
+        // set from the tools directly to line up with the neighbouring chunks.
+        // The other stages leave the faces alone.
+        let tools: Vec<StageTool> = self.stages.iter().enumerate()
+            .filter_map(|(index, stage)| match stage {
+                GenStage::Heightmap { heightmap, bounds } => Some(StageTool::Heightmap(heightmap_tool(heightmap, bounds))),
+                GenStage::Noise { params, bounds, action } => Some(StageTool::Noise(self.noise_tool(index, params, bounds), *action)),
+                GenStage::Smooth { .. } | GenStage::Erode { .. } | GenStage::Materials { .. } => None,
+            })
+            .collect();
+        terrain.map_face_values(|pos, _| {
+            let mut value = initial.sample(pos);
+            tools.iter().for_each(|tool| tool.apply(&mut value, pos));
+            value
+        });
+    }
+
+    /// The noise of the stage at `index`, seeded from the pipeline.
+    fn noise_tool(&self, index: usize, params: &NoiseParams, bounds: &AABB) -> Tool<RidgedNoise> {
+        let params = NoiseParams {
+            seed: Seed(params.seed.0 ^ self.seed.derive(index as u64).0),
+            ..*params
+        };
+        Tool::new(RidgedNoise::new(params, *bounds))
+    }
+
+    /// Runs every stage on the chunk at `key` of `terrain`, creating it if
+    /// needed. The dirty callback isn't called.
+    pub fn generate_chunk(&self, terrain: &mut ChunkedTerrain, key: ChunkKey, max_depth: u8) {
+        self.apply(terrain.chunk_or_create(key), max_depth);
+    }
+}
+
+#[test]
+fn gen_pipeline_test() {
+    use glam::Vec3;
+
+    let heights = (0..64).map(|index| 0.4 + 0.2 * ((index % 8) as f32 * 0.7).sin()).collect();
+    let world = AABB { start: Vec3::ZERO, size: Vec3::new(32.0, 16.0, 32.0) };
+    let pipeline = GenPipeline::new(Seed(42))
+        .stage(GenStage::Heightmap { heightmap: Heightmap::new(8, 8, heights), bounds: world })
+        .stage(GenStage::Noise { params: NoiseParams::default(), bounds: world, action: Action::Remove })
+        .stage(GenStage::Smooth { iterations: 1 })
+        .stage(GenStage::Erode { iterations: 4, talus: 0.5 });
+
+    let keys = [ChunkKey(0, 0, 0), ChunkKey(1, 0, 0), ChunkKey(0, 0, 1), ChunkKey(1, 0, 1)];
+    let mut forward = ChunkedTerrain::new(16.0);
+    keys.iter().for_each(|&key| pipeline.generate_chunk(&mut forward, key, 4));
+    let mut backward = ChunkedTerrain::new(16.0);
+    keys.iter().rev().for_each(|&key| pipeline.generate_chunk(&mut backward, key, 4));
+
+    for key in keys {
+        let hash = forward.generate_mesh(key, 4).unwrap().index().geometry_hash();
+        assert_eq!(backward.generate_mesh(key, 4).unwrap().index().geometry_hash(), hash);
+    }
+    assert!(!forward.generate_mesh(ChunkKey(0, 0, 0), 4).unwrap().faces.is_empty());
+
+    // Neighbouring chunks put the same vertices on the face they share
+    let face_verts = |key: ChunkKey| {
+        let mut verts: Vec<[f32; 3]> = forward.generate_mesh(key, 4).unwrap().faces.iter().flatten()
+            .filter(|vert| (vert.x - 16.0).abs() < 1e-4)
+            .map(|vert| (*vert * 1000.0).round().to_array())
+            .collect();
+        verts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        verts.dedup();
+        verts
+    };
+    assert!(!face_verts(ChunkKey(0, 0, 0)).is_empty());
+    assert_eq!(face_verts(ChunkKey(0, 0, 0)), face_verts(ChunkKey(1, 0, 0)));
+
+    // A different seed carves different caves
+    let reseeded = GenPipeline { seed: Seed(7), ..pipeline.clone() };
+    let mut other = ChunkedTerrain::new(16.0);
+    reseeded.generate_chunk(&mut other, keys[0], 4);
+    assert_ne!(other.generate_mesh(keys[0], 4).unwrap().index().geometry_hash(), forward.generate_mesh(keys[0], 4).unwrap().index().geometry_hash());
+}
+
+#[test]
+fn gen_materials_test() {
+    // Low ground on one side and a plateau on the other, with a cliff between
+    let heights = (0..64).map(|index| if index % 8 < 4 { 0.25 } else { 0.75 }).collect();
+    let world = AABB { start: Vec3::ZERO, size: Vec3::new(32.0, 16.0, 32.0) };
+    let patch = AABB { start: Vec3::ZERO, size: Vec3::new(8.0, 16.0, 32.0) };
+    let rules = vec![
+        MaterialRule { material: 3, height: Some(10.0..16.0), slope: Some(0.0..0.4), ..Default::default() },
+        MaterialRule { material: 2, slope: Some(0.8..4.0), ..Default::default() },
+        MaterialRule {
+            material: 4,
+            noise: Some(RidgedNoise::new(NoiseParams { threshold: 0.0, ..Default::default() }, patch)),
+            ..Default::default()
+        },
+        MaterialRule { material: 1, ..Default::default() },
+    ];
+    let pipeline = GenPipeline::new(Seed(42))
+        .stage(GenStage::Heightmap { heightmap: Heightmap::new(8, 8, heights), bounds: world })
+        .stage(GenStage::Materials { rules });
+
+    let mut terrain = NaiveOctree::new(32.0);
+    pipeline.apply(&mut terrain, 5);
+    assert!(terrain.validate().is_empty());
+
+    let mesh = terrain.generate_mesh(5);
+    let material_near = |target: Vec3| {
+        let vert = mesh.faces.iter().flatten()
+            .min_by(|a, b| a.distance_squared(target).total_cmp(&b.distance_squared(target)))
+            .unwrap();
+        terrain.material(*vert)
+    };
+    assert_eq!(material_near(Vec3::new(4.0, 4.0, 16.0)), 4);
+    assert_eq!(material_near(Vec3::new(10.0, 4.0, 16.0)), 1);
+    assert_eq!(material_near(Vec3::new(16.0, 8.0, 16.0)), 2);
+    assert_eq!(material_near(Vec3::new(26.0, 12.0, 16.0)), 3);
+
+    // Materials carry over into new cells
+    let mut refined = terrain.clone();
+    refined.refine_region(AABB { start: Vec3::new(24.0, 8.0, 8.0), size: Vec3::splat(8.0) }, 7);
+    assert_eq!(refined.material(Vec3::new(26.0, 11.9, 16.0)), 3);
+    assert!(refined.validate().is_empty());
+}
@@ -0,0 +1,86 @@
+use ahash::AHashMap;
+use crate::naive_octree::NaiveOctree;
+
+/// A set of named copies of a [NaiveOctree], so edits can be tried out
+/// and rolled back without writing the Terrain anywhere.
+/// 
+/// Snapshots share every cell that hasn't been edited since with each
+/// other and with the Terrain they were taken from, so taking, restoring
+/// or branching one is cheap, and each snapshot only keeps the cells that
+/// were copied by later edits.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshots {
+    snapshots: AHashMap<String, NaiveOctree>,
+}
+
+impl Snapshots {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a copy of `terrain` as `name`, replacing any snapshot
+    /// with the same name.
+    pub fn snapshot(&mut self, name: impl Into<String>, terrain: &NaiveOctree) {
+        self.snapshots.insert(name.into(), terrain.clone());
+    }
+
+    /// Overwrites `terrain` with the snapshot `name`. Returns false if
+    /// there is no such snapshot.
+    pub fn restore(&self, name: &str, terrain: &mut NaiveOctree) -> bool {
+        match self.snapshots.get(name) {
+            Some(snapshot) => {
+                terrain.clone_from(snapshot);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Returns a new Terrain copied from the snapshot `name`.
+    pub fn branch(&self, name: &str) -> Option<NaiveOctree> {
+        self.snapshots.get(name).cloned()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NaiveOctree> {
+        self.snapshots.get(name)
+    }
+
+    /// Removes the snapshot `name` and returns it.
+    pub fn remove(&mut self, name: &str) -> Option<NaiveOctree> {
+        self.snapshots.remove(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.snapshots.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+#[test]
+fn snapshots_test() {
+    use crate::tool::{ Tool, Sphere, Action };
+    use glam::{ Vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(5.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+
+    let mut snapshots = Snapshots::new();
+    snapshots.snapshot("before_cave", &terrain);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(Vec3A::splat(8.0)), Action::Remove, 5);
+    assert!(terrain.sample(Vec3::splat(8.0)) < 0.0);
+
+    let branch = snapshots.branch("before_cave").unwrap();
+    assert!(branch.sample(Vec3::splat(8.0)) > 0.0);
+
+    assert!(snapshots.restore("before_cave", &mut terrain));
+    assert!(terrain.sample(Vec3::splat(8.0)) > 0.0);
+    assert!(!snapshots.restore("missing", &mut terrain));
+    assert_eq!(snapshots.names().collect::<Vec<_>>(), ["before_cave"]);
+}
@@ -0,0 +1,63 @@
+//! Performance counters recorded by the apply and meshing paths.
+//! 
+//! Counters are global to the process and are shared by every terrain,
+//! so they're only meaningful when one operation runs at a time. Call
+//! [`take`] before and after an operation to get its counts.
+
+use std::sync::atomic::{ AtomicU64, Ordering };
+
+pub(crate) static CELLS_VISITED: AtomicU64 = AtomicU64::new(0);
+pub(crate) static TOOL_EVALUATIONS: AtomicU64 = AtomicU64::new(0);
+pub(crate) static SUBDIVISIONS: AtomicU64 = AtomicU64::new(0);
+pub(crate) static COLLAPSES: AtomicU64 = AtomicU64::new(0);
+pub(crate) static TRIANGLES: AtomicU64 = AtomicU64::new(0);
+
+/// Counts of the work done since the last call to [`take`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Cells visited while applying tools or generating meshes.
+    pub cells_visited: u64,
+    /// Calls to [`ToolFunc::value`](crate::tool::ToolFunc::value), counting
+    /// each of the 8 values of a batched call.
+    pub tool_evaluations: u64,
+    /// Cells split into 8 children.
+    pub subdivisions: u64,
+    /// Cells whose children were removed.
+    pub collapses: u64,
+    /// Triangles emitted by Marching Cubes.
+    pub triangles: u64,
+}
+
+/// Returns the counters and resets them to zero.
+pub fn take() -> Stats {
+    let take = |counter: &AtomicU64| counter.swap(0, Ordering::Relaxed);
+    Stats {
+        cells_visited: take(&CELLS_VISITED),
+        tool_evaluations: take(&TOOL_EVALUATIONS),
+        subdivisions: take(&SUBDIVISIONS),
+        collapses: take(&COLLAPSES),
+        triangles: take(&TRIANGLES),
+    }
+}
+
+#[test]
+fn stats_test() {
+    use crate::{ naive_octree::NaiveOctree, tool::{ Tool, Sphere, Action } };
+    use glam::{ Vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(1.0);
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(0.3)).translated(Vec3A::splat(0.5));
+
+    // Other tests run in parallel and add to the counters, so only lower
+    // bounds can be checked here
+    take();
+    terrain.apply_tool(tool, Action::Place, 3);
+    let apply_stats = take();
+    assert!(apply_stats.cells_visited > 1);
+    assert!(apply_stats.tool_evaluations >= 8);
+    assert!(apply_stats.subdivisions > 0);
+
+    let mesh = terrain.generate_mesh(255);
+    let mesh_stats = take();
+    assert!(mesh_stats.triangles >= mesh.faces.len() as u64);
+}
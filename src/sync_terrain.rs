@@ -0,0 +1,146 @@
+use glam::Vec3;
+use std::{
+    borrow::Borrow,
+    sync::{ RwLock, RwLockReadGuard, RwLockWriteGuard },
+};
+use crate::{
+    naive_octree::NaiveOctree,
+    tool::{ Tool, ToolFunc, Action, AABB, IntersectType::DoesNotIntersect },
+    UnindexedMesh,
+};
+
+/// A [NaiveOctree] that can be shared between threads, with each of the
+/// 8 octants of the root behind its own lock.
+/// 
+/// Edits only lock the octants their tool reaches, so queries and
+/// edits in other octants can go on at the same time. An edit takes all
+/// of its write locks in Z-order before changing anything, so it can't
+/// deadlock with another edit and readers never see it half done.
+#[derive(Debug)]
+pub struct SyncTerrain {
+    octants: [RwLock<NaiveOctree>; 8],
+    scale: f32,
+    origin: Vec3,
+}
+
+impl SyncTerrain {
+    pub fn new(terrain: NaiveOctree) -> Self {
+        let scale = terrain.scale;
+        let origin = terrain.origin;
+        Self {
+            octants: terrain.into_octants().map(RwLock::new),
+            scale,
+            origin,
+        }
+    }
+
+    /// The AABB covered by the Terrain.
+    pub fn aabb(&self) -> AABB {
+        AABB { start: self.origin, size: Vec3::splat(self.scale) }
+    }
+
+    /// Locks the octant at `index` (in Z-order) for reading.
+    pub fn read_octant(&self, index: usize) -> RwLockReadGuard<'_, NaiveOctree> {
+        self.octants[index].read().unwrap()
+    }
+
+    /// Locks the octant at `index` (in Z-order) for writing.
+    pub fn write_octant(&self, index: usize) -> RwLockWriteGuard<'_, NaiveOctree> {
+        self.octants[index].write().unwrap()
+    }
+
+    fn octant_index(&self, pos: Vec3) -> usize {
+        let center = self.aabb().center();
+        (pos.x >= center.x) as usize | ((pos.y >= center.y) as usize) << 1 | ((pos.z >= center.z) as usize) << 2
+    }
+
+    /// Applies the [Tool] to every octant it reaches with the given
+    /// [Action]. `max_depth` is the depth in the whole Terrain, as in
+    /// [`NaiveOctree::apply_tool`].
+    /// 
+    /// Every octant is locked before the first one is edited, so the edit
+    /// is seen all at once.
+    pub fn apply_tool<T: Borrow<Tool<F>>, F: ToolFunc>(&self, tool: T, action: Action, max_depth: u8) {
+        let tool = tool.borrow();
+        let aoe_aabb = tool.aoe_aabb();
+        let mut guards: Vec<_> = self.octants.iter()
+            .zip(self.aabb().octree_subdivide())
+            .filter(|(_, aabb)| !matches!(aabb.intersect(aoe_aabb), DoesNotIntersect))
+            .map(|(octant, _)| octant.write().unwrap())
+            .collect();
+        for octant in guards.iter_mut() {
+            octant.apply_tool(tool, action, max_depth.saturating_sub(1));
+        }
+    }
+
+    /// Returns the value of the Terrain at `pos`.
+    pub fn sample(&self, pos: Vec3) -> f32 {
+        self.read_octant(self.octant_index(pos)).sample(pos)
+    }
+
+    /// Uses Marching Cubes to generate an [UnindexedMesh]. Every octant is
+    /// locked for reading first, so the mesh doesn't mix octants from
+    /// before and after an edit.
+    pub fn generate_mesh(&self, max_depth: u8) -> UnindexedMesh {
+        let guards: Vec<_> = self.octants.iter().map(|octant| octant.read().unwrap()).collect();
+        let mut faces = Vec::new();
+        for octant in guards.iter() {
+            faces.append(&mut octant.generate_mesh(max_depth.saturating_sub(1)).faces);
+        }
+        UnindexedMesh {
+            faces,
+            normals: None,
+        }
+    }
+
+    /// Joins the octants back into a [NaiveOctree].
+    pub fn into_inner(self) -> NaiveOctree {
+        NaiveOctree::from_octants(self.octants.map(|octant| octant.into_inner().unwrap()))
+    }
+}
+
+#[test]
+fn sync_terrain_test() {
+    use crate::tool::Sphere;
+    use glam::Vec3A;
+
+    let terrain = SyncTerrain::new(NaiveOctree::new(16.0));
+    std::thread::scope(|scope| {
+        for center in [Vec3A::splat(4.0), Vec3A::splat(12.0)] {
+            let terrain = &terrain;
+            scope.spawn(move || terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(center), Action::Place, 5));
+        }
+    });
+    assert!(terrain.sample(Vec3::splat(4.0)) > 0.0);
+    assert!(terrain.sample(Vec3::splat(12.0)) > 0.0);
+    assert!(terrain.sample(Vec3::splat(8.0)) < 0.0);
+
+    let mesh_faces = terrain.generate_mesh(5).faces.len();
+    let octree = terrain.into_inner();
+    assert_eq!(octree.generate_mesh(5).faces.len(), mesh_faces);
+    assert!(octree.sample(Vec3::splat(12.0)) > 0.0);
+}
+
+#[test]
+fn sync_terrain_shell_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, Vec3A };
+
+    let mut terrain = NaiveOctree::new(16.0);
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(30.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+    terrain.boundary_shell = Some(1.0);
+    let terrain = SyncTerrain::new(terrain);
+
+    // Digging across the centre reaches the inner faces of the octants
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::splat(8.0)), Action::Remove, 5);
+    assert!(terrain.sample(Vec3::splat(8.0)) < 0.0);
+    assert!(terrain.sample(vec3(8.0, 8.0, 6.0)) < 0.0);
+
+    // The outer faces are still protected
+    terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(3.0)).translated(Vec3A::new(0.0, 8.0, 8.0)), Action::Remove, 5);
+    assert!(terrain.sample(vec3(0.0, 8.0, 8.0)) > 0.0);
+    assert!(terrain.sample(vec3(2.5, 8.0, 8.0)) < 0.0);
+
+    let octree = terrain.into_inner();
+    assert_eq!(octree.shell(), Some((octree.aabb(), 1.0)));
+}
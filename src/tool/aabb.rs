@@ -4,6 +4,7 @@ use crate::CUBE_CORNERS;
 
 /// Axis-Aligned Bounding Box
 #[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AABB {
     pub start: Vec3,
     pub size: Vec3,
@@ -47,9 +48,9 @@ impl AABB {
     /// Returns true if `point` lies within the AABB.
     pub fn contains(&self, point: Vec3) -> bool
     {
-        return point.to_array().into_iter()
+        point.to_array().into_iter()
             .zip(self.start.to_array().into_iter()
-            .zip(self.size.to_array().into_iter()))
+            .zip(self.size.to_array()))
             .all(|(point, (start, size))|
             {
                 point >= start && point <= start + size
@@ -62,19 +63,106 @@ impl AABB {
             .zip(self.start.as_mut().iter_mut())
             .zip(self.size.as_mut().iter_mut())
             .for_each(|((p, start), size)| {
+                let end = (*start + *size).max(p);
                 *start = start.min(p);
-                *size = size.max(p - *start);
+                *size = end - *start;
             });
     }
 
+    /// Create an AABB extending from `min` to `max`.
+    /// 
+    /// Accepts any type convertible to [Vec3], such as `mint` or `nalgebra`
+    /// points and vectors when the respective features are enabled.
+    pub fn from_min_max(min: impl Into<Vec3>, max: impl Into<Vec3>) -> Self {
+        let min = min.into();
+        Self {
+            start: min,
+            size: max.into() - min,
+        }
+    }
+
+    /// Returns the minimum and maximum corners of the AABB, converted to
+    /// any type constructible from [Vec3].
+    pub fn min_max<T: From<Vec3>>(&self) -> (T, T) {
+        (self.start.into(), self.end().into())
+    }
+
+    /// Returns the corner of the AABB opposite to `start`.
+    pub fn end(&self) -> Vec3 {
+        self.start + self.size
+    }
+
+    /// Returns the center point of the AABB.
+    pub fn center(&self) -> Vec3 {
+        self.start + (self.size / 2.0)
+    }
+
+    /// Returns the total area of the AABB's six faces.
+    pub fn surface_area(&self) -> f32 {
+        let size = self.size;
+        2.0 * ((size.x * size.y) + (size.y * size.z) + (size.z * size.x))
+    }
+
+    /// Returns the smallest AABB that contains both this AABB and `other`.
+    pub fn union(&self, other: AABB) -> AABB {
+        let start = self.start.min(other.start);
+        let end = self.end().max(other.end());
+        AABB {
+            start,
+            size: end - start,
+        }
+    }
+
+    /// Returns a copy of the AABB grown by `margin` in every direction.
+    /// 
+    /// eg. An AABB from (0,0,0) to (1,1,1) expanded by 1.0 would extend
+    /// from (-1,-1,-1) to (2,2,2).
+    pub fn expanded_by(&self, margin: f32) -> AABB {
+        AABB {
+            start: self.start - margin,
+            size: self.size + (margin * 2.0),
+        }
+    }
+
+    /// Returns the point inside of the AABB that is closest to `point`.
+    /// If `point` is inside of the AABB, then `point` is returned.
+    pub fn closest_point(&self, point: Vec3) -> Vec3 {
+        point.clamp(self.start, self.end())
+    }
+
+    /// Returns the squared distance between `point` and the closest point
+    /// in the AABB. Points inside of the AABB have a distance of 0.0.
+    pub fn distance_squared(&self, point: Vec3) -> f32 {
+        self.closest_point(point).distance_squared(point)
+    }
+
+    /// Returns true if the sphere at `center` with radius `radius`
+    /// overlaps the AABB.
+    pub fn intersects_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.distance_squared(center) <= radius * radius
+    }
+
+    /// Returns the range of distances along the ray from `origin` in
+    /// direction `dir` that lie inside the AABB, or [None] if the ray
+    /// misses it. The range may start behind `origin`.
+    pub fn ray_intersection(&self, origin: Vec3, dir: Vec3) -> Option<(f32, f32)> {
+        let inv_dir = dir.recip();
+        let t1 = (self.start - origin) * inv_dir;
+        let t2 = (self.end() - origin) * inv_dir;
+        // NaN comes from rays parallel to a face and starting on it
+        let near = t1.min(t2).to_array().into_iter().filter(|t| !t.is_nan()).fold(f32::NEG_INFINITY, f32::max);
+        let far = t1.max(t2).to_array().into_iter().filter(|t| !t.is_nan()).fold(f32::INFINITY, f32::min);
+        (near <= far).then_some((near, far))
+    }
+
     /// Create an AABB centered on `pos`, using `extents` as the length
     /// of the box's edges.
     pub fn from_extents(pos: Vec3, extents: Vec3) -> Self {
         let half_extents = extents / 2.0;
-        return Self {
+        Self {
             start: pos - half_extents,
             size: extents,
-        };
+        }
     }
 
     /// Create an AABB centered on `pos`, using `radius * 2` as the length
@@ -92,11 +180,9 @@ impl AABB {
     /// Get the positions of the AABB's corners in Z-index order.
     pub fn calculate_corners(&self) -> [Vec3; 8] {
         assert!(self.size.is_negative_bitmask() == 0);
-        let corners = CUBE_CORNERS.map(|offset| {
+        CUBE_CORNERS.map(|offset| {
             self.start + (self.size * offset)
-        });
-
-        return corners;
+        })
     }
 
     /// Calculate the intersection between two AABBs and return the result.
@@ -112,10 +198,10 @@ impl AABB {
         // Intersect one axis at a time
         let axis_intersects: ArrayVec<AxisIntersectType, 3> = self
             .start.to_array().into_iter()
-            .zip(self.size.to_array().into_iter())
+            .zip(self.size.to_array())
             .zip(
                 other.start.to_array().into_iter()
-                .zip(other.size.to_array().into_iter())
+                .zip(other.size.to_array())
             )
             .map(|(this, other)| {
                 // Change (start, size) to (start, end)
@@ -127,29 +213,29 @@ impl AABB {
             .map(|(this, other)| {
                 // Perform axis intersection
                 if this.0 >= other.1 || this.1 <= other.0 {
-                    return AxisIntersectType::DoesNotIntersect;
+                    AxisIntersectType::DoesNotIntersect
                 }
                 else if this.0 <= other.0 && this.1 >= other.1 {
-                    return AxisIntersectType::Contains
+                    AxisIntersectType::Contains
                 }
                 else if(this.0 >= other.0 && this.1 < other.1) || (this.0 > other.0 && this.1 <= other.1) {
-                    return AxisIntersectType::ContainedBy
+                    AxisIntersectType::ContainedBy
                 }
                 else {
-                    return AxisIntersectType::Intersects(
+                    AxisIntersectType::Intersects(
                         this.0.max(other.0), this.1.min(other.1)
-                    );
+                    )
                 }
             }).collect();
         
         use AxisIntersectType::*;
         
         match axis_intersects.into_inner().unwrap() {
-            [Contains, Contains, Contains] => return IntersectType::Contains,
-            [ContainedBy, ContainedBy, ContainedBy] => return IntersectType::ContainedBy,
+            [Contains, Contains, Contains] => IntersectType::Contains,
+            [ContainedBy, ContainedBy, ContainedBy] => IntersectType::ContainedBy,
             [DoesNotIntersect, _, _] |
             [_, DoesNotIntersect, _] |
-            [_, _, DoesNotIntersect] => return IntersectType::DoesNotIntersect,
+            [_, _, DoesNotIntersect] => IntersectType::DoesNotIntersect,
             [x,y,z] => {
                 let x = match x {
                     Intersects(start, end) => (start, end - start),
@@ -169,7 +255,7 @@ impl AABB {
                     ContainedBy => (self.start.z, self.size.z),
                     DoesNotIntersect => unreachable!(),
                 };
-                return IntersectType::Intersects(
+                IntersectType::Intersects(
                     AABB {
                         start: vec3(x.0, y.0, z.0),
                         size: vec3(x.1, y.1, z.1),
@@ -195,19 +281,12 @@ impl AABB {
     /// array is in Z-index order.
     pub fn octree_subdivide(&self) -> [AABB; 8] {
         let half_size = self.size / 2.0;
-        let mut cells: ArrayVec<AABB, 8> = ArrayVec::new();
-        
-        CUBE_CORNERS.into_iter().for_each(|point| {
-            let start = self.start + (half_size * point);
-            cells.push(
-        AABB {
-                    start,
-                    size: half_size,
-                }
-            );
-        });
-        assert!(cells.windows(2).all(|a| {a[0].size == a[1].size}));
-        cells.into_inner().unwrap()
+        CUBE_CORNERS.map(|point| {
+            AABB {
+                start: self.start + (half_size * point),
+                size: half_size,
+            }
+        })
     }
 
     /// Calculate the AABB of octant child `index`.
@@ -287,4 +366,39 @@ fn octree_subdivide_test() {
     assert_eq!(subdiv[6], AABB { start: vec3(0.5,0.25,0.75), size: Vec3::splat(0.25) });
     let subdiv = subdiv[6].octree_subdivide();
     assert_eq!(subdiv[3], AABB { start: vec3(0.625,0.375,0.75), size: Vec3::splat(0.125) });
-}
\ No newline at end of file
+}
+
+#[test]
+fn utility_test() {
+    let aabb = AABB { start: vec3(1.0,2.0,3.0), size: vec3(2.0,2.0,2.0) };
+
+    assert_eq!(aabb.end(), vec3(3.0,4.0,5.0));
+    assert_eq!(aabb.center(), vec3(2.0,3.0,4.0));
+    assert_eq!(aabb.surface_area(), 24.0);
+    assert_eq!(aabb.expanded_by(1.0), AABB { start: vec3(0.0,1.0,2.0), size: Vec3::splat(4.0) });
+    assert_eq!(aabb.union(AABB::ONE_CUBIC_METER), AABB { start: Vec3::ZERO, size: vec3(3.0,4.0,5.0) });
+
+    assert_eq!(aabb.closest_point(vec3(2.0,3.0,4.0)), vec3(2.0,3.0,4.0));
+    assert_eq!(aabb.closest_point(vec3(0.0,3.0,10.0)), vec3(1.0,3.0,5.0));
+    assert_eq!(aabb.distance_squared(vec3(0.0,3.0,10.0)), 26.0);
+
+    assert!(aabb.intersects_sphere(vec3(0.0,3.0,4.0), 1.0));
+    assert!(!aabb.intersects_sphere(vec3(0.0,0.0,0.0), 2.0));
+
+    assert_eq!(aabb.ray_intersection(vec3(0.0,3.0,4.0), Vec3::X), Some((1.0, 3.0)));
+    assert_eq!(aabb.ray_intersection(vec3(0.0,0.0,4.0), Vec3::X), None);
+}
+
+#[test]
+#[cfg(all(feature = "mint", feature = "nalgebra"))]
+fn interop_test() {
+    let aabb = AABB::from_min_max(
+        mint::Point3 { x: 1.0, y: 2.0, z: 3.0 },
+        nalgebra::Point3::new(2.0, 4.0, 6.0),
+    );
+    assert_eq!(aabb, AABB { start: vec3(1.0,2.0,3.0), size: vec3(1.0,2.0,3.0) });
+
+    let (min, max): (nalgebra::Point3<f32>, nalgebra::Point3<f32>) = aabb.min_max();
+    assert_eq!(min, nalgebra::Point3::new(1.0, 2.0, 3.0));
+    assert_eq!(max, nalgebra::Point3::new(2.0, 4.0, 6.0));
+}
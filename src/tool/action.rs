@@ -1,6 +1,7 @@
 /// Action represents operations to perform on a Terrain with a given
 /// Tool.
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Action
 {
     /// Subtract material from the Terrain
@@ -22,4 +23,15 @@ impl Action
             },
         }
     }
+
+    /// Same as [`apply_value`](Self::apply_value), but only moves `point`
+    /// `weight` of the way towards the result. See [ToolFunc::weight](crate::tool::ToolFunc::weight).
+    pub fn apply_weighted(&self, point: &mut f32, val: f32, weight: f32)
+    {
+        let old = *point;
+        self.apply_value(point, val);
+        if weight < 1.0 {
+            *point = old + (*point - old) * weight.max(0.0);
+        }
+    }
 }
\ No newline at end of file
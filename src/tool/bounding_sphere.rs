@@ -0,0 +1,80 @@
+use glam::{ Vec3, Affine3A };
+
+use crate::tool::AABB;
+
+/// A sphere enclosing some region of space, used as a cheap and (for
+/// round tools) tight alternative to [AABB] bounds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BoundingSphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: Vec3, radius: f32) -> Self {
+        Self {
+            center,
+            radius,
+        }
+    }
+
+    /// Create the smallest BoundingSphere centered on the AABB that
+    /// contains all of its corners.
+    pub fn containing_aabb(aabb: AABB) -> Self {
+        Self {
+            center: aabb.center(),
+            radius: aabb.size.length() / 2.0,
+        }
+    }
+
+    /// Returns a BoundingSphere that contains this sphere after it has been
+    /// transformed by `transform`.
+    pub fn transformed(self, transform: Affine3A) -> Self {
+        let max_scale = [
+            transform.matrix3.x_axis.length(),
+            transform.matrix3.y_axis.length(),
+            transform.matrix3.z_axis.length(),
+        ].into_iter().fold(0.0, f32::max);
+
+        Self {
+            center: transform.transform_point3(self.center),
+            radius: self.radius * max_scale,
+        }
+    }
+
+    /// Returns true if the sphere overlaps `aabb`.
+    pub fn intersects_aabb(&self, aabb: AABB) -> bool {
+        aabb.intersects_sphere(self.center, self.radius)
+    }
+
+    /// Returns true if every corner of `aabb` lies within the sphere.
+    pub fn contains_aabb(&self, aabb: AABB) -> bool {
+        let farthest = (aabb.center() - self.center).abs() + (aabb.size / 2.0);
+        farthest.length_squared() <= self.radius * self.radius
+    }
+
+    /// Returns true if the sphere overlaps `aabb` without fully containing
+    /// it, meaning the surface of the sphere passes through the AABB.
+    pub fn straddles_aabb(&self, aabb: AABB) -> bool {
+        self.intersects_aabb(aabb) && !self.contains_aabb(aabb)
+    }
+}
+
+#[test]
+fn bounding_sphere_test() {
+    use glam::vec3;
+
+    let sphere = BoundingSphere::new(Vec3::ZERO, 2.0);
+
+    assert!(sphere.contains_aabb(AABB::from_radius(Vec3::ZERO, 1.0)));
+    assert!(!sphere.contains_aabb(AABB::from_radius(Vec3::ZERO, 1.5)));
+    assert!(sphere.straddles_aabb(AABB::from_radius(Vec3::ZERO, 1.5)));
+    // Overlaps the AABB of the sphere, but not the sphere itself
+    assert!(!sphere.intersects_aabb(AABB::from_radius(vec3(2.0,2.0,2.0), 0.5)));
+
+    let scaled = sphere.transformed(Affine3A::from_scale_rotation_translation(
+        vec3(1.0,3.0,2.0), Default::default(), vec3(1.0,0.0,0.0)
+    ));
+    assert_eq!(scaled, BoundingSphere::new(vec3(1.0,0.0,0.0), 6.0));
+}
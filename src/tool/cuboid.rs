@@ -0,0 +1,76 @@
+use glam::Vec3;
+use wide::f32x8;
+
+use crate::{
+    tool::{ ToolFunc, AABB, BoundingSphere },
+    utils::Vec3x8,
+};
+
+/// A ToolFunc that represents a cube from -1.0 to 1.0 on each axis.
+/// For boxes of other sizes, use [Tool](super::Tool) with a scaled
+/// Transform.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cuboid;
+
+impl ToolFunc for Cuboid {
+    fn value(&self, pos: Vec3) -> f32 {
+        let d = pos.abs() - Vec3::ONE;
+        let distance = d.max(Vec3::ZERO).length() + d.max_element().min(0.0);
+        (-distance).clamp(-1.0, 1.0)
+    }
+
+    #[inline]
+    fn value8(&self, pos: [Vec3; 8]) -> [f32; 8] {
+        self.value_x8(Vec3x8::from_points(pos)).to_array()
+    }
+
+    #[inline]
+    fn value_x8(&self, pos: Vec3x8) -> f32x8 {
+        let d = pos.abs() - Vec3::ONE;
+        let outside = Vec3x8 { x: d.x.fast_max(f32x8::ZERO), y: d.y.fast_max(f32x8::ZERO), z: d.z.fast_max(f32x8::ZERO) };
+        let distance = outside.length() + d.x.fast_max(d.y.fast_max(d.z)).fast_min(f32x8::ZERO);
+        (-distance).fast_max(f32x8::splat(-1.0)).fast_min(f32x8::ONE)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        AABB::from_radius(Vec3::ZERO, 1.0)
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        AABB::from_radius(Vec3::ZERO, 2.0)
+    }
+
+    fn aoe_bounding_sphere(&self) -> BoundingSphere {
+        // Values reach -1.0 one unit away from the corners
+        BoundingSphere::new(Vec3::ZERO, 3f32.sqrt() + 1.0)
+    }
+
+    #[inline(always)]
+    fn is_exact_distance(&self) -> bool {
+        true
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        false
+    }
+}
+
+#[test]
+fn cuboid_test() {
+    use crate::utils::Rng;
+    use glam::vec3;
+
+    assert_eq!(Cuboid.value(Vec3::ZERO), 1.0);
+    assert_eq!(Cuboid.value(vec3(0.5, 0.0, 0.0)), 0.5);
+    assert_eq!(Cuboid.value(vec3(1.5, 0.0, 0.0)), -0.5);
+    assert!((Cuboid.value(vec3(1.5, 1.5, 0.0)) + 0.5f32.sqrt()).abs() < 1e-6);
+
+    // The lanes must give exactly the same values as the scalar path
+    let mut rng = Rng::new(5);
+    for _ in 0..64 {
+        let points: [Vec3; 8] = std::array::from_fn(|_| Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 6.0 - 3.0);
+        assert_eq!(Cuboid.value8(points), points.map(|p| Cuboid.value(p)));
+    }
+}
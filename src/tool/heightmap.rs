@@ -0,0 +1,160 @@
+use glam::Vec3;
+
+use crate::tool::{ ToolFunc, AABB };
+
+/// A ToolFunc that represents a heightfield occupying the unit cube.
+/// 
+/// Heights are sampled on a `width` x `depth` grid spanning X and Z from
+/// 0.0 to 1.0, and are expected to be in the range `[0, 1]`. The space
+/// between Y = 0.0 and the interpolated height is solid. To fit a
+/// Heightmap to a Terrain, use [Tool](super::Tool) with a scaled Transform.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Heightmap {
+    width: usize,
+    depth: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Create a new Heightmap from `heights` in row-major order, where each
+    /// row runs along the X axis.
+    /// 
+    /// Panics if `heights` doesn't contain `width * depth` values, or if
+    /// either dimension is 0.
+    pub fn new(width: usize, depth: usize, heights: Vec<f32>) -> Self {
+        Self::try_new(width, depth, heights).expect("Invalid Heightmap")
+    }
+
+    /// Same as [`new`](Self::new), but returns an error instead of
+    /// panicking if the dimensions are invalid.
+    pub fn try_new(width: usize, depth: usize, heights: Vec<f32>) -> crate::Result<Self> {
+        if width == 0 || depth == 0 || heights.len() != width * depth {
+            return Err(crate::Error::InvalidDimensions { dims: vec![width, depth], len: heights.len() });
+        }
+        Ok(Self {
+            width,
+            depth,
+            heights,
+        })
+    }
+
+    /// Decodes an image and uses its luminance as the heights. 16-bit
+    /// grayscale images keep their full precision.
+    #[cfg(feature = "image")]
+    pub fn from_image_bytes(bytes: &[u8]) -> crate::Result<Self> {
+        Self::from_image(image::load_from_memory(bytes)?)
+    }
+
+    /// Loads an image file and uses its luminance as the heights. 16-bit
+    /// grayscale images keep their full precision.
+    #[cfg(feature = "image")]
+    pub fn from_image_path(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        Self::from_image(image::open(path)?)
+    }
+
+    /// Uses the luminance of a decoded image as the heights. Returns an
+    /// error if the image is empty.
+    #[cfg(feature = "image")]
+    pub fn from_image(image: image::DynamicImage) -> crate::Result<Self> {
+        let luma = image.into_luma16();
+        let (width, depth) = luma.dimensions();
+        let heights = luma.into_raw().into_iter()
+            .map(|val| val as f32 / u16::MAX as f32)
+            .collect();
+        Self::try_new(width as usize, depth as usize, heights)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn heights(&self) -> &[f32] {
+        &self.heights
+    }
+
+    /// Returns the bilinearly interpolated height at `(x, z)`, clamped to
+    /// the edges of the Heightmap.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let sample = |val: f32, size: usize| -> (usize, usize, f32) {
+            let pos = val.clamp(0.0, 1.0) * (size - 1) as f32;
+            let low = (pos.floor() as usize).min(size - 1);
+            let high = (low + 1).min(size - 1);
+            (low, high, pos - low as f32)
+        };
+        let (x0, x1, tx) = sample(x, self.width);
+        let (z0, z1, tz) = sample(z, self.depth);
+        let height = |x: usize, z: usize| self.heights[x + z * self.width];
+
+        let near = height(x0, z0) + (height(x1, z0) - height(x0, z0)) * tx;
+        let far = height(x0, z1) + (height(x1, z1) - height(x0, z1)) * tx;
+        near + (far - near) * tz
+    }
+
+    fn max_height(&self) -> f32 {
+        self.heights.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+impl ToolFunc for Heightmap {
+    fn value(&self, pos: Vec3) -> f32 {
+        let height = self.height_at(pos.x, pos.z);
+        // Solid below the surface and above the floor, and within the
+        // unit square on X and Z
+        (height - pos.y)
+            .min(pos.y)
+            .min(pos.x).min(1.0 - pos.x)
+            .min(pos.z).min(1.0 - pos.z)
+            .clamp(-1.0, 1.0)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        AABB {
+            start: Vec3::ZERO,
+            size: Vec3::new(1.0, self.max_height(), 1.0),
+        }
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        self.tool_aabb().expanded_by(1.0)
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn heightmap_test() {
+    use glam::vec3;
+
+    let heightmap = Heightmap::new(2, 2, vec![0.0, 0.5, 0.5, 1.0]);
+    assert!(Heightmap::try_new(2, 3, vec![0.0; 4]).is_err());
+    assert_eq!(heightmap.height_at(0.5, 0.5), 0.5);
+    assert_eq!(heightmap.height_at(1.0, 0.0), 0.5);
+    assert_eq!(heightmap.height_at(2.0, 2.0), 1.0);
+
+    assert!(heightmap.value(vec3(0.75,0.25,0.75)) > 0.0);
+    assert!(heightmap.value(vec3(0.25,0.4,0.25)) < 0.0);
+    assert!(heightmap.value(vec3(1.5,0.1,0.5)) < 0.0);
+    assert_eq!(heightmap.tool_aabb(), AABB::ONE_CUBIC_METER);
+}
+
+#[test]
+#[cfg(feature = "image")]
+fn heightmap_image_test() {
+    use image::{ ImageBuffer, Luma, ImageOutputFormat };
+
+    let image = ImageBuffer::from_fn(4, 2, |x, _| Luma([x as u16 * 20000]));
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageLuma16(image).write_to(&mut bytes, ImageOutputFormat::Png).unwrap();
+
+    let heightmap = Heightmap::from_image_bytes(bytes.get_ref()).unwrap();
+    assert_eq!((heightmap.width(), heightmap.depth()), (4, 2));
+    assert_eq!(heightmap.heights()[1], 20000.0 / u16::MAX as f32);
+}
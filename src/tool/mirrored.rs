@@ -0,0 +1,119 @@
+use glam::{ Vec3, Mat3A, Affine3A };
+
+use crate::tool::{ ToolFunc, AABB };
+
+/// A plane to reflect a [Mirrored] ToolFunc across. The plane contains
+/// every point `p` where `p.dot(normal) == offset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MirrorPlane {
+    pub normal: Vec3,
+    pub offset: f32,
+}
+
+impl MirrorPlane {
+    /// Create a new MirrorPlane. `normal` is normalized.
+    pub fn new(normal: Vec3, offset: f32) -> Self {
+        Self {
+            normal: normal.normalize(),
+            offset,
+        }
+    }
+
+    /// Create a MirrorPlane with the given `normal` that passes through
+    /// `point`.
+    pub fn through_point(normal: Vec3, point: Vec3) -> Self {
+        let normal = normal.normalize();
+        Self {
+            normal,
+            offset: normal.dot(point),
+        }
+    }
+
+    /// Returns the transform that reflects points across the plane.
+    pub fn reflection(&self) -> Affine3A {
+        let n = self.normal;
+        let matrix = Mat3A::IDENTITY - Mat3A::from_cols(
+            (n * (2.0 * n.x)).into(),
+            (n * (2.0 * n.y)).into(),
+            (n * (2.0 * n.z)).into(),
+        );
+        Affine3A {
+            matrix3: matrix,
+            translation: (n * (2.0 * self.offset)).into(),
+        }
+    }
+}
+
+/// A ToolFunc wrapper that evaluates another ToolFunc along with its
+/// reflections across one or more [MirrorPlane]s, for symmetric editing.
+/// 
+/// With multiple planes, every combination of reflections is included, so
+/// `n` planes produce `2^n` copies of the tool.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mirrored<F> {
+    pub func: F,
+    reflections: Vec<Affine3A>,
+}
+
+impl<F> Mirrored<F> {
+    pub fn new(func: F, planes: impl IntoIterator<Item = MirrorPlane>) -> Self {
+        let mut reflections = vec![Affine3A::IDENTITY];
+        planes.into_iter().for_each(|plane| {
+            let reflection = plane.reflection();
+            let mirrored: Vec<Affine3A> = reflections.iter().map(|&r| reflection * r).collect();
+            reflections.extend(mirrored);
+        });
+
+        Self {
+            func,
+            reflections,
+        }
+    }
+}
+
+impl<F: ToolFunc> ToolFunc for Mirrored<F> {
+    fn value(&self, pos: Vec3) -> f32 {
+        // Reflections are their own inverse
+        self.reflections.iter()
+            .map(|reflection| self.func.value(reflection.transform_point3(pos)))
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        let aabb = self.func.tool_aabb();
+        self.reflections.iter().map(|&r| aabb.transformed(r)).reduce(|a, b| a.union(b)).unwrap()
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        let aabb = self.func.aoe_aabb();
+        self.reflections.iter().map(|&r| aabb.transformed(r)).reduce(|a, b| a.union(b)).unwrap()
+    }
+
+    fn weight(&self) -> f32 {
+        self.func.weight()
+    }
+
+    /// The union of several copies of a tool is generally not convex.
+    fn is_concave(&self) -> bool {
+        self.reflections.len() > 1 || self.func.is_concave()
+    }
+}
+
+#[test]
+fn mirrored_test() {
+    use crate::tool::{ Tool, Sphere };
+    use glam::{ vec3, vec3a };
+
+    let tool = Tool::new(Sphere).translated(vec3a(2.0,3.0,0.0));
+    let mirrored = Mirrored::new(tool, [
+        MirrorPlane::new(Vec3::X, 0.0),
+        MirrorPlane::through_point(Vec3::Y, vec3(0.0,1.0,0.0)),
+    ]);
+
+    [vec3(2.0,3.0,0.0), vec3(-2.0,3.0,0.0), vec3(2.0,-1.0,0.0), vec3(-2.0,-1.0,0.0)].into_iter().for_each(|pos| {
+        assert_eq!(mirrored.value(pos), 1.0, "{} is not mirrored", pos);
+    });
+    assert_eq!(mirrored.tool_aabb(), AABB { start: vec3(-3.0,-2.0,-1.0), size: vec3(6.0,6.0,2.0) });
+}
@@ -1,21 +1,90 @@
 mod sphere;
 pub use sphere::*;
 
+mod cuboid;
+pub use cuboid::*;
+
+mod heightmap;
+pub use heightmap::*;
+
 mod aabb;
 pub use aabb::*;
 
+mod obb;
+pub use obb::*;
+
+mod bounding_sphere;
+pub use bounding_sphere::*;
+
 mod action;
 pub use action::*;
 
-use glam::{ Vec3, Affine3A, Quat, Vec3A };
+mod weighted;
+pub use weighted::*;
+
+mod swept;
+pub use swept::*;
+
+mod mirrored;
+pub use mirrored::*;
+
+mod preset;
+pub use preset::*;
+
+mod normalize;
+pub use normalize::*;
+
+mod noise;
+pub use noise::*;
+
+use glam::{ Vec3, Affine3A, Quat, Vec3A, Mat4 };
+use wide::f32x8;
+use crate::utils::Vec3x8;
 
 /// A ToolFunc represents a function that can return a density value for a given
 /// point. i.e. a [Sphere] will produce positive values within the Sphere's surface,
 /// and negative values outside of it.
+/// 
+/// Values are expected to be clamped to `[-1, 1]`. ToolFuncs that return the
+/// true signed distance to their surface (positive inside) should report so
+/// with [`is_exact_distance`](Self::is_exact_distance), and can be mapped into
+/// a consistent blend width with [Normalize].
 pub trait ToolFunc {
     /// Get the isovalue of `pos` in the ToolFunc.
     fn value(&self, pos: Vec3) -> f32;
 
+    /// Get the isovalues of 8 points at once, such as the corners of
+    /// an octree cell. ToolFuncs can override this to vectorize the
+    /// evaluation.
+    #[inline]
+    fn value8(&self, pos: [Vec3; 8]) -> [f32; 8] {
+        pos.map(|p| self.value(p))
+    }
+
+    /// Same as [`value8`](Self::value8), with the points split into SIMD
+    /// lanes. ToolFuncs built from simple math can override this to
+    /// evaluate all 8 points at once, and override `value8` to call it.
+    #[inline]
+    fn value_x8(&self, pos: Vec3x8) -> f32x8 {
+        f32x8::from(self.value8(pos.to_points()))
+    }
+
+    /// Get the gradient of the isovalue at `pos`, pointing towards
+    /// increasing values (i.e. into the tool).
+    /// 
+    /// The default implementation uses central differences.
+    fn gradient(&self, pos: Vec3) -> Vec3 {
+        const EPSILON: f32 = 0.001;
+        let diff = |offset: Vec3| {
+            (self.value(pos + offset) - self.value(pos - offset)) / (2.0 * EPSILON)
+        };
+        Vec3::new(
+            diff(Vec3::X * EPSILON),
+            diff(Vec3::Y * EPSILON),
+            diff(Vec3::Z * EPSILON),
+        )
+    }
+
     /// Returns the ToolFunc AABB, representing a rough
     /// estimated area of space that might produce values
     /// greater than 0.0
@@ -26,6 +95,35 @@ pub trait ToolFunc {
     /// greater than -1.0
     fn aoe_aabb(&self) -> AABB;
 
+    /// Returns a sphere enclosing the same space as
+    /// [`tool_aabb`](Self::tool_aabb). The default implementation
+    /// encloses the corners of the AABB, so round ToolFuncs should
+    /// override this with a tighter sphere.
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::containing_aabb(self.tool_aabb())
+    }
+
+    /// Returns a sphere enclosing the same space as
+    /// [`aoe_aabb`](Self::aoe_aabb).
+    fn aoe_bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::containing_aabb(self.aoe_aabb())
+    }
+
+    /// Returns true if [`value`](Self::value) returns the true signed
+    /// distance to the ToolFunc's surface, positive inside and negative
+    /// outside, at least within the `[-1, 1]` range.
+    fn is_exact_distance(&self) -> bool {
+        false
+    }
+
+    /// How strongly the ToolFunc is applied, from 0.0 to 1.0. Each Terrain
+    /// value only moves this fraction of the way towards the value the
+    /// [Action] would give it. See [Weighted].
+    #[inline(always)]
+    fn weight(&self) -> f32 {
+        1.0
+    }
+
     /// Returns true if the given ToolFunc is [convex](https://en.wikipedia.org/wiki/Convex_polygon).
     fn is_concave(&self) -> bool;
 
@@ -37,36 +135,86 @@ pub trait ToolFunc {
 }
 
 /// A wrapper for ToolFunc that gives it a Transform.
+/// 
+/// The world-space AABBs of the tool are cached, and are recalculated
+/// whenever the transform or the wrapped ToolFunc is changed.
+#[derive(Debug)]
 pub struct Tool<F> {
-    pub func: F,
+    func: F,
     transform: Affine3A,
     _inverse: Affine3A,
+    _kind: TransformKind,
+    _tool_aabb: AABB,
+    _aoe_aabb: AABB,
+    _tool_obb: OBB,
+    _aoe_obb: OBB,
+    _tool_sphere: BoundingSphere,
+    _aoe_sphere: BoundingSphere,
+}
+
+/// Describes the transform of a [Tool], so that [`ToolFunc::value`] can
+/// skip the full inverse transform when it isn't needed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TransformKind {
+    Identity,
+    Translation(Vec3A),
+    General,
+}
+
+impl TransformKind {
+    fn of(transform: &Affine3A) -> Self {
+        if transform.matrix3 != glam::Mat3A::IDENTITY {
+            TransformKind::General
+        }
+        else if transform.translation == Vec3A::ZERO {
+            TransformKind::Identity
+        }
+        else {
+            TransformKind::Translation(transform.translation)
+        }
+    }
 }
 
 impl<F: Clone> Clone for Tool<F> {
     fn clone(&self) -> Self {
         Self {
             func: self.func.clone(),
-            transform: self.transform.clone(),
-            _inverse: self._inverse.clone(),
+            transform: self.transform,
+            _inverse: self._inverse,
+            _kind: self._kind,
+            _tool_aabb: self._tool_aabb,
+            _aoe_aabb: self._aoe_aabb,
+            _tool_obb: self._tool_obb,
+            _aoe_obb: self._aoe_obb,
+            _tool_sphere: self._tool_sphere,
+            _aoe_sphere: self._aoe_sphere,
         }
     }
 }
 
 impl<F: Copy> Copy for Tool<F> {}
 
-impl<F> Tool<F> {
+impl<F: ToolFunc> Tool<F> {
     pub fn new(func: F) -> Self {
-        Self {
+        let mut tool = Self {
             func,
             transform: Affine3A::IDENTITY,
             _inverse: Affine3A::IDENTITY,
-        }
+            _kind: TransformKind::Identity,
+            _tool_aabb: AABB::default(),
+            _aoe_aabb: AABB::default(),
+            _tool_obb: OBB::from_transformed_aabb(AABB::default(), Affine3A::IDENTITY),
+            _aoe_obb: OBB::from_transformed_aabb(AABB::default(), Affine3A::IDENTITY),
+            _tool_sphere: BoundingSphere::new(Vec3::ZERO, 0.0),
+            _aoe_sphere: BoundingSphere::new(Vec3::ZERO, 0.0),
+        };
+        tool.update_cache();
+        tool
     }
 
     pub fn translated(mut self, translation: Vec3A) -> Self {
         self.transform.translation += translation;
-        self._inverse = self.transform.inverse();
+        self.update_cache();
         self
     }
 
@@ -80,49 +228,194 @@ impl<F> Tool<F> {
 
     pub fn transformed(mut self, transform: Affine3A) -> Self {
         self.transform = transform * self.transform;
-        self._inverse = self.transform.inverse();
+        self.update_cache();
         self
     }
 
     pub fn set_transform(&mut self, trns: Affine3A) {
         self.transform = trns;
-        self._inverse = trns.inverse();
+        self.update_cache();
+    }
+
+    /// Sets the transform from a 4x4 matrix, such as a `mint` or
+    /// `nalgebra` matrix when the respective features are enabled. The
+    /// matrix is expected to be an affine transform.
+    pub fn set_transform_matrix(&mut self, matrix: impl Into<Mat4>) {
+        self.set_transform(Affine3A::from_mat4(matrix.into()));
+    }
+
+    /// Returns the transform as a 4x4 matrix, converted to any type
+    /// constructible from [Mat4].
+    pub fn transform_matrix<T: From<Mat4>>(&self) -> T {
+        Mat4::from(self.transform).into()
     }
 
     pub fn transform(&self) -> &Affine3A {
-        &&self.transform
+        &self.transform
     }
 
     pub fn inverse_transform(&self) -> &Affine3A {
         &self._inverse
     }
 
-    pub fn value(&self, pos: Vec3) -> f32 where F: ToolFunc {
-        let inverse = self.inverse_transform();
-        let local_pos = inverse.transform_point3(pos);
+    /// Returns the oriented bounding box of the transformed
+    /// [`ToolFunc::tool_aabb`]. For rotated tools, this is tighter than the
+    /// world-space [`ToolFunc::tool_aabb`] of the Tool.
+    pub fn tool_obb(&self) -> OBB {
+        self._tool_obb
+    }
+
+    /// Returns the oriented bounding box of the transformed
+    /// [`ToolFunc::aoe_aabb`].
+    pub fn aoe_obb(&self) -> OBB {
+        self._aoe_obb
+    }
+
+    /// Returns the wrapped ToolFunc.
+    pub fn func(&self) -> &F {
+        &self.func
+    }
+
+    /// Replaces the wrapped ToolFunc, keeping the current transform.
+    pub fn set_func(&mut self, func: F) {
+        self.func = func;
+        self.update_cache();
+    }
+
+    /// Unwraps the Tool, discarding its transform.
+    pub fn into_func(self) -> F {
+        self.func
+    }
+
+    /// Recalculates the inverse transform and the cached world-space
+    /// AABBs.
+    fn update_cache(&mut self) {
+        self._inverse = self.transform.inverse();
+        self._kind = TransformKind::of(&self.transform);
+        let local_tool_aabb = self.func.tool_aabb();
+        let local_aoe_aabb = self.func.aoe_aabb();
+        self._tool_aabb = local_tool_aabb.transformed(self.transform);
+        self._aoe_aabb = local_aoe_aabb.transformed(self.transform);
+        self._tool_obb = OBB::from_transformed_aabb(local_tool_aabb, self.transform);
+        self._aoe_obb = OBB::from_transformed_aabb(local_aoe_aabb, self.transform);
+        self._tool_sphere = self.func.bounding_sphere().transformed(self.transform);
+        self._aoe_sphere = self.func.aoe_bounding_sphere().transformed(self.transform);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<F: serde::Serialize> serde::Serialize for Tool<F> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        // Only the ToolFunc and transform are stored, the cached
+        // values are recalculated on deserialization.
+        let mut state = serializer.serialize_struct("Tool", 2)?;
+        state.serialize_field("func", &self.func)?;
+        state.serialize_field("transform", &self.transform)?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, F: ToolFunc + serde::Deserialize<'de>> serde::Deserialize<'de> for Tool<F> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Tool")]
+        struct ToolData<F> {
+            func: F,
+            transform: Affine3A,
+        }
+
+        let data = ToolData::<F>::deserialize(deserializer)?;
+        let mut tool = Tool::new(data.func);
+        tool.set_transform(data.transform);
+        Ok(tool)
+    }
+}
+
+/// A transformed ToolFunc is itself a ToolFunc, evaluated in world space.
+/// This allows a [Tool] to be nested inside other tools.
+impl<F: ToolFunc> ToolFunc for Tool<F> {
+    #[inline]
+    fn value(&self, pos: Vec3) -> f32 {
+        let local_pos = match self._kind {
+            TransformKind::Identity => pos,
+            TransformKind::Translation(translation) => pos - Vec3::from(translation),
+            TransformKind::General => self._inverse.transform_point3(pos),
+        };
         self.func.value(local_pos)
     }
 
-    pub fn tool_aabb(&self) -> AABB where F: ToolFunc {
-        let mut local_aabb = self.func.tool_aabb();
-        local_aabb.transform_with(self.transform);
-        local_aabb
+    #[inline]
+    fn value8(&self, pos: [Vec3; 8]) -> [f32; 8] {
+        self.value_x8(Vec3x8::from_points(pos)).to_array()
+    }
+
+    #[inline]
+    fn value_x8(&self, pos: Vec3x8) -> f32x8 {
+        let local_pos = match self._kind {
+            TransformKind::Identity => pos,
+            TransformKind::Translation(translation) => pos - Vec3::from(translation),
+            TransformKind::General => pos.transform(&self._inverse),
+        };
+        self.func.value_x8(local_pos)
     }
 
-    pub fn aoe_aabb(&self) -> AABB where F: ToolFunc {
-        let mut local_aabb = self.func.aoe_aabb();
-        local_aabb.transform_with(self.transform);
-        local_aabb
+    /// Evaluates the ToolFunc's gradient in local space, and transforms it
+    /// back to world space using the inverse-transpose of the transform.
+    fn gradient(&self, pos: Vec3) -> Vec3 {
+        match self._kind {
+            TransformKind::Identity => self.func.gradient(pos),
+            TransformKind::Translation(translation) => self.func.gradient(pos - Vec3::from(translation)),
+            TransformKind::General => {
+                let local_grad = self.func.gradient(self._inverse.transform_point3(pos));
+                self._inverse.matrix3.transpose().mul_vec3(local_grad)
+            },
+        }
     }
 
     #[inline(always)]
-    pub fn is_concave(&self) -> bool where F: ToolFunc {
-        self.func.is_concave()
+    fn tool_aabb(&self) -> AABB {
+        self._tool_aabb
+    }
+
+    #[inline(always)]
+    fn aoe_aabb(&self) -> AABB {
+        self._aoe_aabb
+    }
+
+    #[inline(always)]
+    fn bounding_sphere(&self) -> BoundingSphere {
+        self._tool_sphere
+    }
+
+    #[inline(always)]
+    fn aoe_bounding_sphere(&self) -> BoundingSphere {
+        self._aoe_sphere
+    }
+
+    /// Scaling changes the distances produced by the ToolFunc, so a Tool
+    /// is only exact if its transform is rigid.
+    fn is_exact_distance(&self) -> bool {
+        let rigid = match self._kind {
+            TransformKind::Identity | TransformKind::Translation(_) => true,
+            TransformKind::General => {
+                let matrix = self.transform.matrix3;
+                (matrix.transpose() * matrix).abs_diff_eq(glam::Mat3A::IDENTITY, 0.0001)
+            },
+        };
+        rigid && self.func.is_exact_distance()
     }
 
     #[inline(always)]
-    pub fn is_convex(&self) -> bool where F: ToolFunc {
-        self.func.is_convex()
+    fn weight(&self) -> f32 {
+        self.func.weight()
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        self.func.is_concave()
     }
 }
 
@@ -145,4 +438,75 @@ fn tool_test() {
     println!("tool({}) = {}", pos, tool.value(pos));
     tool = tool.translated(vec3a(1.0,0.0,0.0));
     println!("tool({}) = {}", pos, tool.value(pos));
-}
\ No newline at end of file
+}
+
+#[test]
+fn nested_tool_test() {
+    use glam::{ vec3, vec3a };
+
+    let inner = Tool::new(Sphere).scaled(Vec3::splat(2.0));
+    let nested = Tool::new(inner).translated(vec3a(5.0,0.0,0.0));
+    let flat = Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(vec3a(5.0,0.0,0.0));
+
+    let pos = vec3(6.0,0.5,0.0);
+    assert_eq!(nested.value(pos), flat.value(pos));
+    assert_eq!(nested.tool_aabb(), flat.tool_aabb());
+    assert_eq!(nested.aoe_aabb(), flat.aoe_aabb());
+}
+
+
+#[test]
+fn tool_cache_test() {
+    use glam::{ vec3, vec3a };
+
+    let mut tool = Tool::new(Sphere).translated(vec3a(1.0,2.0,3.0));
+    assert_eq!(tool._kind, TransformKind::Translation(vec3a(1.0,2.0,3.0)));
+    assert_eq!(tool.value(vec3(1.0,2.0,3.0)), 1.0);
+
+    tool = tool.scaled(Vec3::splat(2.0));
+    assert_eq!(tool._kind, TransformKind::General);
+    assert_eq!(tool.tool_aabb(), AABB::from_radius(vec3(2.0,4.0,6.0), 2.0));
+
+    tool.set_transform(Affine3A::IDENTITY);
+    assert_eq!(tool._kind, TransformKind::Identity);
+    assert_eq!(tool.tool_aabb(), Sphere.tool_aabb());
+    assert_eq!(tool.aoe_aabb(), Sphere.aoe_aabb());
+}
+
+#[test]
+fn value8_test() {
+    use glam::{ vec3, vec3a };
+
+    let tool = Tool::new(Sphere).scaled(Vec3::splat(2.0)).translated(vec3a(0.5,0.0,0.0));
+    let corners = AABB::from_radius(Vec3::ZERO, 1.5).calculate_corners();
+    assert_eq!(tool.value8(corners), corners.map(|p| tool.value(p)));
+
+    // Rotated tools are transformed lane-wise too
+    let tool = Tool::new(Sphere).scaled(vec3(2.0, 1.0, 0.5)).rotated(Quat::from_rotation_z(0.3)).translated(vec3a(0.5,0.0,0.0));
+    assert_eq!(tool.value8(corners), corners.map(|p| tool.value(p)));
+    let nested = Tool::new(Normalize::new(tool, 0.5));
+    assert_eq!(nested.value8(corners), corners.map(|p| nested.value(p)));
+}
+
+#[test]
+fn gradient_test() {
+    use glam::{ vec3, vec3a, Quat };
+
+    struct Numeric<F>(F);
+    impl<F: ToolFunc> ToolFunc for Numeric<F> {
+        fn value(&self, pos: Vec3) -> f32 { self.0.value(pos) }
+        fn tool_aabb(&self) -> AABB { self.0.tool_aabb() }
+        fn aoe_aabb(&self) -> AABB { self.0.aoe_aabb() }
+        fn is_concave(&self) -> bool { self.0.is_concave() }
+    }
+
+    let pos = vec3(1.0,2.0,0.5);
+    let analytic = Tool::new(Sphere)
+        .scaled(vec3(2.0,3.0,1.0))
+        .rotated(Quat::from_rotation_z(0.3))
+        .translated(vec3a(0.5,0.0,0.0));
+    let numeric = Tool::new(Numeric(Sphere)).transformed(*analytic.transform());
+
+    assert_ne!(analytic.gradient(pos), Vec3::ZERO);
+    assert!(analytic.gradient(pos).abs_diff_eq(numeric.gradient(pos), 0.01));
+}
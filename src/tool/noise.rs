@@ -0,0 +1,113 @@
+use glam::Vec3;
+
+use crate::{ tool::{ ToolFunc, AABB }, utils::Seed };
+
+/// Settings for a ridged multifractal noise field.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NoiseParams {
+    pub seed: Seed,
+    /// The number of noise features per unit of distance in the first octave.
+    pub frequency: f32,
+    pub octaves: u32,
+    /// How much the frequency is multiplied by for each octave.
+    pub lacunarity: f32,
+    /// How much the amplitude is multiplied by for each octave.
+    pub gain: f32,
+    /// Noise values from 0.0 to 1.0 above this threshold are inside of
+    /// the tool. Higher thresholds produce thinner tunnels.
+    pub threshold: f32,
+}
+
+impl Default for NoiseParams {
+    fn default() -> Self {
+        Self {
+            seed: Seed(0),
+            frequency: 0.1,
+            octaves: 3,
+            lacunarity: 2.0,
+            gain: 0.5,
+            threshold: 0.85,
+        }
+    }
+}
+
+/// A ToolFunc of ridged multifractal noise, which forms networks of
+/// winding tunnels where it is positive. The noise is limited to `bounds`.
+/// 
+/// Removing this from a Terrain carves caves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RidgedNoise {
+    pub params: NoiseParams,
+    pub bounds: AABB,
+}
+
+impl RidgedNoise {
+    pub fn new(params: NoiseParams, bounds: AABB) -> Self {
+        Self { params, bounds }
+    }
+
+    /// Returns the ridged noise at `pos`, in the range `[0, 1]`.
+    pub fn ridged(&self, pos: Vec3) -> f32 {
+        let params = &self.params;
+        let mut frequency = params.frequency;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        for octave in 0..params.octaves {
+            let ridge = 1.0 - gradient_noise(params.seed.derive(octave as u64), pos * frequency).abs();
+            total += ridge * ridge * amplitude;
+            total_amplitude += amplitude;
+            frequency *= params.lacunarity;
+            amplitude *= params.gain;
+        }
+        if total_amplitude > 0.0 { total / total_amplitude } else { 0.0 }
+    }
+}
+
+/// 3D gradient noise in the range of about `[-1, 1]`.
+fn gradient_noise(seed: Seed, pos: Vec3) -> f32 {
+    let cell = pos.floor();
+    let local = pos - cell;
+    let cell = cell.as_ivec3();
+
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let t = Vec3::new(fade(local.x), fade(local.y), fade(local.z));
+
+    let values = crate::CUBE_CORNERS.map(|corner| {
+        let lattice = cell + corner.as_ivec3();
+        let mut rng = seed.rng_at(lattice.as_vec3());
+        let gradient = Vec3::new(
+            rng.next_f32() * 2.0 - 1.0,
+            rng.next_f32() * 2.0 - 1.0,
+            rng.next_f32() * 2.0 - 1.0,
+        ).normalize_or_zero();
+        gradient.dot(local - corner)
+    });
+
+    (crate::utils::trilinear(&values, t) * 1.5).clamp(-1.0, 1.0)
+}
+
+impl ToolFunc for RidgedNoise {
+    fn value(&self, pos: Vec3) -> f32 {
+        if !self.bounds.contains(pos) {
+            return -1.0;
+        }
+        let threshold = self.params.threshold;
+        ((self.ridged(pos) - threshold) / (1.0 - threshold).max(f32::EPSILON)).clamp(-1.0, 1.0)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        self.bounds
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        self.bounds
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        true
+    }
+}
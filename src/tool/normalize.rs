@@ -0,0 +1,123 @@
+use glam::Vec3;
+use wide::{ f32x8, CmpLe };
+
+use crate::{
+    tool::{ ToolFunc, AABB },
+    utils::Vec3x8,
+};
+
+/// A ToolFunc adapter that maps the true distances of an
+/// [exact](ToolFunc::is_exact_distance) ToolFunc into the crate's
+/// clamped `[-1, 1]` convention.
+/// 
+/// Values transition from 1.0 to -1.0 over a band of `falloff * 2.0`
+/// around the surface, so tools wrapped with the same falloff blend with
+/// the same width regardless of their size.
+/// 
+/// The wrapped ToolFunc is only exact within `[-1, 1]`, so with a falloff
+/// above 1.0 its values saturate before the band is over. Saturated values
+/// are mapped to -1.0, which keeps the area of effect to the wrapped
+/// ToolFunc's own instead of spreading `-1.0 / falloff` over all space.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Normalize<F> {
+    pub func: F,
+    pub falloff: f32,
+}
+
+impl<F> Normalize<F> {
+    pub fn new(func: F, falloff: f32) -> Self {
+        Self {
+            func,
+            falloff,
+        }
+    }
+}
+
+impl<F: ToolFunc> Normalize<F> {
+    #[inline(always)]
+    fn normalize(&self, val: f32) -> f32 {
+        if val <= -1.0 {
+            return -1.0;
+        }
+        (val / self.falloff).clamp(-1.0, 1.0)
+    }
+}
+
+impl<F: ToolFunc> ToolFunc for Normalize<F> {
+    fn value(&self, pos: Vec3) -> f32 {
+        self.normalize(self.func.value(pos))
+    }
+
+    fn value8(&self, pos: [Vec3; 8]) -> [f32; 8] {
+        self.func.value8(pos).map(|val| self.normalize(val))
+    }
+
+    fn value_x8(&self, pos: Vec3x8) -> f32x8 {
+        let val = self.func.value_x8(pos);
+        let normalized = (val / f32x8::splat(self.falloff)).fast_max(f32x8::splat(-1.0)).fast_min(f32x8::ONE);
+        val.cmp_le(f32x8::splat(-1.0)).blend(f32x8::splat(-1.0), normalized)
+    }
+
+    fn gradient(&self, pos: Vec3) -> Vec3 {
+        let val = self.func.value(pos);
+        if val.abs() >= self.falloff || val <= -1.0 {
+            return Vec3::ZERO;
+        }
+        self.func.gradient(pos) / self.falloff
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        self.func.tool_aabb()
+    }
+
+    /// Values reach -1.0 at `falloff` distance outside of the surface, or
+    /// wherever the wrapped ToolFunc saturates if that is closer.
+    fn aoe_aabb(&self) -> AABB {
+        if self.falloff > 1.0 {
+            self.func.aoe_aabb()
+        }
+        else {
+            self.func.tool_aabb().expanded_by(self.falloff)
+        }
+    }
+
+    fn weight(&self) -> f32 {
+        self.func.weight()
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        self.func.is_concave()
+    }
+}
+
+#[test]
+fn normalize_test() {
+    use crate::tool::{ Tool, Sphere };
+    use glam::{ vec3, Quat };
+
+    assert!(Sphere.is_exact_distance());
+    assert!(Tool::new(Sphere).rotated(Quat::from_rotation_x(1.0)).is_exact_distance());
+    assert!(!Tool::new(Sphere).scaled(Vec3::splat(2.0)).is_exact_distance());
+
+    let normalized = Normalize::new(Sphere, 0.5);
+    assert_eq!(normalized.value(Vec3::ZERO), 1.0);
+    assert_eq!(normalized.value(vec3(1.25,0.0,0.0)), -0.5);
+    assert_eq!(normalized.value(vec3(1.75,0.0,0.0)), -1.0);
+    assert_eq!(normalized.aoe_aabb(), AABB::from_radius(Vec3::ZERO, 1.5));
+
+    // Past the Sphere's own falloff, its values saturate and the band ends
+    let wide = Normalize::new(Sphere, 4.0);
+    assert_eq!(wide.value(vec3(0.0,0.5,0.0)), 0.125);
+    assert_eq!(wide.value(vec3(0.0,1.5,0.0)), -0.125);
+    assert_eq!(wide.value(vec3(0.0,3.0,0.0)), -1.0);
+    assert_eq!(wide.gradient(vec3(0.0,3.0,0.0)), Vec3::ZERO);
+    let aoe = wide.aoe_aabb();
+    assert_eq!(aoe, AABB::from_radius(Vec3::ZERO, 2.0));
+    for pos in [vec3(1.9,0.0,0.0), vec3(0.0,-1.5,1.2), vec3(2.5,2.5,0.0), vec3(0.0,0.0,-5.0)] {
+        assert_eq!(aoe.contains(pos), wide.value(pos) > -1.0, "{pos}");
+        let x8 = wide.value_x8(Vec3x8::from_points([pos; 8])).to_array();
+        assert_eq!(x8, [wide.value(pos); 8]);
+    }
+}
@@ -0,0 +1,113 @@
+use glam::{ Vec3, Affine3A };
+
+use crate::tool::AABB;
+
+/// Oriented Bounding Box
+/// 
+/// Used to keep bounds tight for rotated [Tool](super::Tool)s, where the
+/// world-space [AABB] of the rotated corners can cover far more space than
+/// the tool itself.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OBB {
+    pub center: Vec3,
+    /// The unit-length local axes of the box.
+    pub axes: [Vec3; 3],
+    /// Half of the box's edge length along each of its axes.
+    pub half_extents: Vec3,
+}
+
+impl OBB {
+    /// Create an OBB that encloses `aabb` after it has been transformed by
+    /// `transform`.
+    pub fn from_transformed_aabb(aabb: AABB, transform: Affine3A) -> Self {
+        let cols = [
+            Vec3::from(transform.matrix3.x_axis),
+            Vec3::from(transform.matrix3.y_axis),
+            Vec3::from(transform.matrix3.z_axis),
+        ];
+        let half_size = aabb.size / 2.0;
+
+        Self {
+            center: transform.transform_point3(aabb.center()),
+            axes: cols.map(|col| col.normalize_or_zero()),
+            half_extents: Vec3::new(
+                cols[0].length() * half_size.x,
+                cols[1].length() * half_size.y,
+                cols[2].length() * half_size.z,
+            ),
+        }
+    }
+
+    /// Returns true if `point` lies within the OBB.
+    pub fn contains(&self, point: Vec3) -> bool {
+        const EPSILON: f32 = 0.0001;
+        let local = point - self.center;
+        self.axes.iter()
+            .zip(self.half_extents.to_array())
+            .all(|(axis, extent)| local.dot(*axis).abs() <= extent + EPSILON)
+    }
+
+    /// Returns true if every corner of `aabb` lies within the OBB.
+    pub fn contains_aabb(&self, aabb: AABB) -> bool {
+        aabb.calculate_corners().into_iter().all(|corner| self.contains(corner))
+    }
+
+    /// Returns true if the OBB overlaps `aabb`, using the separating axis
+    /// test. Boxes that only touch are considered to be intersecting.
+    pub fn intersects_aabb(&self, aabb: AABB) -> bool {
+        const EPSILON: f32 = 0.000001;
+        let aabb_axes = [Vec3::X, Vec3::Y, Vec3::Z];
+        let aabb_half = aabb.size / 2.0;
+        let offset = aabb.center() - self.center;
+
+        let separated_on = |axis: Vec3| -> bool {
+            if axis.length_squared() < EPSILON {
+                // Parallel edges produce a degenerate cross product
+                return false;
+            }
+            let obb_radius: f32 = self.axes.iter()
+                .zip(self.half_extents.to_array())
+                .map(|(a, extent)| a.dot(axis).abs() * extent)
+                .sum();
+            let aabb_radius = axis.abs().dot(aabb_half);
+            offset.dot(axis).abs() > obb_radius + aabb_radius
+        };
+
+        if aabb_axes.into_iter().chain(self.axes).any(separated_on) {
+            return false;
+        }
+
+        !aabb_axes.into_iter().any(|a| {
+            self.axes.into_iter().any(|b| separated_on(a.cross(b)))
+        })
+    }
+
+    /// Returns true if the OBB overlaps `aabb` without fully containing it,
+    /// meaning the surface of the OBB passes through the AABB.
+    pub fn straddles_aabb(&self, aabb: AABB) -> bool {
+        self.intersects_aabb(aabb) && !self.contains_aabb(aabb)
+    }
+}
+
+#[test]
+fn obb_test() {
+    use glam::{ vec3, Quat };
+
+    // A long, thin box rotated 45 degrees about Z
+    let local = AABB::from_extents(Vec3::ZERO, vec3(10.0,1.0,1.0));
+    let transform = Affine3A::from_quat(Quat::from_rotation_z(45f32.to_radians()));
+    let obb = OBB::from_transformed_aabb(local, transform);
+
+    assert!(obb.half_extents.abs_diff_eq(vec3(5.0,0.5,0.5), 0.0001));
+
+    // The world AABB covers the corners of the diagonal, but the OBB doesn't
+    let corner = AABB::from_radius(vec3(3.0,-3.0,0.0), 0.5);
+    assert!(local.transformed(transform).get_intersect_aabb(corner).is_some());
+    assert!(!obb.intersects_aabb(corner));
+
+    let diagonal = AABB::from_radius(vec3(2.0,2.0,0.0), 0.1);
+    assert!(obb.intersects_aabb(diagonal));
+    assert!(obb.contains_aabb(diagonal));
+    assert!(!obb.straddles_aabb(diagonal));
+    assert!(obb.straddles_aabb(AABB::from_radius(vec3(2.0,2.0,0.0), 1.0)));
+}
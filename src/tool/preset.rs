@@ -0,0 +1,28 @@
+use crate::tool::{ Tool, Action };
+
+/// A saved brush, bundling a [Tool] with the [Action] to apply it with and
+/// the maximum depth to subdivide to.
+/// 
+/// With the `serde` feature enabled, presets can be saved to and loaded
+/// from disk to build brush libraries or replay strokes.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "F: serde::Serialize",
+    deserialize = "F: crate::tool::ToolFunc + serde::Deserialize<'de>",
+)))]
+pub struct BrushPreset<F> {
+    pub tool: Tool<F>,
+    pub action: Action,
+    pub max_depth: u8,
+}
+
+impl<F> BrushPreset<F> {
+    pub fn new(tool: Tool<F>, action: Action, max_depth: u8) -> Self {
+        Self {
+            tool,
+            action,
+            max_depth,
+        }
+    }
+}
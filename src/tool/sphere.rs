@@ -1,11 +1,16 @@
 use glam::Vec3;
+use wide::f32x8;
 
-use crate::tool::{ ToolFunc, AABB };
+use crate::{
+    tool::{ ToolFunc, AABB, BoundingSphere },
+    utils::Vec3x8,
+};
 
 /// A ToolFunc that represents a Sphere of radius 1.0.
 /// For Spheres of different radiuses, use [Tool](super::Tool) with
 /// a scaled Transform.
 #[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sphere;
 
 impl ToolFunc for Sphere {
@@ -13,6 +18,24 @@ impl ToolFunc for Sphere {
         (1.0 - pos.length()).clamp(-1.0,1.0)
     }
 
+    #[inline]
+    fn value8(&self, pos: [Vec3; 8]) -> [f32; 8] {
+        self.value_x8(Vec3x8::from_points(pos)).to_array()
+    }
+
+    #[inline]
+    fn value_x8(&self, pos: Vec3x8) -> f32x8 {
+        (f32x8::ONE - pos.length()).fast_max(f32x8::splat(-1.0)).fast_min(f32x8::ONE)
+    }
+
+    fn gradient(&self, pos: Vec3) -> Vec3 {
+        let length = pos.length();
+        if length == 0.0 || length >= 2.0 {
+            return Vec3::ZERO;
+        }
+        -pos / length
+    }
+
     fn tool_aabb(&self) -> AABB {
         AABB::from_radius(Vec3::ZERO, 1.0) 
     }
@@ -21,8 +44,45 @@ impl ToolFunc for Sphere {
         AABB::from_radius(Vec3::ZERO, 2.0)
     }
 
+    fn bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::new(Vec3::ZERO, 1.0)
+    }
+
+    fn aoe_bounding_sphere(&self) -> BoundingSphere {
+        BoundingSphere::new(Vec3::ZERO, 2.0)
+    }
+
+    #[inline(always)]
+    fn is_exact_distance(&self) -> bool {
+        true
+    }
+
     #[inline(always)]
     fn is_concave(&self) -> bool {
         false
     }
-}
\ No newline at end of file
+}
+#[test]
+fn sphere_value8_test() {
+    use crate::utils::Rng;
+
+    // The lanes must give exactly the same values as the scalar path
+    let mut rng = Rng::new(3);
+    for _ in 0..64 {
+        let points: [Vec3; 8] = std::array::from_fn(|_| Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * 6.0 - 3.0);
+        assert_eq!(Sphere.value8(points), points.map(|p| Sphere.value(p)));
+    }
+}
+
+#[test]
+#[ignore]
+fn sphere_value8_bench() {
+    use crate::utils::time_test;
+
+    let cells: Vec<[Vec3; 8]> = (0..4096)
+        .map(|index| AABB::from_radius(Vec3::splat(index as f32 * 0.001), 0.5).calculate_corners())
+        .collect();
+    let lanes = time_test!((0..200).map(|_| cells.iter().map(|&cell| Sphere.value8(cell).iter().sum::<f32>()).sum::<f32>()).sum::<f32>(), "Sphere value8");
+    let scalar = time_test!((0..200).map(|_| cells.iter().map(|&cell| cell.map(|p| Sphere.value(p)).iter().sum::<f32>()).sum::<f32>()).sum::<f32>(), "Sphere value");
+    assert_eq!(lanes, scalar);
+}
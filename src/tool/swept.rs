@@ -0,0 +1,123 @@
+use glam::{ Vec3, Affine3A };
+
+use crate::tool::{ Tool, ToolFunc, AABB };
+
+/// A ToolFunc that represents another ToolFunc swept between two
+/// transforms.
+/// 
+/// The sweep is approximated by the union of evenly spaced stamps of the
+/// tool along the interpolated transform. Stamps are spaced closely enough
+/// that they overlap, so fast brush strokes produce a continuous trail
+/// instead of separate dots.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(
+    serialize = "F: serde::Serialize",
+    deserialize = "F: ToolFunc + serde::Deserialize<'de>",
+)))]
+pub struct Swept<F> {
+    stamps: Vec<Tool<F>>,
+    tool_aabb: AABB,
+    aoe_aabb: AABB,
+    concave: bool,
+}
+
+impl<F: ToolFunc + Clone> Swept<F> {
+    pub fn new(func: F, from: Affine3A, to: Affine3A) -> Self {
+        let start = Tool::new(func.clone()).transformed(from);
+        let end = Tool::new(func.clone()).transformed(to);
+
+        let (from_scale, from_rot, from_trans) = from.to_scale_rotation_translation();
+        let (to_scale, to_rot, to_trans) = to.to_scale_rotation_translation();
+
+        // Space the stamps at half of the smallest extent of the tool, so
+        // that neighbouring stamps always overlap. No point of the tool
+        // moves further than the translation, plus the arc swept by the
+        // rotation and the growth from the scale at the tool's furthest
+        // point from its origin.
+        let spacing = start.tool_aabb().size.min_element()
+            .min(end.tool_aabb().size.min_element()) * 0.5;
+        let local = func.tool_aabb();
+        let radius = local.start.abs().max(local.end().abs()).length();
+        let distance = (to_trans - from_trans).length() +
+            from_rot.angle_between(to_rot) * radius * from_scale.abs().max(to_scale.abs()).max_element() +
+            (to_scale - from_scale).abs().max_element() * radius;
+        let steps = if spacing > 0.0 { (distance / spacing).ceil() as usize } else { 0 }.max(1);
+
+        let stamps: Vec<Tool<F>> = (0..=steps).map(|step| {
+            let t = step as f32 / steps as f32;
+            let transform = Affine3A::from_scale_rotation_translation(
+                from_scale.lerp(to_scale, t),
+                from_rot.slerp(to_rot, t),
+                from_trans.lerp(to_trans, t),
+            );
+            Tool::new(func.clone()).transformed(transform)
+        }).collect();
+
+        let tool_aabb = stamps.iter().map(|stamp| stamp.tool_aabb()).reduce(|a, b| a.union(b)).unwrap();
+        let aoe_aabb = stamps.iter().map(|stamp| stamp.aoe_aabb()).reduce(|a, b| a.union(b)).unwrap();
+
+        Self {
+            stamps,
+            tool_aabb,
+            aoe_aabb,
+            concave: func.is_concave() || from.matrix3 != to.matrix3,
+        }
+    }
+}
+
+impl<F: ToolFunc> ToolFunc for Swept<F> {
+    fn value(&self, pos: Vec3) -> f32 {
+        self.stamps.iter()
+            .map(|stamp| stamp.value(pos))
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        self.tool_aabb
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        self.aoe_aabb
+    }
+
+    fn weight(&self) -> f32 {
+        self.stamps[0].weight()
+    }
+
+    /// A sweep that rotates or scales its tool can curve, so it is only
+    /// treated as convex if the tool is convex and purely translated.
+    fn is_concave(&self) -> bool {
+        self.concave
+    }
+}
+
+#[test]
+fn swept_test() {
+    use crate::tool::Sphere;
+    use glam::{ vec3, vec3a, Quat };
+
+    let from = Affine3A::from_translation(vec3(0.0,0.0,0.0));
+    let to = Affine3A::from_translation(vec3(10.0,0.0,0.0));
+    let swept = Swept::new(Sphere, from, to);
+
+    // Every point along the stroke is inside the sweep
+    (0..=20).map(|x| vec3(x as f32 * 0.5, 0.0, 0.0)).for_each(|pos| {
+        assert!(swept.value(pos) > 0.0, "{} is outside of the sweep", pos);
+    });
+    assert!(swept.value(vec3(5.0,2.0,0.0)) < 0.0);
+    assert_eq!(swept.tool_aabb(), AABB { start: Vec3::splat(-1.0), size: vec3(12.0,2.0,2.0) });
+    assert!(swept.is_convex());
+
+    // Rotating or scaling in place still leaves no gaps
+    let arm = Tool::new(Sphere).scaled(Vec3::splat(0.5)).translated(vec3a(4.0,0.0,0.0));
+    let turned = Swept::new(arm, Affine3A::IDENTITY, Affine3A::from_quat(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)));
+    (0..=20).map(|step| step as f32 / 20.0 * std::f32::consts::FRAC_PI_2).for_each(|angle| {
+        let pos = vec3(angle.cos(), angle.sin(), 0.0) * 4.0;
+        assert!(turned.value(pos) > 0.0, "{} is outside of the sweep", pos);
+    });
+    let grown = Swept::new(arm, Affine3A::IDENTITY, Affine3A::from_scale(Vec3::splat(3.0)));
+    (0..=20).map(|step| vec3(4.0 + step as f32 * 0.4, 0.0, 0.0)).for_each(|pos| {
+        assert!(grown.value(pos) > 0.0, "{} is outside of the sweep", pos);
+    });
+}
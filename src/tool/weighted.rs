@@ -0,0 +1,101 @@
+use glam::Vec3;
+use wide::f32x8;
+
+use crate::{
+    tool::{ ToolFunc, AABB, BoundingSphere },
+    utils::Vec3x8,
+};
+
+/// A ToolFunc wrapper that applies another ToolFunc with a `weight` from
+/// 0.0 to 1.0, like the opacity of a brush.
+/// 
+/// The values of the ToolFunc are unchanged. Instead, each Terrain value
+/// only moves `weight` of the way towards the value the [Action](crate::tool::Action)
+/// would give it, so weights below 1.0 add or remove less volume. This is
+/// useful for soft brush dabs or pressure-sensitive input.
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Weighted<F> {
+    pub func: F,
+    pub weight: f32,
+}
+
+impl<F> Weighted<F> {
+    pub fn new(func: F, weight: f32) -> Self {
+        Self {
+            func,
+            weight,
+        }
+    }
+}
+
+impl<F: ToolFunc> ToolFunc for Weighted<F> {
+    fn value(&self, pos: Vec3) -> f32 {
+        self.func.value(pos)
+    }
+
+    fn value8(&self, pos: [Vec3; 8]) -> [f32; 8] {
+        self.func.value8(pos)
+    }
+
+    fn value_x8(&self, pos: Vec3x8) -> f32x8 {
+        self.func.value_x8(pos)
+    }
+
+    fn gradient(&self, pos: Vec3) -> Vec3 {
+        self.func.gradient(pos)
+    }
+
+    fn tool_aabb(&self) -> AABB {
+        self.func.tool_aabb()
+    }
+
+    fn aoe_aabb(&self) -> AABB {
+        self.func.aoe_aabb()
+    }
+
+    fn bounding_sphere(&self) -> BoundingSphere {
+        self.func.bounding_sphere()
+    }
+
+    fn aoe_bounding_sphere(&self) -> BoundingSphere {
+        self.func.aoe_bounding_sphere()
+    }
+
+    fn weight(&self) -> f32 {
+        self.weight.clamp(0.0, 1.0) * self.func.weight()
+    }
+
+    #[inline(always)]
+    fn is_concave(&self) -> bool {
+        self.func.is_concave()
+    }
+}
+
+#[test]
+fn weighted_test() {
+    use crate::{ naive_octree::NaiveOctree, tool::{ Tool, Sphere, Action } };
+    use glam::{ vec3, Vec3A };
+
+    let tool = Tool::new(Weighted::new(Sphere, 0.5)).scaled(Vec3::splat(2.0));
+    assert_eq!(tool.value(Vec3::ZERO), 1.0);
+    assert_eq!(tool.weight(), 0.5);
+    assert_eq!(tool.tool_aabb(), Tool::new(Sphere).scaled(Vec3::splat(2.0)).tool_aabb());
+
+    // Only half of the way from empty to full
+    let mut value = -1.0;
+    Action::Place.apply_weighted(&mut value, 1.0, tool.weight());
+    assert_eq!(value, 0.0);
+
+    // A light dab adds less than a full one
+    let dab = |weight: f32| {
+        let mut terrain = NaiveOctree::new(16.0);
+        terrain.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(4.0)).translated(Vec3A::splat(8.0)), Action::Place, 5);
+        terrain.apply_tool(Tool::new(Weighted::new(Sphere, weight)).scaled(Vec3::splat(4.0)).translated(Vec3A::new(11.0, 8.0, 8.0)), Action::Place, 5);
+        terrain
+    };
+    let (light, full) = (dab(0.05), dab(1.0));
+    assert!(light.sample(vec3(14.0, 8.0, 8.0)) < 0.0);
+    assert!(full.sample(vec3(14.0, 8.0, 8.0)) > 0.0);
+    assert!(light.generate_mesh(5).surface_area() < full.generate_mesh(5).surface_area());
+}
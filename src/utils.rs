@@ -1,5 +1,6 @@
 use lerp::Lerp;
-use glam::Vec3;
+use glam::{ Vec3, Vec3A, IVec3, Affine3A };
+use wide::f32x8;
 use arrayvec::ArrayVec;
 
 /// Splits a cube into 8 cubes, while interpolating corner values
@@ -47,18 +48,58 @@ use arrayvec::ArrayVec;
 /// Note: Gap between cubes is exaggerated. In practice, 
 /// adjacent points are the same.
 ///```
-pub fn subdivide_cell(cell: &[f32; 8]) -> [[f32; 8]; 8] {
+pub fn subdivide_cell<T: Lerp<f32> + Copy + Default>(cell: &[T; 8]) -> [[T; 8]; 8] {
+        let points = subdivide_cell_into_grid(cell);
+
+        let make_cell = |start_index: usize| -> [T; 8] {
+                [
+                        points[start_index  ],
+                        points[start_index+1],
+                        points[start_index+3],
+                        points[start_index+4],
+                        points[start_index+9],
+                        points[start_index+10],
+                        points[start_index+12],
+                        points[start_index+13],
+                ]
+        };
+
+        // Split the points into 8 cubes and return
+        [
+                make_cell(0),
+                make_cell(1),
+                make_cell(3),
+                make_cell(4),
+                make_cell(9),
+                make_cell(10),
+                make_cell(12),
+                make_cell(13),
+        ]
+}
+
+/// Same as [`subdivide_cell`] for corner positions, but interpolates
+/// using SIMD vectors.
+pub fn subdivide_cell_positions(cell: &[Vec3; 8]) -> [[Vec3; 8]; 8] {
+        subdivide_cell(&cell.map(Vec3A::from)).map(|cell| cell.map(Vec3::from))
+}
+
+/// Interpolates the 8 corners of a cube into a 3x3x3 grid of 27 points.
+/// This works for both corner values and corner positions.
+/// 
+/// The points are indexed from the bottom-left-back point to the
+/// top-right-front point, counting in order of X, then Y, then Z, so
+/// the point at grid position `(x, y, z)` is at index `x + y*3 + z*9`.
+/// The original corners end up at indices 0, 2, 6, 8, 18, 20, 24 and 26.
+/// 
+/// See also: [`subdivide_cell`]
+pub fn subdivide_cell_into_grid<T: Lerp<f32> + Copy + Default>(cell: &[T; 8]) -> [T; 27] {
         // Construct 19 new points, for a total
         // of 27 points
         // 
-        // The points are indexed from the bottom-left-back point to
-        // the top-right-front point, counting in order of X, then Y,
-        // then Z.
-        // 
         // E.G. bottom-left-back is 0, bottom-middle-back is 1, bottom-
         // right-back is 2, middle-left-back is 3, middle-middle-back is 4,
         // etc.
-        let mut points = [0.0;27];
+        let mut points = [T::default(); 27];
 
         // First, copy the base points
         //      24-----------------26
@@ -149,30 +190,7 @@ pub fn subdivide_cell(cell: &[f32; 8]) -> [[f32; 8]; 8] {
         // Total points: 27
         points[13] = points[4].lerp(points[22], 0.5);
 
-        let make_cell = |start_index: usize| -> [f32; 8] {
-                [
-                        points[start_index  ],
-                        points[start_index+1],
-                        points[start_index+3],
-                        points[start_index+4],
-                        points[start_index+9],
-                        points[start_index+10],
-                        points[start_index+12],
-                        points[start_index+13],
-                ]
-        };
-
-        // Split the points into 8 cubes and return
-        [
-                make_cell(0),
-                make_cell(1),
-                make_cell(3),
-                make_cell(4),
-                make_cell(9),
-                make_cell(10),
-                make_cell(12),
-                make_cell(13),
-        ]
+        points
 }
 
 pub enum LineDir {
@@ -222,7 +240,7 @@ pub fn line_vertices(pos: Vec3, length: f32, scale: f32, line_dir: LineDir) -> [
 
         CUBE_INDICES.chunks_exact(3).for_each(|idx| verts.push([cube_verts[idx[0]], cube_verts[idx[1]], cube_verts[idx[2]]]));
 
-        return verts.into_inner().unwrap();
+        verts.into_inner().unwrap()
 }
 
 #[allow(unused_macros)]
@@ -238,4 +256,225 @@ macro_rules! time_test {
         }}
 }
 #[allow(unused_imports)]
-pub(crate) use time_test;
\ No newline at end of file
+pub(crate) use time_test;
+
+/// Trilinearly interpolates the 8 corner values of a cell at `t`, where
+/// each component of `t` ranges from 0.0 to 1.0 across the cell.
+pub fn trilinear(values: &[f32; 8], t: Vec3) -> f32 {
+        let x = [
+                values[0].lerp(values[1], t.x),
+                values[2].lerp(values[3], t.x),
+                values[4].lerp(values[5], t.x),
+                values[6].lerp(values[7], t.x),
+        ];
+        let y = [
+                x[0].lerp(x[1], t.y),
+                x[2].lerp(x[3], t.y),
+        ];
+        y[0].lerp(y[1], t.z)
+}
+
+/// 8 points with their components split into SIMD lanes, so the corners
+/// of a cell can be evaluated with one instruction per operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vec3x8 {
+        pub x: f32x8,
+        pub y: f32x8,
+        pub z: f32x8,
+}
+
+impl Vec3x8 {
+        pub fn from_points(points: [Vec3; 8]) -> Self {
+                let (mut x, mut y, mut z) = ([0.0; 8], [0.0; 8], [0.0; 8]);
+                for (lane, point) in points.iter().enumerate() {
+                        x[lane] = point.x;
+                        y[lane] = point.y;
+                        z[lane] = point.z;
+                }
+                Self { x: f32x8::from(x), y: f32x8::from(y), z: f32x8::from(z) }
+        }
+
+        pub fn to_points(self) -> [Vec3; 8] {
+                let (x, y, z) = (self.x.to_array(), self.y.to_array(), self.z.to_array());
+                std::array::from_fn(|lane| Vec3::new(x[lane], y[lane], z[lane]))
+        }
+
+        /// Returns the length of each point.
+        pub fn length(self) -> f32x8 {
+                (self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+        }
+
+        /// Returns each point with its components made non-negative.
+        pub fn abs(self) -> Self {
+                Self { x: self.x.abs(), y: self.y.abs(), z: self.z.abs() }
+        }
+
+        /// Transforms every point by `transform`, in the same order of
+        /// operations as [`Affine3A::transform_point3`].
+        pub fn transform(self, transform: &Affine3A) -> Self {
+                let axis = |component: fn(Vec3A) -> f32| {
+                        let matrix = transform.matrix3;
+                        self.x * f32x8::splat(component(matrix.x_axis)) +
+                                self.y * f32x8::splat(component(matrix.y_axis)) +
+                                self.z * f32x8::splat(component(matrix.z_axis)) +
+                                f32x8::splat(component(transform.translation))
+                };
+                Self { x: axis(|v| v.x), y: axis(|v| v.y), z: axis(|v| v.z) }
+        }
+}
+
+/// Subtracts the offset from every point.
+impl std::ops::Sub<Vec3> for Vec3x8 {
+        type Output = Self;
+
+        fn sub(self, offset: Vec3) -> Self {
+                Self {
+                        x: self.x - f32x8::splat(offset.x),
+                        y: self.y - f32x8::splat(offset.y),
+                        z: self.z - f32x8::splat(offset.z),
+                }
+        }
+}
+
+/// Returns the offsets from a grid point to itself and its 26 neighbours.
+pub fn neighborhood() -> impl Iterator<Item = IVec3> {
+        (-1..=1).flat_map(|z| (-1..=1).flat_map(move |y| (-1..=1).map(move |x| IVec3::new(x, y, z))))
+}
+
+/// A small, fast random number generator (SplitMix64).
+/// 
+/// This produces the same sequence on every platform for the same seed,
+/// so it can be used for deterministic procedural generation. It is not
+/// suitable for cryptography.
+#[derive(Debug, Clone)]
+pub struct Rng {
+        state: u64,
+}
+
+impl Rng {
+        pub fn new(seed: u64) -> Self {
+                Self { state: seed }
+        }
+
+        /// Creates a generator for a position, so that values generated
+        /// for a point don't depend on the order points are visited in.
+        pub fn for_position(seed: u64, pos: Vec3) -> Self {
+                let mut rng = Self::new(seed);
+                for component in pos.to_array() {
+                        // Adding zero turns -0.0 into 0.0, so both give the same values
+                        rng.state ^= (component + 0.0).to_bits() as u64;
+                        rng.next_u64();
+                }
+                rng
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+                self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+                let mut z = self.state;
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+                z ^ (z >> 31)
+        }
+
+        /// Returns a value in the range `[0.0, 1.0)`.
+        pub fn next_f32(&mut self) -> f32 {
+                (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+        }
+}
+
+/// A seed for procedural generation.
+/// 
+/// Each procedural feature takes its own Seed, which can be derived from
+/// one world seed with [`derive`](Self::derive). The values generated from
+/// a Seed only depend on the Seed and world positions, never on the order
+/// of hash maps or on the platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct Seed(pub u64);
+
+impl Seed {
+        /// Returns an independent Seed for `stream`, such as the index of a
+        /// generation stage or of a noise octave.
+        pub fn derive(self, stream: u64) -> Seed {
+                Seed(Rng::new(self.0 ^ Rng::new(stream).next_u64()).next_u64())
+        }
+
+        pub fn rng(self) -> Rng {
+                Rng::new(self.0)
+        }
+
+        /// See [`Rng::for_position`].
+        pub fn rng_at(self, pos: Vec3) -> Rng {
+                Rng::for_position(self.0, pos)
+        }
+}
+
+impl From<u64> for Seed {
+        fn from(seed: u64) -> Self {
+                Seed(seed)
+        }
+}
+
+/// Adds to one of the [stats](crate::stats) counters when the `stats`
+/// feature is enabled, and does nothing otherwise.
+macro_rules! stat {
+        ($counter:ident, $amount:expr) => {{
+                #[cfg(feature = "stats")]
+                crate::stats::$counter.fetch_add($amount as u64, std::sync::atomic::Ordering::Relaxed);
+        }}
+}
+pub(crate) use stat;
+
+/// Enters a `tracing` span until the end of the enclosing scope when the
+/// `tracing` feature is enabled, and does nothing otherwise.
+macro_rules! span {
+        ($($args:tt)*) => {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::info_span!($($args)*).entered();
+        }
+}
+pub(crate) use span;
+
+/// Records a field of the current `tracing` span. `$value` is only
+/// evaluated if the span is enabled.
+macro_rules! span_record {
+        ($field:literal, $value:expr) => {{
+                #[cfg(feature = "tracing")]
+                {
+                        let span = tracing::Span::current();
+                        if !span.is_disabled() {
+                                span.record($field, $value);
+                        }
+                }
+        }}
+}
+pub(crate) use span_record;
+
+#[test]
+fn subdivide_cell_into_grid_test() {
+        let cell = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+        let grid = subdivide_cell_into_grid(&cell);
+
+        // Original corners are preserved
+        [0, 2, 6, 8, 18, 20, 24, 26].into_iter().zip(cell).for_each(|(index, value)| {
+                assert_eq!(grid[index], value);
+        });
+        // Centre is the average of all corners
+        assert_eq!(grid[13], 3.5);
+
+        // Cells produced by subdivide_cell are sampled from the same grid
+        let cells = subdivide_cell(&cell);
+        assert_eq!(cells[0][7], grid[13]);
+        assert_eq!(cells[7][0], grid[13]);
+        assert_eq!(cells[7][7], grid[26]);
+}
+
+#[test]
+fn seed_test() {
+        let seed = Seed(42);
+        assert_eq!(seed.derive(3), Seed(42).derive(3));
+        assert_ne!(seed.derive(3), seed.derive(4));
+        assert_ne!(seed.derive(0), seed);
+        assert_eq!(seed.rng().next_u64(), Rng::new(42).next_u64());
+        assert_eq!(seed.rng_at(Vec3::new(-0.0, 1.0, 0.0)).next_u64(), seed.rng_at(Vec3::Y).next_u64());
+}
@@ -0,0 +1,141 @@
+use crate::{
+    naive_octree::NaiveOctree,
+    tool::{ Tool, ToolFunc, Action },
+    ChunkKey, ChunkedTerrain, UnindexedMesh,
+};
+use std::{
+    collections::{ BTreeMap, BTreeSet },
+    sync::mpsc::{ self, Sender, Receiver, TryRecvError },
+    thread::{ self, JoinHandle },
+};
+#[cfg(feature = "multi-thread")]
+use rayon::prelude::*;
+
+enum WorkerMessage<F: ToolFunc> {
+    Apply(Box<Tool<F>>, Action, u8),
+    Stop,
+}
+
+/// Owns a [ChunkedTerrain] on a background thread, applying queued edits
+/// and sending back the regenerated mesh of each chunk they changed.
+///
+/// Edits that are queued while the worker is busy are applied together,
+/// and then each chunk they changed is meshed once, so a slow consumer
+/// only receives the latest state of the chunks that changed instead of
+/// a backlog of meshes. Chunks no edit touched are never remeshed. With
+/// the `multi-thread` feature, the chunks are meshed in parallel.
+pub struct TerrainWorker<F: ToolFunc> {
+    edits: Sender<WorkerMessage<F>>,
+    meshes: Receiver<(ChunkKey, UnindexedMesh)>,
+    handle: Option<JoinHandle<ChunkedTerrain>>,
+}
+
+impl<F: ToolFunc + Send + 'static> TerrainWorker<F> {
+    /// Moves `terrain` to a new thread. Meshes are generated up to
+    /// `mesh_depth`.
+    pub fn spawn(mut terrain: ChunkedTerrain, mesh_depth: u8) -> Self {
+        let (edits, edit_receiver) = mpsc::channel::<WorkerMessage<F>>();
+        let (mesh_sender, meshes) = mpsc::channel();
+
+        let handle = thread::spawn(move || {
+            while let Ok(message) = edit_receiver.recv() {
+                let mut stopping = false;
+                let mut dirty = BTreeSet::new();
+                let mut next = Some(message);
+                // Apply everything that is queued before meshing
+                while let Some(message) = next.take() {
+                    match message {
+                        WorkerMessage::Apply(tool, action, max_depth) => dirty.extend(terrain.apply_tool(&*tool, action, max_depth)),
+                        WorkerMessage::Stop => stopping = true,
+                    }
+                    next = match edit_receiver.try_recv() {
+                        Ok(message) => Some(message),
+                        Err(TryRecvError::Empty) => None,
+                        Err(TryRecvError::Disconnected) => { stopping = true; None },
+                    };
+                }
+
+                if stopping {
+                    break;
+                }
+
+                let chunks: Vec<(ChunkKey, &NaiveOctree)> = dirty.into_iter()
+                    .filter_map(|key| terrain.chunk(key).map(|chunk| (key, chunk)))
+                    .collect();
+                #[cfg(feature = "multi-thread")]
+                let chunks = chunks.into_par_iter();
+                #[cfg(not(feature = "multi-thread"))]
+                let chunks = chunks.into_iter();
+                let meshes: Vec<_> = chunks.map(|(key, chunk)| (key, chunk.generate_mesh(mesh_depth))).collect();
+
+                if meshes.into_iter().any(|mesh| mesh_sender.send(mesh).is_err()) {
+                    break;
+                }
+            }
+            terrain
+        });
+
+        Self {
+            edits,
+            meshes,
+            handle: Some(handle),
+        }
+    }
+
+    /// Queues the [Tool] to be applied with the given [Action].
+    pub fn apply_tool(&self, tool: Tool<F>, action: Action, max_depth: u8) {
+        // The worker only stops when asked to, so this can't fail
+        let _ = self.edits.send(WorkerMessage::Apply(Box::new(tool), action, max_depth));
+    }
+
+    /// Returns the meshes that finished since the last call, sorted by
+    /// key. Only the most recent mesh of each chunk is returned.
+    pub fn try_recv_meshes(&self) -> Vec<(ChunkKey, UnindexedMesh)> {
+        self.meshes.try_iter().collect::<BTreeMap<_, _>>().into_iter().collect()
+    }
+
+    /// Blocks until the next chunk mesh is finished. Returns [None] if the
+    /// worker has stopped.
+    pub fn recv_mesh(&self) -> Option<(ChunkKey, UnindexedMesh)> {
+        self.meshes.recv().ok()
+    }
+
+    /// Stops the worker once the queued edits are applied and returns
+    /// the terrain.
+    pub fn finish(mut self) -> ChunkedTerrain {
+        let _ = self.edits.send(WorkerMessage::Stop);
+        self.handle.take().unwrap().join().expect("TerrainWorker thread panicked")
+    }
+}
+
+impl<F: ToolFunc> Drop for TerrainWorker<F> {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            let _ = self.edits.send(WorkerMessage::Stop);
+            let _ = handle.join();
+        }
+    }
+}
+
+#[test]
+fn worker_test() {
+    use crate::tool::Sphere;
+    use glam::{ Vec3, Vec3A };
+
+    let worker = TerrainWorker::spawn(ChunkedTerrain::new(1.0), 255);
+    worker.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(0.3)).translated(Vec3A::splat(0.5)), Action::Place, 3);
+
+    let (key, mesh) = worker.recv_mesh().unwrap();
+    assert_eq!(key, ChunkKey(0, 0, 0));
+    assert!(!mesh.faces.is_empty());
+
+    // Only the chunk the next edit changes is remeshed
+    worker.apply_tool(Tool::new(Sphere).scaled(Vec3::splat(0.3)).translated(Vec3A::new(2.5, 0.5, 0.5)), Action::Place, 3);
+    let (other_key, other_mesh) = worker.recv_mesh().unwrap();
+    assert_eq!(other_key, ChunkKey(2, 0, 0));
+    assert!(worker.try_recv_meshes().is_empty());
+
+    let terrain = worker.finish();
+    assert_eq!(terrain.generate_mesh(key, 255).unwrap().faces, mesh.faces);
+    assert_eq!(terrain.generate_mesh(other_key, 255).unwrap().faces, other_mesh.faces);
+}